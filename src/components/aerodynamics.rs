@@ -0,0 +1,40 @@
+use bevy::prelude::Component;
+
+/// How a vessel flies through an atmosphere and how much punishment it takes
+#[derive(Component, Clone, Debug)]
+pub struct Aerodynamics {
+    /// Dimensionless drag coefficient
+    pub drag_coefficient: f32,
+    /// Cross-section facing the airflow in m²
+    pub reference_area: f32,
+    /// Radius of the blunt nose or heat shield in meters, smaller noses heat up faster
+    pub nose_radius: f32,
+    /// Seconds the hull takes to close most of the gap to its equilibrium temperature
+    pub thermal_time_constant: f32,
+    /// Hull temperature in Kelvin the vessel breaks up above
+    pub max_temperature: f32,
+    /// Non-gravitational acceleration in g the vessel breaks up above
+    pub max_g_load: f32,
+    /// Current hull temperature in Kelvin
+    pub temperature: f32,
+    /// Heat flux of the last step in W/m²
+    pub heat_flux: f32,
+    /// Non-gravitational acceleration of the last step in g
+    pub g_load: f32,
+}
+
+impl Default for Aerodynamics {
+    fn default() -> Self {
+        Self {
+            drag_coefficient: 1.2,
+            reference_area: 200.0,
+            nose_radius: 8.0,
+            thermal_time_constant: 20.0,
+            max_temperature: 1200.0,
+            max_g_load: 5.5,
+            temperature: 290.0,
+            heat_flux: 0.0,
+            g_load: 0.0,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use bevy::prelude::{Color, Component, Vec2};
+
+/// Shape used when building the render mesh for an object
+#[derive(Clone, Debug)]
+pub enum Shape {
+    /// Filled circle with the given radius
+    Circle(f32),
+    /// Filled triangle given by its three vertices
+    Triangle([Vec2; 3]),
+}
+
+/// Visual description of a simulated object
+///
+/// Keeps the simulation free of render assets: the presentation layer turns
+/// this into a mesh and material, while headless runs simply ignore it.
+#[derive(Component, Clone, Debug)]
+pub struct Appearance {
+    pub shape: Shape,
+    pub color: Color,
+}
+
+impl Appearance {
+    pub fn circle(radius: f32, color: Color) -> Self {
+        Self {
+            shape: Shape::Circle(radius),
+            color,
+        }
+    }
+
+    pub fn triangle(vertices: [Vec2; 3], color: Color) -> Self {
+        Self {
+            shape: Shape::Triangle(vertices),
+            color,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use bevy::prelude::Component;
+
+/// Exponential atmosphere around a celestial body
+///
+/// Heights are in simulation units, measured from the body's surface.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Atmosphere {
+    /// Air density at the surface in kg/m³
+    pub sea_level_density: f32,
+    /// Height over which the density falls by a factor of e, in meters
+    pub scale_height: f32,
+    /// Altitude above which the atmosphere is treated as vacuum, in meters
+    pub height: f32,
+    /// Sutton-Graves constant of the gas mix, relating density and speed to heat flux
+    pub heating_coefficient: f32,
+}
+
+impl Atmosphere {
+    /// Air density in kg/m³ at `altitude` meters above the surface
+    pub fn density(&self, altitude: f32) -> f32 {
+        if altitude >= self.height {
+            return 0.;
+        }
+        self.sea_level_density * (-altitude.max(0.) / self.scale_height).exp()
+    }
+
+    /// Stagnation point heat flux in W/m² for a blunt body flying at `speed`
+    pub fn heat_flux(&self, altitude: f32, speed: f32, nose_radius: f32) -> f32 {
+        self.heating_coefficient * (self.density(altitude) / nose_radius).sqrt() * speed.powi(3)
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::{Component, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Direction SAS keeps the vessel pointed at
+///
+/// Normal and anti-normal point out of the orbital plane, which the planar
+/// simulation has no room for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AttitudeHold {
+    /// Only damp the spin
+    #[default]
+    Stability,
+    /// Along the velocity relative to the central body
+    Prograde,
+    /// Against the velocity relative to the central body
+    Retrograde,
+    /// Away from the central body
+    RadialOut,
+    /// Towards the central body
+    RadialIn,
+    /// Towards the selected [`Target`](crate::components::target::Target)
+    Target,
+}
+
+impl AttitudeHold {
+    /// Every mode that points somewhere, in key order after `Stability`
+    pub const DIRECTIONS: [AttitudeHold; 5] = [
+        AttitudeHold::Prograde,
+        AttitudeHold::Retrograde,
+        AttitudeHold::RadialOut,
+        AttitudeHold::RadialIn,
+        AttitudeHold::Target,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AttitudeHold::Stability => "stability",
+            AttitudeHold::Prograde => "prograde",
+            AttitudeHold::Retrograde => "retrograde",
+            AttitudeHold::RadialOut => "radial out",
+            AttitudeHold::RadialIn => "radial in",
+            AttitudeHold::Target => "target",
+        }
+    }
+}
+
+/// Spin and attitude control hardware of a spacecraft
+///
+/// The orientation itself stays in the vessel's `Transform`, this tracks how
+/// fast it turns and how hard the reaction wheels and RCS thrusters can push
+/// against that.
+#[derive(Component, Clone, Debug)]
+pub struct AttitudeControl {
+    /// Spin rate in rad/s, counter-clockwise positive
+    pub angular_velocity: f32,
+    /// Pilot torque command from -1.0 (clockwise) to 1.0 (counter-clockwise)
+    pub torque_input: f32,
+    /// Torque of the reaction wheels in N·m, they need no propellant
+    pub wheel_torque: f32,
+    /// Extra torque of the RCS thrusters in N·m while monopropellant lasts
+    pub rcs_torque: f32,
+    /// RCS propellant left in kg
+    pub monopropellant: f32,
+    /// RCS tank capacity in kg
+    pub max_monopropellant: f32,
+    /// Monopropellant used per second at full RCS torque, in kg/s
+    pub monopropellant_rate: f32,
+    /// Stability assist, holds `hold` whenever the pilot isn't steering
+    pub sas: bool,
+    /// What SAS holds
+    pub hold: AttitudeHold,
+    /// World direction of the hold mode, refreshed every step by the autopilot
+    pub hold_direction: Option<Vec3>,
+}
+
+impl Default for AttitudeControl {
+    fn default() -> Self {
+        Self {
+            angular_velocity: 0.0,
+            torque_input: 0.0,
+            wheel_torque: 2.0e6,
+            rcs_torque: 6.0e6,
+            monopropellant: 400.0,
+            max_monopropellant: 400.0,
+            monopropellant_rate: 1.0,
+            sas: true,
+            hold: AttitudeHold::Stability,
+            hold_direction: None,
+        }
+    }
+}
+
+impl AttitudeControl {
+    /// Torque available right now in N·m, the RCS drops out once its tank is dry
+    pub fn max_torque(&self) -> f32 {
+        if self.monopropellant > 0.0 {
+            self.wheel_torque + self.rcs_torque
+        } else {
+            self.wheel_torque
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::constants::{
+    DT_STAGES, TIME_WARP_LIMITED_MOON_ALTITUDE, TIME_WARP_LIMITED_PLANET_ALTITUDE,
+    TIME_WARP_LIMITED_STAGE, TIME_WARP_MIN_MOON_ALTITUDE, TIME_WARP_MIN_PLANET_ALTITUDE,
+};
+use bevy::prelude::{Color, Component, Entity};
+
+/// A planet or moon the vessel can orbit, land on and warp near
+///
+/// Altitudes are in simulation units, measured from the body's surface.
+#[derive(Component, Clone, Debug)]
+pub struct CelestialBody {
+    pub name: String,
+    /// Surface radius in meters
+    pub radius: f32,
+    /// Body this one orbits, `None` for planets
+    pub parent: Option<Entity>,
+    /// Altitude below which time warp is disabled, in meters
+    pub min_warp_altitude: f32,
+    /// Altitude below which time warp is capped at `TIME_WARP_LIMITED_STAGE`, in meters
+    pub limited_warp_altitude: f32,
+    pub color: Color,
+}
+
+impl CelestialBody {
+    /// A body with the default time warp limits of a planet, or of a moon when it has a parent
+    pub fn new(name: impl Into<String>, radius: f32, parent: Option<Entity>, color: Color) -> Self {
+        let (min_warp_altitude, limited_warp_altitude) = if parent.is_some() {
+            (TIME_WARP_MIN_MOON_ALTITUDE, TIME_WARP_LIMITED_MOON_ALTITUDE)
+        } else {
+            (
+                TIME_WARP_MIN_PLANET_ALTITUDE,
+                TIME_WARP_LIMITED_PLANET_ALTITUDE,
+            )
+        };
+        Self {
+            name: name.into(),
+            radius,
+            parent,
+            min_warp_altitude,
+            limited_warp_altitude,
+            color,
+        }
+    }
+
+    /// Whether the body orbits another one
+    pub fn is_moon(&self) -> bool {
+        self.parent.is_some()
+    }
+
+    /// Altitude above the surface at `distance` meters from the center
+    pub fn altitude(&self, distance: f32) -> f32 {
+        distance - self.radius
+    }
+
+    /// Highest time warp stage allowed at `altitude` meters above the surface
+    pub fn max_warp_stage(&self, altitude: f32) -> usize {
+        if altitude < self.min_warp_altitude {
+            0
+        } else if altitude < self.limited_warp_altitude {
+            TIME_WARP_LIMITED_STAGE
+        } else {
+            DT_STAGES.len() - 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADIUS: f32 = 100_000.;
+
+    #[test]
+    fn moons_get_the_moon_warp_limits() {
+        let planet = CelestialBody::new("Earth", RADIUS, None, Color::WHITE);
+        let moon = CelestialBody::new("Luna", RADIUS, Some(Entity::PLACEHOLDER), Color::WHITE);
+
+        assert!(!planet.is_moon());
+        assert_eq!(planet.min_warp_altitude, TIME_WARP_MIN_PLANET_ALTITUDE);
+        assert!(moon.is_moon());
+        assert_eq!(moon.limited_warp_altitude, TIME_WARP_LIMITED_MOON_ALTITUDE);
+    }
+
+    #[test]
+    fn warp_opens_up_with_altitude() {
+        let planet = CelestialBody::new("Earth", RADIUS, None, Color::WHITE);
+        let stage_at = |distance| planet.max_warp_stage(planet.altitude(distance));
+
+        assert_eq!(stage_at(RADIUS), 0);
+        assert_eq!(
+            stage_at(RADIUS + planet.min_warp_altitude),
+            TIME_WARP_LIMITED_STAGE
+        );
+        assert_eq!(
+            stage_at(RADIUS + planet.limited_warp_altitude),
+            DT_STAGES.len() - 1
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::prelude::Component;
+use serde::{Deserialize, Serialize};
+
+/// Why a vessel was destroyed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DestructionCause {
+    /// The hull got hotter than it can withstand
+    Overheated,
+    /// Deceleration exceeded the structural limit
+    Overstressed,
+    /// Hit the surface too fast or at too steep an angle
+    Crashed,
+}
+
+impl DestructionCause {
+    pub fn label(self) -> &'static str {
+        match self {
+            DestructionCause::Overheated => "burned up on reentry",
+            DestructionCause::Overstressed => "broke apart under g-load",
+            DestructionCause::Crashed => "crashed into the surface",
+        }
+    }
+}
+
+/// Marks a vessel that has been destroyed, its engine no longer works
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Destroyed(pub DestructionCause);
@@ -0,0 +1,27 @@
+use crate::components::objectives::Objective;
+use crate::flight_program::FlightProgram;
+use bevy::prelude::Component;
+
+/// Runs a [`FlightProgram`] on a vessel
+#[derive(Component, Default)]
+pub struct FlightComputer {
+    /// Program flying the vessel, `None` leaves the controls to the pilot
+    pub program: Option<Box<dyn FlightProgram>>,
+    /// Load the built-in program for each objective as the mission goes on
+    pub mission: bool,
+    /// Objective the mission program was picked for
+    pub objective: Option<Objective>,
+    /// What the program reported doing on its last step
+    pub phase: &'static str,
+    /// The program is done and no longer touches the controls
+    pub finished: bool,
+    /// A staging request is on its way, so it isn't sent again before the stage drops
+    pub staging: bool,
+}
+
+impl FlightComputer {
+    /// Hands the vessel back to the pilot
+    pub fn disengage(&mut self) {
+        *self = Self::default();
+    }
+}
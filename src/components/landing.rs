@@ -0,0 +1,82 @@
+use crate::constants::{
+    LANDING_CRASH_SPEED, LANDING_CRASH_TILT_DEGREES, LANDING_SAFE_HORIZONTAL_SPEED,
+    LANDING_SAFE_TILT_DEGREES, LANDING_SAFE_VERTICAL_SPEED,
+};
+use bevy::prelude::{Component, Entity};
+
+/// How a touchdown went
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LandingOutcome {
+    /// Within the safe speed and attitude limits
+    Landed,
+    /// Survived, but too fast or too tilted for a clean landing
+    HardLanding,
+    /// The vessel did not survive the impact
+    Destroyed,
+}
+
+impl LandingOutcome {
+    /// Grades a touchdown by its speed components and tilt in radians
+    pub fn classify(vertical_speed: f32, horizontal_speed: f32, tilt: f32) -> Self {
+        let impact_speed = vertical_speed.hypot(horizontal_speed);
+        if impact_speed > LANDING_CRASH_SPEED || tilt > LANDING_CRASH_TILT_DEGREES.to_radians() {
+            LandingOutcome::Destroyed
+        } else if vertical_speed > LANDING_SAFE_VERTICAL_SPEED
+            || horizontal_speed > LANDING_SAFE_HORIZONTAL_SPEED
+            || tilt > LANDING_SAFE_TILT_DEGREES.to_radians()
+        {
+            LandingOutcome::HardLanding
+        } else {
+            LandingOutcome::Landed
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LandingOutcome::Landed => "landed",
+            LandingOutcome::HardLanding => "hard landing",
+            LandingOutcome::Destroyed => "crashed",
+        }
+    }
+
+    /// Whether the vessel came out of it in one piece
+    pub fn survived(self) -> bool {
+        self != LandingOutcome::Destroyed
+    }
+}
+
+/// Measurements taken when a vessel first touches a body
+#[derive(Clone, Copy, Debug)]
+pub struct Touchdown {
+    /// Body that was touched
+    pub body: Entity,
+    /// Mission time of the contact in seconds
+    pub time: f64,
+    /// Speed relative to the body's surface in m/s
+    pub impact_speed: f32,
+    /// Speed towards the surface in m/s
+    pub vertical_speed: f32,
+    /// Speed along the surface in m/s
+    pub horizontal_speed: f32,
+    /// Angle between the vessel's nose and the surface normal in radians
+    pub tilt: f32,
+    pub outcome: LandingOutcome,
+}
+
+/// Tracks a vessel's contact with celestial bodies
+#[derive(Component, Clone, Debug, Default)]
+pub struct SurfaceContact {
+    /// Body the vessel currently rests on or touches
+    pub touching: Option<Entity>,
+    /// The most recent touchdown, kept after lifting off again
+    pub last_touchdown: Option<Touchdown>,
+}
+
+impl SurfaceContact {
+    /// The touchdown on the body the vessel is still in contact with
+    pub fn current_touchdown(&self) -> Option<&Touchdown> {
+        self.last_touchdown
+            .as_ref()
+            .filter(|touchdown| self.touching == Some(touchdown.body))
+    }
+}
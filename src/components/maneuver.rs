@@ -0,0 +1,71 @@
+use crate::components::trajectory_prediction::PathPoint;
+use bevy::prelude::*;
+
+/// An impulsive burn planned at a point on the predicted trajectory
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManeuverNode {
+    /// Mission time of the burn in seconds
+    pub time: f64,
+    /// Delta-v along the velocity relative to the central body in m/s
+    pub prograde: f32,
+    /// Delta-v perpendicular to prograde, away from the central body, in m/s
+    pub radial: f32,
+}
+
+impl ManeuverNode {
+    pub fn new(time: f64) -> Self {
+        Self {
+            time,
+            prograde: 0.,
+            radial: 0.,
+        }
+    }
+
+    /// Delta-v as (prograde, radial)
+    pub fn delta_v(&self) -> Vec2 {
+        Vec2::new(self.prograde, self.radial)
+    }
+}
+
+/// Maneuver nodes of a vessel and the trajectory they produce
+#[derive(Component, Default)]
+pub struct ManeuverPlan {
+    /// Planned burns, sorted by time
+    pub nodes: Vec<ManeuverNode>,
+    /// Index of the node being edited
+    pub selected: Option<usize>,
+    /// Nodes the planned path was last predicted for
+    pub predicted_nodes: Vec<ManeuverNode>,
+    /// Drawn trajectory after the burns
+    pub planned_path: Vec<PathPoint>,
+    /// Drawn position of each node, matching `predicted_nodes`
+    pub node_points: Vec<Option<PathPoint>>,
+    /// Prograde and radial-out directions at each node, matching `predicted_nodes`
+    pub node_frames: Vec<(Vec3, Vec3)>,
+    /// World positions of `planned_path`, refreshed every frame
+    pub planned_points: Vec<Vec3>,
+    /// World positions of `node_points`, refreshed every frame
+    pub node_positions: Vec<Option<Vec3>>,
+}
+
+impl ManeuverPlan {
+    /// Adds a node and selects it, keeping the nodes in time order
+    pub fn add_node(&mut self, node: ManeuverNode) {
+        let index = self.nodes.partition_point(|n| n.time <= node.time);
+        self.nodes.insert(index, node);
+        self.selected = Some(index);
+    }
+
+    /// Removes the selected node, if any
+    pub fn remove_selected(&mut self) {
+        if let Some(index) = self.selected.take()
+            && index < self.nodes.len()
+        {
+            self.nodes.remove(index);
+        }
+    }
+
+    pub fn selected_node(&self) -> Option<&ManeuverNode> {
+        self.selected.and_then(|index| self.nodes.get(index))
+    }
+}
@@ -4,6 +4,15 @@ use bevy::prelude::Component;
 #[derive(Component, Clone)]
 pub struct User;
 
+/// Marks a spent stage jettisoned from a vessel, it coasts under gravity but
+/// pulls on nothing
+#[derive(Component, Clone)]
+pub struct Debris;
+
 /// Marks UI elements that display user information
 #[derive(Component)]
 pub struct UserInfoUi;
+
+/// Marks the conservation diagnostics overlay
+#[derive(Component)]
+pub struct DiagnosticsUi;
@@ -1,6 +1,19 @@
+pub mod aerodynamics;
+pub mod appearance;
+pub mod atmosphere;
+pub mod attitude;
+pub mod celestial_body;
+pub mod damage;
+pub mod flight_computer;
+pub mod landing;
+pub mod maneuver;
 pub mod markers;
 pub mod object_bundle;
 pub mod objectives;
+pub mod orbit;
 pub mod physics_object;
 pub mod propulsion;
+pub mod staging;
+pub mod target;
 pub mod trajectory_prediction;
+pub mod transfer;
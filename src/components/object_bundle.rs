@@ -1,11 +1,11 @@
+use crate::components::appearance::Appearance;
 use crate::components::physics_object::PhysicsObject;
-use bevy::prelude::{Bundle, ColorMaterial, Mesh2d, MeshMaterial2d, Transform};
+use bevy::prelude::{Bundle, Transform};
 
 #[derive(Bundle)]
 pub struct ObjectBundle {
     pub transform: Transform,
     pub physics_object: PhysicsObject,
-    // visual components
-    pub mesh2d: Mesh2d,
-    pub mesh_material: MeshMaterial2d<ColorMaterial>,
+    // visual description, turned into a mesh by the presentation layer
+    pub appearance: Appearance,
 }
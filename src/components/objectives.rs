@@ -1,19 +1,56 @@
+use crate::constants::{LEO_MAX_APOAPSIS_ALTITUDE, LEO_MIN_ALTITUDE};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
 /// Game objectives that players must complete in sequence
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Objective {
     /// Escape Moon's gravitational influence
     EscapeMoon,
-    /// Achieve stable orbit around Earth
-    OrbitEarth,
+    /// Achieve a closed orbit around Earth whose periapsis clears the surface
+    /// and whose apoapsis altitude (in meters) lies within the given band
+    OrbitEarth {
+        #[serde(default = "default_min_apoapsis")]
+        min_apoapsis: f32,
+        #[serde(default = "default_max_apoapsis")]
+        max_apoapsis: f32,
+    },
     /// Successfully land on Earth's surface
     LandOnEarth,
 }
 
+fn default_min_apoapsis() -> f32 {
+    LEO_MIN_ALTITUDE
+}
+
+fn default_max_apoapsis() -> f32 {
+    LEO_MAX_APOAPSIS_ALTITUDE
+}
+
 impl Objective {
+    /// Low Earth orbit with the default apoapsis band
+    pub fn orbit_earth() -> Self {
+        Objective::OrbitEarth {
+            min_apoapsis: default_min_apoapsis(),
+            max_apoapsis: default_max_apoapsis(),
+        }
+    }
+
+    /// Circular orbit altitudes in meters that satisfy an orbit objective
+    pub fn orbit_band(&self) -> Option<RangeInclusive<f32>> {
+        match *self {
+            Objective::OrbitEarth {
+                min_apoapsis,
+                max_apoapsis,
+            } => Some(min_apoapsis.max(LEO_MIN_ALTITUDE)..=max_apoapsis),
+            _ => None,
+        }
+    }
+
     pub fn title(&self) -> &'static str {
         match self {
             Objective::EscapeMoon => "Escape Moon",
-            Objective::OrbitEarth => "Earth Orbit",
+            Objective::OrbitEarth { .. } => "Earth Orbit",
             Objective::LandOnEarth => "Earth Landing",
         }
     }
@@ -24,6 +61,8 @@ impl Objective {
 pub struct ObjectiveProgress {
     /// The objective currently being pursued
     pub current: Objective,
+    /// Objectives still to come after the current one, in order
+    pub upcoming: Vec<Objective>,
     /// List of objectives that have been completed
     pub completed: Vec<Objective>,
     /// Whether the current objective has been completed
@@ -34,16 +73,30 @@ pub struct ObjectiveProgress {
 
 impl Default for ObjectiveProgress {
     fn default() -> Self {
-        Self {
-            current: Objective::EscapeMoon,
+        Self::new(vec![
+            Objective::EscapeMoon,
+            Objective::orbit_earth(),
+            Objective::LandOnEarth,
+        ])
+        .unwrap()
+    }
+}
+
+impl ObjectiveProgress {
+    /// Starts tracking the given objectives, returns `None` for an empty list
+    pub fn new(objectives: Vec<Objective>) -> Option<Self> {
+        let mut objectives = objectives.into_iter();
+        let current = objectives.next()?;
+
+        Some(Self {
+            current,
+            upcoming: objectives.collect(),
             completed: Vec::new(),
             is_completed: false,
             completion_time: None,
-        }
+        })
     }
-}
 
-impl ObjectiveProgress {
     pub fn complete_current(&mut self, time: f32) {
         if !self.is_completed {
             self.completed.push(self.current.clone());
@@ -54,20 +107,25 @@ impl ObjectiveProgress {
 
     pub fn advance_to_next(&mut self) {
         if self.is_completed {
-            match self.current {
-                Objective::EscapeMoon => self.current = Objective::OrbitEarth,
-                Objective::OrbitEarth => self.current = Objective::LandOnEarth,
-                Objective::LandOnEarth => {
-                    // All objectives completed
-                    return;
-                }
+            if self.upcoming.is_empty() {
+                // All objectives completed
+                return;
             }
+            self.current = self.upcoming.remove(0);
             self.is_completed = false;
             self.completion_time = None;
         }
     }
 
+    /// Orbit band of the first orbit objective still ahead, the default band without one
+    pub fn orbit_band(&self) -> RangeInclusive<f32> {
+        std::iter::once(&self.current)
+            .chain(&self.upcoming)
+            .find_map(Objective::orbit_band)
+            .unwrap_or_else(|| default_min_apoapsis()..=default_max_apoapsis())
+    }
+
     pub fn all_completed(&self) -> bool {
-        self.completed.len() == 3
+        self.is_completed && self.upcoming.is_empty()
     }
 }
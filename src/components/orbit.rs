@@ -0,0 +1,28 @@
+use crate::orbital::OrbitalElements;
+use bevy::prelude::Component;
+
+/// Keplerian orbit of an object around its `PhysicsObject::central_body`
+///
+/// Updated after every physics step, so any system can read the current
+/// orbit without redoing the state vector conversion.
+#[derive(Component, Clone, Default)]
+pub struct Orbit {
+    /// Elements relative to the central body, `None` without one
+    pub elements: Option<OrbitalElements>,
+    /// Radius of the central body in meters
+    pub central_radius: f32,
+}
+
+impl Orbit {
+    /// Height of the periapsis above the central body's surface
+    pub fn periapsis_altitude(&self) -> Option<f32> {
+        self.elements.map(|e| e.periapsis - self.central_radius)
+    }
+
+    /// Height of the apoapsis above the central body's surface, `None` for open orbits
+    pub fn apoapsis_altitude(&self) -> Option<f32> {
+        self.elements
+            .and_then(|e| e.apoapsis)
+            .map(|apoapsis| apoapsis - self.central_radius)
+    }
+}
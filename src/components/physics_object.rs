@@ -1,38 +1,61 @@
-use bevy::math::Vec3;
+use crate::components::orbit::Orbit;
+use bevy::math::{DVec3, Vec3};
 use bevy::prelude::{Component, Entity};
 
 /// Physics simulation data for celestial bodies and spacecraft
 ///
 /// Contains all the physical properties needed for n-body gravity simulation
-/// and orbital mechanics calculations.
+/// and orbital mechanics calculations. Position and velocity are kept in
+/// double precision, `Transform` is derived from them relative to the
+/// [`FloatingOrigin`](crate::systems::physics::FloatingOrigin) for rendering.
 #[derive(Component, Clone)]
+#[require(Orbit)]
 pub struct PhysicsObject {
+    /// Position in simulation space in meters
+    pub position: DVec3,
     /// Current velocity vector in m/s
-    pub vel: Vec3,
+    pub vel: DVec3,
     /// Mass of the object in kg
     pub mass: f32,
     /// Physical radius in meters (used for collision detection)
     pub radius: f32,
     /// Accumulated forces to be applied this frame (reset each frame)
     pub applied_force: Vec3,
-    /// The primary gravitational body this object orbits (for reference)
+    /// Body whose sphere of influence the object is in, see
+    /// [`SoiHierarchy`](crate::systems::physics::SoiHierarchy)
     pub central_body: Option<Entity>,
+    /// Whether the last step followed the Kepler orbit around the central body
+    /// instead of integrating gravity
+    pub on_rails: bool,
 }
 
 impl PhysicsObject {
-    pub fn new(mass: f32, radius: f32, vel: Vec3, central_body: Option<Entity>) -> Self {
+    pub fn new(
+        mass: f32,
+        radius: f32,
+        position: DVec3,
+        vel: DVec3,
+        central_body: Option<Entity>,
+    ) -> Self {
         Self {
+            position,
             vel,
             mass,
             radius,
             applied_force: Vec3::ZERO,
             central_body,
+            on_rails: false,
         }
     }
+
+    /// Moment of inertia about the spin axis in kg·m², treating the object as a uniform disc
+    pub fn moment_of_inertia(&self) -> f32 {
+        0.5 * self.mass * self.radius * self.radius
+    }
 }
 
 impl Default for PhysicsObject {
     fn default() -> Self {
-        Self::new(1.0, 1.0, Vec3::ZERO, None)
+        Self::new(1.0, 1.0, DVec3::ZERO, DVec3::ZERO, None)
     }
 }
@@ -1,10 +1,12 @@
+use crate::constants::STANDARD_GRAVITY;
 use bevy::prelude::Component;
 
 /// Spacecraft propulsion system component
 ///
 /// Manages thrust output, fuel consumption, and engine parameters
-/// for player and AI controlled spacecraft.
-#[derive(Component)]
+/// for player and AI controlled spacecraft. The vessel's mass is its dry mass
+/// plus the remaining fuel.
+#[derive(Component, Clone)]
 pub struct Propulsion {
     /// Maximum thrust force in Newtons
     pub max_thrust: f32,
@@ -14,8 +16,10 @@ pub struct Propulsion {
     pub fuel: f32,
     /// Maximum fuel capacity in kg
     pub max_fuel: f32,
-    /// Fuel consumption rate in kg/second at full thrust
-    pub fuel_consumption_rate: f32,
+    /// Specific impulse in seconds
+    pub specific_impulse: f32,
+    /// Mass of the vessel without fuel in kg
+    pub dry_mass: f32,
 }
 
 impl Default for Propulsion {
@@ -23,9 +27,87 @@ impl Default for Propulsion {
         Self {
             max_thrust: 10.0,
             thrust_percentage: 0.0,
-            fuel: 1000.0,               // 1000 kg of fuel
-            max_fuel: 1000.0,           // 1000 kg capacity
-            fuel_consumption_rate: 1.0, // 1 kg/s at full thrust (much more reasonable)
+            fuel: 1000.0,            // 1000 kg of fuel
+            max_fuel: 1000.0,        // 1000 kg capacity
+            specific_impulse: 300.0, // typical chemical engine
+            dry_mass: 1000.0,
+        }
+    }
+}
+
+impl Propulsion {
+    /// Effective exhaust velocity in m/s
+    pub fn exhaust_velocity(&self) -> f32 {
+        self.specific_impulse * STANDARD_GRAVITY
+    }
+
+    /// Fuel consumption rate in kg/second at full thrust
+    pub fn fuel_consumption_rate(&self) -> f32 {
+        self.max_thrust / self.exhaust_velocity()
+    }
+
+    /// Total mass in kg, dry mass plus remaining fuel
+    pub fn mass(&self) -> f32 {
+        self.dry_mass + self.fuel
+    }
+
+    /// Velocity change the remaining fuel can still provide, from the rocket equation
+    pub fn delta_v(&self) -> f32 {
+        self.exhaust_velocity() * (self.mass() / self.dry_mass).ln()
+    }
+
+    /// Seconds at full thrust needed to change velocity by `delta_v`
+    ///
+    /// Follows the rocket equation, returns `None` when there is not enough fuel.
+    pub fn burn_duration(&self, delta_v: f32) -> Option<f32> {
+        let final_mass = self.mass() * (-delta_v / self.exhaust_velocity()).exp();
+        let fuel_needed = self.mass() - final_mass;
+        (fuel_needed <= self.fuel).then(|| fuel_needed / self.fuel_consumption_rate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> Propulsion {
+        Propulsion {
+            max_thrust: 50_000.,
+            fuel: 4000.,
+            max_fuel: 4000.,
+            dry_mass: 6000.,
+            ..Propulsion::default()
         }
     }
+
+    #[test]
+    fn burn_duration_follows_the_rocket_equation() {
+        let engine = engine();
+        let mass = engine.mass();
+        let delta_v = 500.;
+
+        let duration = engine.burn_duration(delta_v).unwrap();
+        let final_mass = mass - duration * engine.fuel_consumption_rate();
+        let achieved = engine.exhaust_velocity() * (mass / final_mass).ln();
+        assert!((achieved - delta_v).abs() < 0.01, "{achieved} m/s");
+
+        // The vessel lightens as it burns, so it takes less than at constant mass
+        assert!(duration < delta_v * mass / engine.max_thrust);
+        assert!(engine.burn_duration(20_000.).is_none());
+    }
+
+    #[test]
+    fn delta_v_uses_up_the_fuel() {
+        let engine = engine();
+        let expected = engine.specific_impulse * STANDARD_GRAVITY * (10_000f32 / 6000.).ln();
+        assert!((engine.delta_v() - expected).abs() < 0.01);
+
+        let duration = engine.burn_duration(engine.delta_v() * 0.9999).unwrap();
+        let fuel_used = duration * engine.fuel_consumption_rate();
+        assert!((fuel_used - engine.fuel).abs() < 1., "{fuel_used} kg");
+
+        let empty = Propulsion { fuel: 0., ..engine };
+        assert_eq!(empty.delta_v(), 0.);
+        assert_eq!(empty.mass(), empty.dry_mass);
+    }
 }
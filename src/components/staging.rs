@@ -0,0 +1,108 @@
+use crate::components::propulsion::Propulsion;
+use crate::constants::STANDARD_GRAVITY;
+use bevy::prelude::Component;
+
+/// Structure, tank and engine of one stage of a vessel
+#[derive(Clone, Debug)]
+pub struct Stage {
+    /// Mass of the structure and engine in kg
+    pub dry_mass: f32,
+    /// Current fuel amount in kg
+    pub fuel: f32,
+    /// Maximum fuel capacity in kg
+    pub max_fuel: f32,
+    /// Maximum thrust force in Newtons
+    pub max_thrust: f32,
+    /// Specific impulse in seconds
+    pub specific_impulse: f32,
+}
+
+impl Stage {
+    /// Total mass in kg, dry mass plus remaining fuel
+    pub fn mass(&self) -> f32 {
+        self.dry_mass + self.fuel
+    }
+
+    /// Velocity change this stage provides while pushing `payload` kg of stages above it
+    pub fn delta_v(&self, payload: f32) -> f32 {
+        let dry = payload + self.dry_mass;
+        self.specific_impulse * STANDARD_GRAVITY * ((dry + self.fuel) / dry).ln()
+    }
+}
+
+/// Stages of a vessel that are not firing yet
+///
+/// The firing stage's engine and tank live in the vessel's [`Propulsion`],
+/// whose dry mass is the firing stage's structure plus every stage still
+/// stacked above it.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Stages {
+    /// Structural mass of the firing stage, left behind when it is jettisoned
+    pub active_dry_mass: f32,
+    /// Stages still attached, the next one to fire first
+    pub upcoming: Vec<Stage>,
+}
+
+impl Stages {
+    /// Splits a stack listed in firing order into the firing stage's propulsion
+    /// and the stages above it, `None` when the stack is empty
+    pub fn assemble(stack: Vec<Stage>) -> Option<(Propulsion, Self)> {
+        let mut stack = stack.into_iter();
+        let first = stack.next()?;
+        let mut stages = Self {
+            active_dry_mass: 0.,
+            upcoming: stack.collect(),
+        };
+        let propulsion = stages.ignite(first, 0.);
+        Some((propulsion, stages))
+    }
+
+    /// Whether another stage is left to separate the firing one from
+    pub fn can_stage(&self) -> bool {
+        !self.upcoming.is_empty()
+    }
+
+    /// Drops the firing stage and switches the engine to the next one
+    ///
+    /// Returns the spent stage with whatever fuel was left in it, or `None`
+    /// when the firing stage is the last one.
+    pub fn separate(&mut self, propulsion: &mut Propulsion) -> Option<Stage> {
+        if !self.can_stage() {
+            return None;
+        }
+
+        let spent = Stage {
+            dry_mass: self.active_dry_mass,
+            fuel: propulsion.fuel,
+            max_fuel: propulsion.max_fuel,
+            max_thrust: propulsion.max_thrust,
+            specific_impulse: propulsion.specific_impulse,
+        };
+        let next = self.upcoming.remove(0);
+        *propulsion = self.ignite(next, propulsion.thrust_percentage);
+        Some(spent)
+    }
+
+    /// Velocity change left in every stage, the firing one first
+    pub fn delta_v(&self, propulsion: &Propulsion) -> Vec<f32> {
+        let upcoming = self.upcoming.iter().enumerate().map(|(index, stage)| {
+            let payload: f32 = self.upcoming[index + 1..].iter().map(Stage::mass).sum();
+            stage.delta_v(payload)
+        });
+        std::iter::once(propulsion.delta_v())
+            .chain(upcoming)
+            .collect()
+    }
+
+    fn ignite(&mut self, stage: Stage, thrust_percentage: f32) -> Propulsion {
+        self.active_dry_mass = stage.dry_mass;
+        Propulsion {
+            max_thrust: stage.max_thrust,
+            thrust_percentage,
+            fuel: stage.fuel,
+            max_fuel: stage.max_fuel,
+            specific_impulse: stage.specific_impulse,
+            dry_mass: stage.dry_mass + self.upcoming.iter().map(Stage::mass).sum::<f32>(),
+        }
+    }
+}
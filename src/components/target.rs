@@ -0,0 +1,5 @@
+use bevy::prelude::{Component, Entity};
+
+/// Body the pilot selected for the target hold mode and readouts
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Target(pub Entity);
@@ -1,16 +1,107 @@
 use crate::constants::PREDICTION_POINTS;
 use bevy::prelude::*;
 
+/// A single predicted state, relative to the central body
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PathSample {
+    /// Position relative to the central body
+    pub position: Vec3,
+    /// Velocity relative to the central body
+    pub velocity: Vec3,
+    /// Seconds from the moment the prediction was made
+    pub time: f32,
+}
+
+/// A drawn position, kept relative to the body it is drawn around so cached
+/// paths follow their bodies
+#[derive(Clone, Copy, Debug)]
+pub struct PathPoint {
+    /// Body the offset is relative to, `None` for world positions
+    pub reference: Option<Entity>,
+    pub offset: Vec3,
+}
+
+impl PathPoint {
+    /// World position given a lookup of current body positions
+    pub fn resolve(&self, position_of: impl Fn(Entity) -> Option<Vec3>) -> Vec3 {
+        self.reference
+            .and_then(position_of)
+            .map_or(self.offset, |origin| origin + self.offset)
+    }
+}
+
+/// A point of interest along a predicted path
+#[derive(Clone, Copy, Debug)]
+pub struct PathEvent {
+    pub kind: PathEventKind,
+    /// Seconds from the moment the prediction was made
+    pub time: f32,
+    pub point: PathPoint,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum PathEventKind {
+    /// The path moves into another body's sphere of influence
+    SoiChange { from: Entity, to: Entity },
+    /// The path ends on the surface of a body
+    Collision { body: Entity },
+    /// Closest point of the path to a body, with the body's drawn position at that time
+    ClosestApproach {
+        body: Entity,
+        distance: f32,
+        body_point: PathPoint,
+    },
+}
+
 /// Component that stores predicted trajectory points for an entity
 #[derive(Component, Default)]
 pub struct TrajectoryPrediction {
+    /// World positions for drawing, refreshed every frame from `path`
     pub points: Vec<Vec3>,
+    /// Drawn position of each sample
+    pub path: Vec<PathPoint>,
+    /// Full predicted states matching `path`
+    pub samples: Vec<PathSample>,
+    /// Body the samples are relative to
+    pub central_body: Option<Entity>,
+    /// SOI changes, collisions and closest approaches, in time order
+    pub events: Vec<PathEvent>,
+    /// Mission time the prediction was made at
+    pub epoch: f64,
 }
 
 impl TrajectoryPrediction {
     pub fn new() -> Self {
         Self {
             points: Vec::with_capacity(PREDICTION_POINTS),
+            path: Vec::with_capacity(PREDICTION_POINTS),
+            samples: Vec::with_capacity(PREDICTION_POINTS),
+            central_body: None,
+            events: Vec::new(),
+            epoch: 0.,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.path.clear();
+        self.samples.clear();
+        self.events.clear();
+    }
+
+    /// Interpolated state `time` seconds after the prediction was made
+    pub fn sample_at(&self, time: f32) -> Option<PathSample> {
+        let next = self.samples.iter().position(|s| s.time >= time)?;
+        if next == 0 {
+            return self.samples.first().copied();
         }
+
+        let (a, b) = (self.samples[next - 1], self.samples[next]);
+        let t = (time - a.time) / (b.time - a.time);
+        Some(PathSample {
+            position: a.position.lerp(b.position, t),
+            velocity: a.velocity.lerp(b.velocity, t),
+            time,
+        })
     }
 }
@@ -0,0 +1,21 @@
+use crate::planner::Transfer;
+use bevy::prelude::{Component, Entity};
+
+/// What the planned transfers lead to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferGoal {
+    /// Circular orbit at the given altitude in meters around a body
+    Orbit { body: Entity, altitude: f32 },
+    /// Encounter with a body orbiting the same body as the vessel
+    Intercept(Entity),
+}
+
+/// Transfers from the vessel's orbit to its target, refreshed every frame
+#[derive(Component, Default)]
+pub struct TransferPlanner {
+    /// Where the transfers lead, `None` without a reachable target
+    pub goal: Option<TransferGoal>,
+    pub hohmann: Option<Transfer>,
+    /// Only offered for orbit changes around the body the vessel orbits
+    pub bi_elliptic: Option<Transfer>,
+}
@@ -1,12 +1,72 @@
-use bevy::prelude::Resource;
+use crate::constants::DT_STAGES;
+use crate::integrator::Integrator;
+use bevy::prelude::{Fixed, Resource, Time};
 
 /// Global game configuration settings
 ///
 /// Controls physics simulation timing and time acceleration features.
 #[derive(Resource)]
 pub struct Config {
-    /// Physics time step in seconds (should match FixedUpdate frequency)
-    pub dt: f32,
     /// Time acceleration multiplier for time warp feature
     pub time_multiplier: u32,
+    /// Current index into `DT_STAGES`
+    pub warp_stage: usize,
+    /// How trajectories are predicted
+    pub prediction_mode: PredictionMode,
+    /// Numerical method used by physics and prediction
+    pub integrator: Integrator,
+}
+
+/// Trajectory prediction model
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PredictionMode {
+    /// Gravity of the central body only, which is assumed to move in a straight line
+    #[default]
+    TwoBody,
+    /// Every massive body is propagated with the simulation's integrator
+    NBody,
+}
+
+impl PredictionMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            PredictionMode::TwoBody => PredictionMode::NBody,
+            PredictionMode::NBody => PredictionMode::TwoBody,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PredictionMode::TwoBody => "two-body",
+            PredictionMode::NBody => "N-body",
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let (_, _, time_multiplier) = DT_STAGES[0];
+        Self {
+            time_multiplier,
+            warp_stage: 0,
+            prediction_mode: PredictionMode::default(),
+            integrator: Integrator::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Simulated seconds covered by one fixed step at the current warp stage
+    pub fn step_dt(&self) -> f32 {
+        DT_STAGES[self.warp_stage].0
+    }
+
+    /// Switches to the given time warp stage and retunes the fixed timestep to match
+    pub fn set_warp_stage(&mut self, stage: usize, fixed_time: &mut Time<Fixed>) {
+        let stage = stage.min(DT_STAGES.len() - 1);
+        let (_, timestep, multiplier) = DT_STAGES[stage];
+        self.warp_stage = stage;
+        self.time_multiplier = multiplier;
+        *fixed_time = Time::<Fixed>::from_hz(timestep as f64);
+    }
 }
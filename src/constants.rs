@@ -1,32 +1,97 @@
 // Physics constants
 pub const G: f32 = 6.67430e-11; // m^3/(kg·s^2)
 pub const SOFTENING: f32 = 12.5; // Softening factor to prevent singularities in gravity calculations
+pub const STANDARD_GRAVITY: f32 = 9.80665; // m/s², one g
+pub const STEFAN_BOLTZMANN: f32 = 5.670374e-8; // W/(m²·K⁴)
+pub const HULL_EMISSIVITY: f32 = 0.8; // Fraction of black body radiation the hull sheds
+pub const AMBIENT_TEMPERATURE: f32 = 290.; // K, hull temperature without aerodynamic heating
 
 // Scaling factors
 pub const PLANET_SCALE: f32 = 1.0 / 20.0; // 1:20
 pub const DISTANCE_SCALE: f32 = 1.0 / 50.0; // 1:50
 
 // Celestial body dimensions
-pub const EARTH_RADIUS: f32 = 6_371_000. * PLANET_SCALE; // in meters, scaled
-pub const MOON_RADIUS: f32 = 1_737_100. * PLANET_SCALE; // in meters, scaled
 pub const MOON_ORBIT_RADIUS: f32 = 384_400_000. * DISTANCE_SCALE; // in meters, scaled
 
 // Simulation parameters
-pub const PREDICTION_POINTS: usize = 512;
+pub const PREDICTION_POINTS: usize = 4096;
+pub const PREDICTION_DRIFT_TOLERANCE: f32 = 0.01; // Fraction of the distance to the central body the vessel may stray from its prediction
 pub const MIN_DT: f32 = 10.;
 pub const MAX_DT: f32 = 2048.;
+pub const RK45_TOLERANCE: f64 = 1e-6; // Position error per substep, relative to the distance from the origin
+pub const RK45_MAX_SUBSTEPS: u32 = 64; // Smallest adaptive RK45 substep as a fraction of the step
+pub const DIAGNOSTICS_HISTORY: usize = 1024; // Fixed steps of conservation history kept for the graph
+pub const DIAGNOSTICS_LOG_INTERVAL: f32 = 10.; // Real seconds between conservation drift log lines
+pub const BURN_SUBSTEP_DT: f32 = 1. / 64.; // Longest physics substep while an engine fires, the unwarped step
+pub const BURN_MAX_SUBSTEPS: u32 = 64; // Most physics substeps a warped fixed step is split into during a burn
+
+// Time warp stages: (dt, timestep hertz, time multiplier)
+pub const DT_STAGES: [(f32, f32, u32); 9] = [
+    (1. / 64., 64., 1),
+    (1. / 64., 64. * 5., 5),
+    (1. / 64. * 5., 64. * 5., 25),
+    (1. / 64. * 10., 64. * 10., 100),
+    (1. / 64. * 50., 64. * 10., 500),
+    (1. / 64. * 125., 64. * 20., 2500),
+    (1. / 64. * 500., 64. * 20., 10000),
+    (1. / 64. * 1250., 64. * 40., 50000),
+    (1. / 64. * 2500., 64. * 100., 250000),
+];
+pub const ON_RAILS_WARP_STAGE: usize = 5; // Lowest warp stage at which coasting objects follow their Kepler orbits
+pub const KEPLER_MAX_ITERATIONS: usize = 32; // Newton iterations when solving Kepler's equation
+pub const KEPLER_TOLERANCE: f64 = 1e-12; // Relative change in the universal anomaly that ends the iteration
+pub const SOI_HYSTERESIS: f64 = 0.02; // Fraction beyond its radius a sphere of influence is only left at
 
 // Objective constants
 pub const LEO_MIN_ALTITUDE: f32 = 40_000.; // 40 km above Earth surface (simplified requirement)
-pub const LEO_REQUIRED_TIME: f32 = 1.0; // 1 second in stable LEO to complete objective
+pub const ESCAPE_REQUIRED_TIME: f32 = 1.0; // Mission seconds outside the moon's sphere of influence to complete the escape objective
+pub const LEO_MAX_APOAPSIS_ALTITUDE: f32 = 2_000_000.; // 2000 km, upper edge of low Earth orbit
+pub const ORBIT_REQUIRED_SWEEP_DEGREES: f32 = 90.0; // Quarter revolution in a valid orbit to complete objective
+
+// Landing constants
+pub const LANDING_SAFE_VERTICAL_SPEED: f32 = 5.0; // m/s, fastest descent rate for a clean landing
+pub const LANDING_SAFE_HORIZONTAL_SPEED: f32 = 2.0; // m/s, fastest sideways drift for a clean landing
+pub const LANDING_SAFE_TILT_DEGREES: f32 = 10.0; // Largest nose tilt from the surface normal for a clean landing
+pub const LANDING_CRASH_SPEED: f32 = 20.0; // m/s, impacts faster than this destroy the vessel
+pub const LANDING_CRASH_TILT_DEGREES: f32 = 45.0; // Touching down tilted further than this topples the vessel
+pub const LANDING_CONTACT_MARGIN: f32 = 1.0; // m, gap to the surface that still counts as touching
+
+// Autopilot constants
+pub const AUTOPILOT_BRAKING_MARGIN: f32 = 0.8; // Share of the available torque planned for braking
+pub const AUTOPILOT_SETTLE_TIME: f32 = 0.5; // s, time to close small pointing errors
+
+// Flight computer constants
+pub const FLIGHT_PROGRAM_ALIGNMENT_DEGREES: f32 = 10.0; // Largest pointing error at which a program's burn still fires
+pub const FLIGHT_PROGRAM_BURN_WARP_STAGE: usize = 1; // Fastest time warp stage flight programs burn at
+pub const FLIGHT_PROGRAM_VELOCITY_TOLERANCE: f32 = 0.2; // m/s, velocity error at which a matching burn ends
+pub const ESCAPE_PARKING_ALTITUDE: f32 = 15_000.0; // 15km - parking orbit the escape burn starts from
+pub const LANDING_DEORBIT_PERIAPSIS_RATIO: f32 = 0.5; // Periapsis of the deorbit burn as a share of the body radius
+pub const LANDING_BRAKING_THRESHOLD: f32 = 0.7; // Share of full thrust needed to stop in time that starts the braking burn
+pub const LANDING_FINAL_ALTITUDE: f32 = 200.0; // m, height the braking burn aims to stop at
+pub const LANDING_FINAL_SPEED: f32 = 10.0; // m/s, speed at which the braking burn hands over to the touchdown
+pub const LANDING_TOUCHDOWN_SPEED: f32 = 1.5; // m/s, descent rate the touchdown aims for at the surface
+pub const LANDING_DESCENT_RATE_GAIN: f32 = 0.05; // Descent rate per meter of altitude during the touchdown, in 1/s
+pub const LANDING_RESPONSE_TIME: f32 = 2.0; // s, time the touchdown takes to correct a velocity error
+pub const LANDING_UPRIGHT_ALTITUDE: f32 = 50.0; // m, below this the touchdown keeps the nose nearly upright
+pub const LANDING_MAX_TILT_DEGREES: f32 = 30.0; // Largest tilt the touchdown uses to kill sideways drift
+
+// Transfer planner constants
+pub const TRANSFER_BI_ELLIPTIC_RATIO: f32 = 3.0; // Bi-elliptic apoapsis as a multiple of the larger orbit's radius
+pub const TRANSFER_MIN_LEAD_TIME: f32 = 60.0; // s, windows closer than this are skipped for the next one
+
+// Staging constants
+pub const STAGE_SEPARATION_SPEED: f32 = 2.0; // m/s, speed at which a spent stage is pushed away
+pub const DEBRIS_RADIUS: f32 = 6.0; // m, collision radius of a jettisoned stage
 
 // User control constants
-pub const ROTATION_STEP_DEGREES: f32 = 1.0; // Degrees per key press
-pub const ROTATION_STEP_RADIANS: f32 = ROTATION_STEP_DEGREES * std::f32::consts::PI / 180.0;
 pub const THRUST_ADJUSTMENT_STEP: f32 = 0.1; // Thrust percentage change per key press
+pub const THROTTLE_RAMP_RATE: f32 = 0.5; // Thrust percentage change per second while a ramp key is held
+pub const GAMEPAD_THROTTLE_DEADBAND: f32 = 0.02; // Trigger travel that counts as moving the analog throttle
 
-// Time warp altitude restrictions
-pub const TIME_WARP_MIN_EARTH_ALTITUDE: f32 = 30_000.0; // 30km - no time warp below this
-pub const TIME_WARP_LIMITED_EARTH_ALTITUDE: f32 = 100_000.0; // 100km - limited time warp below this
+// Time warp altitude restrictions, defaults for bodies that don't set their own
+pub const TIME_WARP_MIN_PLANET_ALTITUDE: f32 = 30_000.0; // 30km - no time warp below this
+pub const TIME_WARP_LIMITED_PLANET_ALTITUDE: f32 = 100_000.0; // 100km - limited time warp below this
 pub const TIME_WARP_MIN_MOON_ALTITUDE: f32 = 5_000.0; // 5km - no time warp below this
 pub const TIME_WARP_LIMITED_MOON_ALTITUDE: f32 = 30_000.0; // 30km - limited time warp below this
+pub const TIME_WARP_LIMITED_STAGE: usize = 3; // Highest warp stage between the minimum and limited altitudes
+pub const WARP_TO_RAMP_TIME: f32 = 1.0; // Real seconds a warp-to keeps at least left before the event at its current stage
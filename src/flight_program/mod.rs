@@ -0,0 +1,204 @@
+mod programs;
+
+pub use programs::{EscapeProgram, LandingProgram, OrbitProgram};
+
+use crate::components::attitude::AttitudeHold;
+use crate::components::objectives::{Objective, ObjectiveProgress};
+use crate::components::physics_object::PhysicsObject;
+use crate::constants::G;
+use crate::orbital::OrbitalElements;
+use bevy::prelude::*;
+
+/// Automation that flies a vessel one fixed step at a time
+///
+/// The flight computer hands the program a snapshot of its vessel before the
+/// attitude and propulsion systems run, and applies the returned command to
+/// SAS and the throttle. Spent stages are jettisoned for the program whenever
+/// it asks for thrust with empty tanks.
+pub trait FlightProgram: Send + Sync {
+    /// Short name shown in the HUD
+    fn name(&self) -> &'static str;
+
+    /// Decides the controls for the coming step
+    fn step(&mut self, vessel: &VesselState) -> FlightCommand;
+}
+
+/// Position and velocity relative to a body, along with how hard it pulls
+#[derive(Clone, Copy, Debug)]
+pub struct RelativeState {
+    /// Position relative to the body in meters
+    pub position: Vec2,
+    /// Velocity relative to the body in m/s
+    pub velocity: Vec2,
+    /// Standard gravitational parameter of the body in m³/s²
+    pub mu: f32,
+    /// Radius of the body in meters
+    pub radius: f32,
+}
+
+impl RelativeState {
+    /// State of `object` relative to `body`
+    pub fn between(object: &PhysicsObject, body: &PhysicsObject) -> Self {
+        Self {
+            position: (object.position - body.position).as_vec3().truncate(),
+            velocity: (object.vel - body.vel).as_vec3().truncate(),
+            mu: G * (body.mass + object.mass),
+            radius: body.radius,
+        }
+    }
+
+    /// Distance from the body's center in meters
+    pub fn distance(&self) -> f32 {
+        self.position.length()
+    }
+
+    /// Unit vector pointing away from the body
+    pub fn up(&self) -> Vec2 {
+        self.position.normalize_or(Vec2::Y)
+    }
+
+    /// Unit vector along the surface in the direction of travel, counter-clockwise at rest
+    pub fn horizontal(&self) -> Vec2 {
+        if self.position.perp_dot(self.velocity) < 0. {
+            -self.up().perp()
+        } else {
+            self.up().perp()
+        }
+    }
+
+    /// Speed away from the body, negative while falling towards it
+    pub fn radial_speed(&self) -> f32 {
+        self.velocity.dot(self.up())
+    }
+
+    /// Gravitational acceleration at the current distance in m/s²
+    pub fn gravity(&self) -> f32 {
+        self.mu / self.position.length_squared()
+    }
+
+    /// Specific orbital energy in J/kg, positive on escape trajectories
+    pub fn specific_energy(&self) -> f32 {
+        self.velocity.length_squared() / 2. - self.mu / self.distance()
+    }
+
+    /// Farthest distance the trajectory reaches, infinite on escape trajectories
+    ///
+    /// Unlike [`OrbitalElements::apoapsis`] this also holds for straight up and
+    /// down trajectories, such as right after lifting off.
+    pub fn apoapsis(&self) -> f32 {
+        let energy = self.specific_energy();
+        if energy >= 0. {
+            return f32::INFINITY;
+        }
+        let angular_momentum = self.position.perp_dot(self.velocity);
+        let eccentricity = (1. + 2. * energy * angular_momentum.powi(2) / self.mu.powi(2))
+            .max(0.)
+            .sqrt();
+        -self.mu / (2. * energy) * (1. + eccentricity)
+    }
+
+    pub fn elements(&self) -> OrbitalElements {
+        OrbitalElements::from_state_vectors(
+            self.position.extend(0.),
+            self.velocity.extend(0.),
+            self.mu,
+        )
+    }
+}
+
+/// Everything a flight program gets to see of its vessel for one step
+#[derive(Clone, Copy, Debug)]
+pub struct VesselState {
+    /// Mission time in seconds
+    pub time: f64,
+    /// Length of the coming step in seconds
+    pub dt: f32,
+    /// State relative to the central body
+    pub relative: RelativeState,
+    /// State of the central body relative to its own central body, `None`
+    /// when it orbits nothing
+    pub primary: Option<RelativeState>,
+    /// Central body, changes when the vessel passes into another body's sphere of influence
+    pub central_body: Entity,
+    /// Unit vector the nose points along
+    pub heading: Vec2,
+    /// Radius of the vessel in meters
+    pub radius: f32,
+    /// Acceleration at full thrust in m/s², zero once the firing stage is dry
+    pub max_acceleration: f32,
+    /// Whether the vessel rests on the central body
+    pub landed: bool,
+}
+
+impl VesselState {
+    /// Height of the vessel's lowest point above the central body's surface
+    pub fn altitude(&self) -> f32 {
+        self.relative.distance() - self.relative.radius - self.radius
+    }
+}
+
+/// Where a flight program wants the nose
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttitudeCommand {
+    /// One of the SAS hold modes
+    Hold(AttitudeHold),
+    /// A world direction as a unit vector
+    Direction(Vec2),
+}
+
+/// What a flight program does with the controls for one step
+#[derive(Clone, Copy, Debug)]
+pub struct FlightCommand {
+    /// Throttle from 0.0 to 1.0, held back until the nose is aligned
+    pub throttle: f32,
+    /// Attitude to hold, `None` leaves SAS as the pilot set it
+    pub attitude: Option<AttitudeCommand>,
+    /// What the program is doing, shown in the HUD
+    pub phase: &'static str,
+    /// The program is done and hands the controls back to the pilot
+    pub finished: bool,
+}
+
+impl FlightCommand {
+    /// Engine off while pointing as told
+    pub fn coast(phase: &'static str, attitude: AttitudeCommand) -> Self {
+        Self::burn(phase, attitude, 0.)
+    }
+
+    pub fn burn(phase: &'static str, attitude: AttitudeCommand, throttle: f32) -> Self {
+        Self {
+            throttle,
+            attitude: Some(attitude),
+            phase,
+            finished: false,
+        }
+    }
+
+    /// Engine off, SAS left as it is
+    pub fn finished(phase: &'static str) -> Self {
+        Self {
+            throttle: 0.,
+            attitude: None,
+            phase,
+            finished: true,
+        }
+    }
+}
+
+/// Built-in program for the objective being pursued, `None` once all are done
+///
+/// The escape program already aims its transfer at the orbit the next
+/// objective asks for.
+pub fn program_for(progress: &ObjectiveProgress) -> Option<Box<dyn FlightProgram>> {
+    if progress.all_completed() {
+        return None;
+    }
+
+    let band = progress.orbit_band();
+
+    Some(match progress.current {
+        Objective::EscapeMoon => Box::new(EscapeProgram::new((band.start() + band.end()) / 2.)),
+        Objective::OrbitEarth { .. } => Box::new(OrbitProgram::new(band)),
+        Objective::LandOnEarth => Box::new(LandingProgram::default()),
+    })
+}
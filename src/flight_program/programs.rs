@@ -0,0 +1,421 @@
+use super::{AttitudeCommand, FlightCommand, FlightProgram, VesselState};
+use crate::components::attitude::AttitudeHold;
+use crate::constants::{
+    ESCAPE_PARKING_ALTITUDE, FLIGHT_PROGRAM_VELOCITY_TOLERANCE, LANDING_BRAKING_THRESHOLD,
+    LANDING_DEORBIT_PERIAPSIS_RATIO, LANDING_DESCENT_RATE_GAIN, LANDING_FINAL_ALTITUDE,
+    LANDING_FINAL_SPEED, LANDING_MAX_TILT_DEGREES, LANDING_RESPONSE_TIME,
+    LANDING_SAFE_TILT_DEGREES, LANDING_TOUCHDOWN_SPEED, LANDING_UPRIGHT_ALTITUDE,
+};
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+use std::ops::RangeInclusive;
+
+/// Burns towards a velocity relative to the central body
+///
+/// Eases off over the last second of the burn so it stops on target, returns
+/// `None` once the velocity is matched.
+fn match_velocity(
+    vessel: &VesselState,
+    target: Vec2,
+    phase: &'static str,
+) -> Option<FlightCommand> {
+    let error = target - vessel.relative.velocity;
+    let remaining = error.length();
+    if remaining < FLIGHT_PROGRAM_VELOCITY_TOLERANCE {
+        return None;
+    }
+
+    let throttle = if vessel.max_acceleration > 0. {
+        (remaining / vessel.max_acceleration).min(1.)
+    } else {
+        1.
+    };
+    Some(FlightCommand::burn(
+        phase,
+        AttitudeCommand::Direction(error / remaining),
+        throttle,
+    ))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EscapePhase {
+    Ascent,
+    Coast,
+    Circularize,
+    /// Waiting in the parking orbit, with the angle left to the departure point at the last step
+    Wait(Option<f32>),
+    Burn,
+    Done,
+}
+
+/// Leaves the central body onto a transfer orbit around its primary
+///
+/// Climbs straight up into a low parking orbit, then waits for the point from
+/// which the escape burn leaves in the direction that drops the vessel onto a
+/// transfer orbit reaching down to `periapsis_altitude` above the primary.
+pub struct EscapeProgram {
+    periapsis_altitude: f32,
+    phase: EscapePhase,
+    /// Hyperbolic excess speed the escape burn aims for, in m/s
+    excess_speed: f32,
+}
+
+impl EscapeProgram {
+    pub fn new(periapsis_altitude: f32) -> Self {
+        Self {
+            periapsis_altitude,
+            phase: EscapePhase::Ascent,
+            excess_speed: 0.,
+        }
+    }
+
+    /// Direction the parking orbit runs in, the same as the central body's own orbit
+    fn sense(vessel: &VesselState) -> f32 {
+        match vessel.primary {
+            Some(primary) if primary.position.perp_dot(primary.velocity) < 0. => -1.,
+            _ => 1.,
+        }
+    }
+
+    /// Velocity relative to the central body to leave its pull with, `None` without a primary
+    fn excess_velocity(&self, vessel: &VesselState) -> Option<Vec2> {
+        let primary = vessel.primary?;
+        let distance = primary.distance();
+        let periapsis = primary.radius + self.periapsis_altitude;
+        // Speed at the high point of the transfer orbit
+        let speed = (2. * primary.mu * periapsis / (distance * (distance + periapsis))).sqrt();
+        Some(primary.horizontal() * speed - primary.velocity)
+    }
+
+    /// Angle still to travel in the parking orbit before the escape burn starts
+    fn angle_to_departure(vessel: &VesselState, excess: Vec2) -> f32 {
+        let relative = &vessel.relative;
+        let sense = Self::sense(vessel);
+        let distance = relative.distance();
+        let speed = relative.velocity.length();
+
+        // The departure hyperbola bends away from its periapsis by the true
+        // anomaly of its asymptote
+        let eccentricity = 1. + distance * excess.length_squared() / relative.mu;
+        let asymptote = (-1. / eccentricity).acos();
+        let periapsis_angle = excess.to_angle() - sense * asymptote;
+
+        // Start half a burn early so the burn is centred on the periapsis
+        let burn = (excess.length_squared() + 2. * relative.mu / distance).sqrt() - speed;
+        let lead = if vessel.max_acceleration > 0. {
+            burn / vessel.max_acceleration / 2. * speed / distance
+        } else {
+            0.
+        };
+
+        (sense * (periapsis_angle - relative.position.to_angle()) - lead).rem_euclid(TAU)
+    }
+}
+
+impl FlightProgram for EscapeProgram {
+    fn name(&self) -> &'static str {
+        "escape"
+    }
+
+    fn step(&mut self, vessel: &VesselState) -> FlightCommand {
+        let relative = &vessel.relative;
+        let horizontal = relative.up().perp() * Self::sense(vessel);
+
+        match self.phase {
+            EscapePhase::Ascent => {
+                if relative.apoapsis() >= relative.radius + ESCAPE_PARKING_ALTITUDE {
+                    self.phase = EscapePhase::Coast;
+                    return self.step(vessel);
+                }
+                FlightCommand::burn("ascent", AttitudeCommand::Hold(AttitudeHold::RadialOut), 1.)
+            }
+            EscapePhase::Coast => {
+                if relative.radial_speed() <= 0. {
+                    self.phase = EscapePhase::Circularize;
+                    return self.step(vessel);
+                }
+                FlightCommand::coast("coast to apoapsis", AttitudeCommand::Direction(horizontal))
+            }
+            EscapePhase::Circularize => {
+                let circular = horizontal * (relative.mu / relative.distance()).sqrt();
+                match_velocity(vessel, circular, "parking orbit").unwrap_or_else(|| {
+                    self.phase = EscapePhase::Wait(None);
+                    self.step(vessel)
+                })
+            }
+            EscapePhase::Wait(last) => {
+                let Some(excess) = self.excess_velocity(vessel) else {
+                    self.phase = EscapePhase::Burn;
+                    return self.step(vessel);
+                };
+                let remaining = Self::angle_to_departure(vessel, excess);
+                // The angle wraps around once the departure point has been reached
+                if last.is_some_and(|last| remaining > last + PI) {
+                    self.excess_speed = excess.length();
+                    self.phase = EscapePhase::Burn;
+                    return self.step(vessel);
+                }
+                self.phase = EscapePhase::Wait(Some(remaining));
+                FlightCommand::coast(
+                    "wait for departure",
+                    AttitudeCommand::Hold(AttitudeHold::Prograde),
+                )
+            }
+            EscapePhase::Burn => {
+                if relative.specific_energy() >= self.excess_speed.powi(2) / 2. {
+                    self.phase = EscapePhase::Done;
+                    return self.step(vessel);
+                }
+                FlightCommand::burn(
+                    "escape burn",
+                    AttitudeCommand::Hold(AttitudeHold::Prograde),
+                    1.,
+                )
+            }
+            EscapePhase::Done => FlightCommand::finished("escaping"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OrbitPhase {
+    Plan,
+    Transfer,
+    /// Waiting for the apsis at distance `target`, having started at distance `start`
+    Coast {
+        start: f32,
+        target: f32,
+    },
+    Circularize,
+    Done,
+}
+
+/// Settles into a circular orbit whose altitude lies within `band`
+///
+/// Coasts down to the periapsis when the trajectory already dips into the
+/// band, otherwise first burns onto an orbit with one apsis at the current
+/// distance and the other in the middle of the band. Either way the orbit is
+/// circularized at the apsis it coasts to.
+pub struct OrbitProgram {
+    band: RangeInclusive<f32>,
+    phase: OrbitPhase,
+}
+
+impl OrbitProgram {
+    pub fn new(band: RangeInclusive<f32>) -> Self {
+        Self {
+            band,
+            phase: OrbitPhase::Plan,
+        }
+    }
+}
+
+impl FlightProgram for OrbitProgram {
+    fn name(&self) -> &'static str {
+        "orbit"
+    }
+
+    fn step(&mut self, vessel: &VesselState) -> FlightCommand {
+        let relative = &vessel.relative;
+        let distance = relative.distance();
+
+        match self.phase {
+            OrbitPhase::Plan => {
+                let periapsis = relative.elements().periapsis;
+                self.phase = if self.band.contains(&(periapsis - relative.radius))
+                    && relative.radial_speed() < 0.
+                {
+                    OrbitPhase::Coast {
+                        start: distance,
+                        target: periapsis,
+                    }
+                } else {
+                    OrbitPhase::Transfer
+                };
+                self.step(vessel)
+            }
+            OrbitPhase::Transfer => {
+                let target = relative.radius + (self.band.start() + self.band.end()) / 2.;
+                // Speed at the current apsis of an orbit whose other apsis is the target
+                let speed = (2. * relative.mu * target / (distance * (distance + target))).sqrt();
+                match_velocity(vessel, relative.horizontal() * speed, "transfer burn")
+                    .unwrap_or_else(|| {
+                        self.phase = OrbitPhase::Coast {
+                            start: distance,
+                            target,
+                        };
+                        self.step(vessel)
+                    })
+            }
+            OrbitPhase::Coast { start, target } => {
+                let passed_apsis = if target > start {
+                    relative.radial_speed() <= 0.
+                } else {
+                    relative.radial_speed() >= 0.
+                };
+                // Right after a burn the radial speed hovers around zero
+                let halfway = (distance - start).abs() >= (target - start).abs() / 2.;
+                if passed_apsis && halfway {
+                    self.phase = OrbitPhase::Circularize;
+                    return self.step(vessel);
+                }
+                let phase = if target < start {
+                    "coast to periapsis"
+                } else {
+                    "coast to apoapsis"
+                };
+                FlightCommand::coast(phase, AttitudeCommand::Direction(relative.horizontal()))
+            }
+            OrbitPhase::Circularize => {
+                let circular = relative.horizontal() * (relative.mu / distance).sqrt();
+                match_velocity(vessel, circular, "circularize").unwrap_or_else(|| {
+                    let elements = relative.elements();
+                    let in_band = |radius: f32| self.band.contains(&(radius - relative.radius));
+                    self.phase =
+                        if in_band(elements.periapsis) && elements.apoapsis.is_some_and(in_band) {
+                            OrbitPhase::Done
+                        } else {
+                            OrbitPhase::Plan
+                        };
+                    self.step(vessel)
+                })
+            }
+            OrbitPhase::Done => FlightCommand::finished("in orbit"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum LandingPhase {
+    #[default]
+    Deorbit,
+    Descent,
+    Braking,
+    Touchdown,
+    Landed,
+}
+
+/// Brings the vessel down onto the central body
+///
+/// Drops the periapsis deep below the surface, coasts until stopping in time
+/// takes most of the engine's thrust, brakes against the velocity down to a
+/// slow descent just above the ground and then sets down upright.
+#[derive(Default)]
+pub struct LandingProgram {
+    phase: LandingPhase,
+}
+
+impl LandingProgram {
+    /// Deceleration along the flight path that stops the vessel at the final altitude
+    fn braking_acceleration(vessel: &VesselState) -> f32 {
+        let relative = &vessel.relative;
+        let speed = relative.velocity.length();
+        let sink = -relative.radial_speed();
+        if sink <= 0. {
+            return 0.;
+        }
+
+        // Distance left along a straight flight path, gravity pulls along it as well
+        let path = (vessel.altitude() - LANDING_FINAL_ALTITUDE).max(1.) * speed / sink;
+        speed * speed / (2. * path) + relative.gravity() * sink / speed
+    }
+
+    /// Throttle that makes the engine deliver `acceleration`
+    fn throttle(vessel: &VesselState, acceleration: f32) -> f32 {
+        if vessel.max_acceleration > 0. {
+            (acceleration / vessel.max_acceleration).min(1.)
+        } else {
+            1.
+        }
+    }
+
+    /// Holds a descent rate that shrinks with the altitude while killing the drift
+    fn touchdown(vessel: &VesselState) -> FlightCommand {
+        let relative = &vessel.relative;
+        let up = relative.up();
+        let climb = relative.radial_speed();
+        let drift = relative.velocity - up * climb;
+        let target_climb = -(vessel.altitude() * LANDING_DESCENT_RATE_GAIN)
+            .clamp(LANDING_TOUCHDOWN_SPEED, LANDING_FINAL_SPEED);
+
+        let mut acceleration = up
+            * (relative.gravity() + (target_climb - climb) / LANDING_RESPONSE_TIME)
+            - drift / LANDING_RESPONSE_TIME;
+        if acceleration.dot(up) <= 0. {
+            // Falling slower than wanted, gravity does the rest
+            return FlightCommand::coast("touchdown", AttitudeCommand::Direction(up));
+        }
+
+        // Close to the ground only tilt as far as still lands cleanly
+        let max_tilt = if vessel.altitude() > LANDING_UPRIGHT_ALTITUDE {
+            LANDING_MAX_TILT_DEGREES
+        } else {
+            LANDING_SAFE_TILT_DEGREES / 2.
+        }
+        .to_radians();
+        let tilt = up.angle_to(acceleration);
+        if tilt.abs() > max_tilt {
+            acceleration =
+                Vec2::from_angle(max_tilt.copysign(tilt)).rotate(up) * acceleration.length();
+        }
+
+        FlightCommand::burn(
+            "touchdown",
+            AttitudeCommand::Direction(acceleration.normalize()),
+            Self::throttle(vessel, acceleration.length()),
+        )
+    }
+}
+
+impl FlightProgram for LandingProgram {
+    fn name(&self) -> &'static str {
+        "landing"
+    }
+
+    fn step(&mut self, vessel: &VesselState) -> FlightCommand {
+        let relative = &vessel.relative;
+        if vessel.landed {
+            self.phase = LandingPhase::Landed;
+        }
+
+        match self.phase {
+            LandingPhase::Deorbit => {
+                let target = relative.radius * LANDING_DEORBIT_PERIAPSIS_RATIO;
+                if relative.elements().periapsis <= target {
+                    self.phase = LandingPhase::Descent;
+                    return self.step(vessel);
+                }
+                FlightCommand::burn(
+                    "deorbit burn",
+                    AttitudeCommand::Hold(AttitudeHold::Retrograde),
+                    1.,
+                )
+            }
+            LandingPhase::Descent => {
+                let needed = Self::braking_acceleration(vessel);
+                if needed >= LANDING_BRAKING_THRESHOLD * vessel.max_acceleration
+                    || vessel.altitude() < LANDING_FINAL_ALTITUDE
+                {
+                    self.phase = LandingPhase::Braking;
+                    return self.step(vessel);
+                }
+                FlightCommand::coast("descent", AttitudeCommand::Hold(AttitudeHold::Retrograde))
+            }
+            LandingPhase::Braking => {
+                if relative.velocity.length() < LANDING_FINAL_SPEED
+                    || vessel.altitude() < LANDING_FINAL_ALTITUDE
+                {
+                    self.phase = LandingPhase::Touchdown;
+                    return self.step(vessel);
+                }
+                FlightCommand::burn(
+                    "braking burn",
+                    AttitudeCommand::Hold(AttitudeHold::Retrograde),
+                    Self::throttle(vessel, Self::braking_acceleration(vessel)),
+                )
+            }
+            LandingPhase::Touchdown => Self::touchdown(vessel),
+            LandingPhase::Landed => FlightCommand::finished("landed"),
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use crate::components::attitude::AttitudeHold;
+use crate::constants::GAMEPAD_THROTTLE_DEADBAND;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+
+/// Something the player can do, bound to keys, mouse and gamepad buttons by [`InputMap`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    /// Raise the throttle by one step
+    ThrottleUp,
+    /// Lower the throttle by one step
+    ThrottleDown,
+    /// Raise the throttle smoothly while held
+    ThrottleRampUp,
+    /// Lower the throttle smoothly while held
+    ThrottleRampDown,
+    ThrottleFull,
+    ThrottleCut,
+    /// Torque counter-clockwise while held
+    RotateLeft,
+    /// Torque clockwise while held
+    RotateRight,
+    ToggleSas,
+    /// Switch SAS on in the given hold mode
+    SasHold(AttitudeHold),
+    CycleTarget,
+    ToggleFlightComputer,
+    Stage,
+    WarpDown,
+    WarpUp,
+    WarpToPeriapsis,
+    WarpToApoapsis,
+    WarpToSoiChange,
+    WarpToImpact,
+    /// Warp to the start of the selected maneuver's burn
+    WarpToManeuver,
+    /// Type in a mission time to warp to
+    WarpToTime,
+    Quicksave,
+    Quickload,
+    /// Held to turn the slot keys into saves
+    SaveModifier,
+    /// Held together with [`InputAction::SaveModifier`] to load instead
+    LoadModifier,
+    /// Numbered save slot, counting from zero
+    Slot(usize),
+    TogglePredictionMode,
+    CycleIntegrator,
+    ToggleDiagnostics,
+    PlanHohmann,
+    PlanBiElliptic,
+    /// Place, select and drag maneuver nodes
+    EditManeuver,
+    DeleteManeuver,
+    RecenterCamera,
+    /// Held to drag the camera around
+    PanCamera,
+}
+
+/// A physical input an action can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+/// Bindings of every action, rebind by replacing the entries
+#[derive(Resource, Clone, Debug)]
+pub struct InputMap {
+    pub bindings: HashMap<InputAction, Vec<Binding>>,
+    /// Analog gamepad button whose travel sets the throttle
+    pub throttle_axis: Option<GamepadButton>,
+    /// Gamepad stick axis that steers, right turns clockwise
+    pub steering_axis: Option<GamepadAxis>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::{Gamepad as Pad, Key, Mouse};
+        use InputAction::*;
+
+        let mut bindings = HashMap::default();
+        let mut bind = |action, inputs: &[Binding]| {
+            bindings.insert(action, inputs.to_vec());
+        };
+
+        bind(ThrottleUp, &[Key(KeyCode::ArrowUp)]);
+        bind(ThrottleDown, &[Key(KeyCode::ArrowDown)]);
+        bind(
+            ThrottleRampUp,
+            &[Key(KeyCode::ShiftLeft), Key(KeyCode::ShiftRight)],
+        );
+        bind(
+            ThrottleRampDown,
+            &[Key(KeyCode::ControlLeft), Key(KeyCode::ControlRight)],
+        );
+        bind(
+            ThrottleFull,
+            &[Key(KeyCode::KeyZ), Pad(GamepadButton::West)],
+        );
+        bind(ThrottleCut, &[Key(KeyCode::KeyX), Pad(GamepadButton::East)]);
+        bind(RotateLeft, &[Key(KeyCode::ArrowLeft)]);
+        bind(RotateRight, &[Key(KeyCode::ArrowRight)]);
+        bind(ToggleSas, &[Key(KeyCode::KeyT), Pad(GamepadButton::North)]);
+        let holds = [
+            (KeyCode::Digit1, AttitudeHold::Stability),
+            (KeyCode::Digit2, AttitudeHold::Prograde),
+            (KeyCode::Digit3, AttitudeHold::Retrograde),
+            (KeyCode::Digit4, AttitudeHold::RadialOut),
+            (KeyCode::Digit5, AttitudeHold::RadialIn),
+            (KeyCode::Digit6, AttitudeHold::Target),
+        ];
+        for (key, hold) in holds {
+            bind(SasHold(hold), &[Key(key)]);
+        }
+        bind(
+            CycleTarget,
+            &[Key(KeyCode::Tab), Pad(GamepadButton::Select)],
+        );
+        bind(ToggleFlightComputer, &[Key(KeyCode::KeyF)]);
+        bind(Stage, &[Key(KeyCode::Space), Pad(GamepadButton::South)]);
+        bind(
+            WarpDown,
+            &[Key(KeyCode::BracketLeft), Pad(GamepadButton::DPadLeft)],
+        );
+        bind(
+            WarpUp,
+            &[Key(KeyCode::BracketRight), Pad(GamepadButton::DPadRight)],
+        );
+        bind(WarpToPeriapsis, &[Key(KeyCode::KeyQ)]);
+        bind(WarpToApoapsis, &[Key(KeyCode::KeyE)]);
+        bind(WarpToSoiChange, &[Key(KeyCode::KeyR)]);
+        bind(WarpToImpact, &[Key(KeyCode::KeyV)]);
+        bind(WarpToManeuver, &[Key(KeyCode::KeyN)]);
+        bind(WarpToTime, &[Key(KeyCode::KeyM)]);
+        bind(Quicksave, &[Key(KeyCode::F5)]);
+        bind(Quickload, &[Key(KeyCode::F9)]);
+        // Ctrl and Shift already ramp the throttle
+        bind(
+            SaveModifier,
+            &[Key(KeyCode::AltLeft), Key(KeyCode::AltRight)],
+        );
+        bind(
+            LoadModifier,
+            &[Key(KeyCode::ShiftLeft), Key(KeyCode::ShiftRight)],
+        );
+        let slots = [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+        ];
+        for (index, key) in slots.into_iter().enumerate() {
+            bind(Slot(index), &[Key(key)]);
+        }
+        bind(TogglePredictionMode, &[Key(KeyCode::KeyP)]);
+        bind(CycleIntegrator, &[Key(KeyCode::KeyI)]);
+        bind(ToggleDiagnostics, &[Key(KeyCode::KeyG)]);
+        bind(PlanHohmann, &[Key(KeyCode::KeyH)]);
+        bind(PlanBiElliptic, &[Key(KeyCode::KeyB)]);
+        bind(EditManeuver, &[Mouse(MouseButton::Left)]);
+        bind(
+            DeleteManeuver,
+            &[Key(KeyCode::Delete), Key(KeyCode::Backspace)],
+        );
+        bind(
+            RecenterCamera,
+            &[Key(KeyCode::KeyC), Pad(GamepadButton::Start)],
+        );
+        bind(
+            PanCamera,
+            &[Mouse(MouseButton::Left), Mouse(MouseButton::Middle)],
+        );
+
+        Self {
+            bindings,
+            throttle_axis: Some(GamepadButton::RightTrigger2),
+            steering_axis: Some(GamepadAxis::LeftStickX),
+        }
+    }
+}
+
+impl InputMap {
+    /// Whether `binding` triggers `action`
+    pub fn is_bound(&self, action: InputAction, binding: Binding) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|bindings| bindings.contains(&binding))
+    }
+}
+
+/// What the player asked for this frame, in actions rather than inputs
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pressed: HashSet<InputAction>,
+    just_pressed: HashSet<InputAction>,
+    /// Presses this frame including keyboard auto-repeat
+    repeats: HashMap<InputAction, u32>,
+    /// Throttle the analog axis was moved to this frame
+    pub throttle: Option<f32>,
+    /// Analog steering from -1.0 (clockwise) to 1.0 (counter-clockwise)
+    pub steering: f32,
+    /// Analog throttle position the last time it moved
+    last_throttle: f32,
+    /// Set while a text field takes the keyboard, no actions fire meanwhile
+    pub text_entry: bool,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// How often the action fired this frame, counting keyboard auto-repeat
+    pub fn repeats(&self, action: InputAction) -> u32 {
+        self.repeats.get(&action).copied().unwrap_or_default()
+    }
+}
+
+/// Translates keyboard, mouse and gamepad input into [`ActionState`]
+///
+/// Runs once the input plugin has processed the frame's events, so every
+/// system in `Update` sees the same actions.
+pub fn update_action_state_system(
+    map: Res<InputMap>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut key_events: EventReader<KeyboardInput>,
+    gamepads: Query<&Gamepad>,
+    mut state: ResMut<ActionState>,
+) {
+    let pressed = |binding: &Binding| match *binding {
+        Binding::Key(key) => keyboard.pressed(key),
+        Binding::Mouse(button) => mouse.pressed(button),
+        Binding::Gamepad(button) => gamepads.iter().any(|pad| pad.pressed(button)),
+    };
+    let just_pressed = |binding: &Binding| match *binding {
+        Binding::Key(key) => keyboard.just_pressed(key),
+        Binding::Mouse(button) => mouse.just_pressed(button),
+        Binding::Gamepad(button) => gamepads.iter().any(|pad| pad.just_pressed(button)),
+    };
+
+    state.pressed.clear();
+    state.just_pressed.clear();
+    state.repeats.clear();
+    state.throttle = None;
+    state.steering = 0.;
+    if state.text_entry {
+        key_events.clear();
+        return;
+    }
+
+    for (action, bindings) in &map.bindings {
+        if bindings.iter().any(pressed) {
+            state.pressed.insert(*action);
+        }
+        // Keys count through their events below to pick up auto-repeat
+        let other_presses = bindings
+            .iter()
+            .filter(|binding| !matches!(binding, Binding::Key(_)) && just_pressed(binding))
+            .count() as u32;
+        if bindings.iter().any(just_pressed) {
+            state.just_pressed.insert(*action);
+        }
+        if other_presses > 0 {
+            *state.repeats.entry(*action).or_default() += other_presses;
+        }
+    }
+
+    for event in key_events.read() {
+        if event.state == ButtonState::Released {
+            continue;
+        }
+        for (action, bindings) in &map.bindings {
+            if bindings.contains(&Binding::Key(event.key_code)) {
+                *state.repeats.entry(*action).or_default() += 1;
+            }
+        }
+    }
+
+    let throttle = map
+        .throttle_axis
+        .and_then(|axis| gamepads.iter().find_map(|pad| pad.get(axis)));
+    if let Some(throttle) = throttle
+        && (throttle - state.last_throttle).abs() > GAMEPAD_THROTTLE_DEADBAND
+    {
+        state.last_throttle = throttle;
+        state.throttle = Some(throttle);
+    }
+
+    state.steering = map
+        .steering_axis
+        .and_then(|axis| gamepads.iter().find_map(|pad| pad.get(axis)))
+        .map_or(0., |x| -x);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::input::keyboard::{Key, NativeKey};
+
+    /// World with the default bindings and `keys` held down since this frame
+    fn holding(keys: &[KeyCode]) -> World {
+        let mut world = World::new();
+        world.init_resource::<InputMap>();
+        world.init_resource::<ActionState>();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        world.init_resource::<Events<KeyboardInput>>();
+        let mut keyboard = ButtonInput::<KeyCode>::default();
+        for key in keys {
+            keyboard.press(*key);
+        }
+        world.insert_resource(keyboard);
+        world
+    }
+
+    fn key_press(key_code: KeyCode) -> KeyboardInput {
+        KeyboardInput {
+            key_code,
+            logical_key: Key::Unidentified(NativeKey::Unidentified),
+            state: ButtonState::Pressed,
+            text: None,
+            repeat: true,
+            window: Entity::PLACEHOLDER,
+        }
+    }
+
+    fn update(world: &mut World) -> &ActionState {
+        world.run_system_once(update_action_state_system).unwrap();
+        world.resource::<ActionState>()
+    }
+
+    #[test]
+    fn modifiers_are_actions_of_their_own() {
+        let mut world = holding(&[KeyCode::AltLeft, KeyCode::ShiftRight, KeyCode::Digit1]);
+        let actions = update(&mut world);
+
+        assert!(actions.pressed(InputAction::SaveModifier));
+        assert!(actions.pressed(InputAction::LoadModifier));
+        // Shared keys fire every action they are bound to, the consumers sort out the chord
+        assert!(actions.pressed(InputAction::ThrottleRampUp));
+        assert!(actions.just_pressed(InputAction::Slot(0)));
+        assert!(actions.just_pressed(InputAction::SasHold(AttitudeHold::Stability)));
+        assert!(!actions.pressed(InputAction::ThrottleRampDown));
+    }
+
+    #[test]
+    fn held_keys_are_pressed_but_not_just_pressed() {
+        let mut world = holding(&[KeyCode::AltLeft]);
+        update(&mut world);
+        world.resource_mut::<ButtonInput<KeyCode>>().clear();
+        let actions = update(&mut world);
+
+        assert!(actions.pressed(InputAction::SaveModifier));
+        assert!(!actions.just_pressed(InputAction::SaveModifier));
+    }
+
+    #[test]
+    fn key_repeats_are_counted() {
+        let mut world = holding(&[KeyCode::ArrowUp]);
+        world.send_event(key_press(KeyCode::ArrowUp));
+        world.send_event(key_press(KeyCode::ArrowUp));
+        let actions = update(&mut world);
+
+        assert_eq!(actions.repeats(InputAction::ThrottleUp), 2);
+        assert_eq!(actions.repeats(InputAction::ThrottleDown), 0);
+    }
+
+    #[test]
+    fn text_entry_swallows_the_keyboard() {
+        let mut world = holding(&[KeyCode::AltLeft, KeyCode::Digit1]);
+        world.send_event(key_press(KeyCode::ArrowUp));
+        world.resource_mut::<ActionState>().text_entry = true;
+        let actions = update(&mut world);
+
+        assert!(!actions.pressed(InputAction::SaveModifier));
+        assert!(!actions.just_pressed(InputAction::Slot(0)));
+        assert_eq!(actions.repeats(InputAction::ThrottleUp), 0);
+    }
+}
@@ -0,0 +1,350 @@
+use crate::constants::{RK45_MAX_SUBSTEPS, RK45_TOLERANCE};
+use bevy::math::DVec3;
+
+/// Numerical method used to advance positions and velocities
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Integrator {
+    /// Second-order symplectic, one force evaluation per step
+    #[default]
+    VelocityVerlet,
+    /// Fourth-order symplectic composition of three Verlet steps
+    Yoshida4,
+    /// Classic fourth-order Runge-Kutta, not symplectic
+    Rk4,
+    /// Dormand-Prince 5(4), splitting each step into substeps to meet `RK45_TOLERANCE`
+    AdaptiveRk45,
+}
+
+/// Runge-Kutta matrix of the classic RK4 method
+const RK4_A: [&[f64]; 4] = [&[], &[0.5], &[0., 0.5], &[0., 0., 1.]];
+const RK4_B: [f64; 4] = [1. / 6., 1. / 3., 1. / 3., 1. / 6.];
+
+/// Runge-Kutta matrix of the Dormand-Prince method
+const DP_A: [&[f64]; 7] = [
+    &[],
+    &[1. / 5.],
+    &[3. / 40., 9. / 40.],
+    &[44. / 45., -56. / 15., 32. / 9.],
+    &[
+        19372. / 6561.,
+        -25360. / 2187.,
+        64448. / 6561.,
+        -212. / 729.,
+    ],
+    &[
+        9017. / 3168.,
+        -355. / 33.,
+        46732. / 5247.,
+        49. / 176.,
+        -5103. / 18656.,
+    ],
+    &[
+        35. / 384.,
+        0.,
+        500. / 1113.,
+        125. / 192.,
+        -2187. / 6784.,
+        11. / 84.,
+    ],
+];
+/// Fifth-order weights of the Dormand-Prince method
+const DP_B: [f64; 7] = [
+    35. / 384.,
+    0.,
+    500. / 1113.,
+    125. / 192.,
+    -2187. / 6784.,
+    11. / 84.,
+    0.,
+];
+/// Difference between the fifth- and fourth-order weights, estimating the error
+const DP_E: [f64; 7] = [
+    71. / 57600.,
+    0.,
+    -71. / 16695.,
+    71. / 1920.,
+    -17253. / 339200.,
+    22. / 525.,
+    -1. / 40.,
+];
+
+impl Integrator {
+    pub const ALL: [Integrator; 4] = [
+        Integrator::VelocityVerlet,
+        Integrator::Yoshida4,
+        Integrator::Rk4,
+        Integrator::AdaptiveRk45,
+    ];
+
+    /// The next method in `ALL`, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|i| *i == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Integrator::VelocityVerlet => "Velocity Verlet",
+            Integrator::Yoshida4 => "Yoshida 4",
+            Integrator::Rk4 => "RK4",
+            Integrator::AdaptiveRk45 => "adaptive RK45",
+        }
+    }
+
+    /// Advances `positions` and `velocities` by `dt` given the acceleration at a set of positions
+    pub fn step(
+        self,
+        positions: &mut [DVec3],
+        velocities: &mut [DVec3],
+        dt: f64,
+        accel: impl Fn(&[DVec3]) -> Vec<DVec3>,
+    ) {
+        match self {
+            Integrator::VelocityVerlet => velocity_verlet(positions, velocities, dt, &accel),
+            Integrator::Yoshida4 => yoshida4(positions, velocities, dt, &accel),
+            Integrator::Rk4 => {
+                let (kx, kv) = runge_kutta_stages(positions, velocities, dt, &RK4_A, &accel);
+                apply_weights(positions, velocities, dt, &RK4_B, &kx, &kv);
+            }
+            Integrator::AdaptiveRk45 => {
+                adaptive_rk45(positions, velocities, dt, &accel);
+            }
+        }
+    }
+}
+
+fn velocity_verlet(
+    positions: &mut [DVec3],
+    velocities: &mut [DVec3],
+    dt: f64,
+    accel: &impl Fn(&[DVec3]) -> Vec<DVec3>,
+) {
+    let accel_1 = accel(positions);
+    for ((position, velocity), a) in positions.iter_mut().zip(velocities.iter()).zip(&accel_1) {
+        *position += *velocity * dt + 0.5 * *a * dt * dt;
+    }
+
+    let accel_2 = accel(positions);
+    for ((velocity, a1), a2) in velocities.iter_mut().zip(&accel_1).zip(&accel_2) {
+        *velocity += 0.5 * (*a1 + *a2) * dt;
+    }
+}
+
+fn yoshida4(
+    positions: &mut [DVec3],
+    velocities: &mut [DVec3],
+    dt: f64,
+    accel: &impl Fn(&[DVec3]) -> Vec<DVec3>,
+) {
+    let cbrt_2 = 2f64.cbrt();
+    let w1 = 1. / (2. - cbrt_2);
+    let w0 = -cbrt_2 * w1;
+    let drifts = [w1 / 2., (w0 + w1) / 2., (w0 + w1) / 2., w1 / 2.];
+    let kicks = [w1, w0, w1];
+
+    for (drift, kick) in drifts.iter().zip(kicks) {
+        for (position, velocity) in positions.iter_mut().zip(velocities.iter()) {
+            *position += *velocity * drift * dt;
+        }
+        for (velocity, a) in velocities.iter_mut().zip(accel(positions)) {
+            *velocity += a * kick * dt;
+        }
+    }
+
+    for (position, velocity) in positions.iter_mut().zip(velocities.iter()) {
+        *position += *velocity * drifts[3] * dt;
+    }
+}
+
+/// Returns the number of substeps taken, rejected attempts not counted
+fn adaptive_rk45(
+    positions: &mut [DVec3],
+    velocities: &mut [DVec3],
+    dt: f64,
+    accel: &impl Fn(&[DVec3]) -> Vec<DVec3>,
+) -> u32 {
+    let min_step = dt / RK45_MAX_SUBSTEPS as f64;
+    let mut remaining = dt;
+    let mut h = dt;
+    let mut substeps = 0;
+
+    while remaining > 0. {
+        h = h.min(remaining);
+        let (kx, kv) = runge_kutta_stages(positions, velocities, h, &DP_A, accel);
+
+        // Position error relative to the distance from the origin
+        let error = positions
+            .iter()
+            .enumerate()
+            .map(|(body, position)| {
+                let delta = h * DP_E
+                    .iter()
+                    .zip(&kx)
+                    .map(|(e, k)| *e * k[body])
+                    .sum::<DVec3>();
+                delta.length() / (RK45_TOLERANCE * position.length().max(1.))
+            })
+            .fold(0., f64::max);
+
+        if error <= 1. || h <= min_step {
+            apply_weights(positions, velocities, h, &DP_B, &kx, &kv);
+            remaining -= h;
+            substeps += 1;
+        }
+
+        let factor = if error > 0. {
+            (0.9 * error.powf(-0.2)).clamp(0.2, 5.)
+        } else {
+            5.
+        };
+        h = (h * factor).max(min_step);
+    }
+    substeps
+}
+
+/// Stage derivatives of an explicit Runge-Kutta method for x' = v, v' = a(x)
+fn runge_kutta_stages(
+    positions: &[DVec3],
+    velocities: &[DVec3],
+    dt: f64,
+    matrix: &[&[f64]],
+    accel: &impl Fn(&[DVec3]) -> Vec<DVec3>,
+) -> (Vec<Vec<DVec3>>, Vec<Vec<DVec3>>) {
+    let mut kx: Vec<Vec<DVec3>> = Vec::with_capacity(matrix.len());
+    let mut kv: Vec<Vec<DVec3>> = Vec::with_capacity(matrix.len());
+
+    for row in matrix {
+        let stage_positions: Vec<DVec3> = (0..positions.len())
+            .map(|body| {
+                positions[body]
+                    + dt * row
+                        .iter()
+                        .zip(&kx)
+                        .map(|(a, k)| *a * k[body])
+                        .sum::<DVec3>()
+            })
+            .collect();
+        let stage_velocities: Vec<DVec3> = (0..velocities.len())
+            .map(|body| {
+                velocities[body]
+                    + dt * row
+                        .iter()
+                        .zip(&kv)
+                        .map(|(a, k)| *a * k[body])
+                        .sum::<DVec3>()
+            })
+            .collect();
+
+        kv.push(accel(&stage_positions));
+        kx.push(stage_velocities);
+    }
+
+    (kx, kv)
+}
+
+fn apply_weights(
+    positions: &mut [DVec3],
+    velocities: &mut [DVec3],
+    dt: f64,
+    weights: &[f64],
+    kx: &[Vec<DVec3>],
+    kv: &[Vec<DVec3>],
+) {
+    for (body, (position, velocity)) in positions.iter_mut().zip(velocities.iter_mut()).enumerate()
+    {
+        *position += dt
+            * weights
+                .iter()
+                .zip(kx)
+                .map(|(b, k)| *b * k[body])
+                .sum::<DVec3>();
+        *velocity += dt
+            * weights
+                .iter()
+                .zip(kv)
+                .map(|(b, k)| *b * k[body])
+                .sum::<DVec3>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orbital::propagate_kepler;
+
+    const MU: f64 = 4.0e14;
+    const RADIUS: f64 = 7.0e6;
+
+    fn gravity(positions: &[DVec3]) -> Vec<DVec3> {
+        positions
+            .iter()
+            .map(|p| -MU * *p / p.length().powi(3))
+            .collect()
+    }
+
+    fn energy(position: DVec3, velocity: DVec3) -> f64 {
+        velocity.length_squared() / 2. - MU / position.length()
+    }
+
+    /// Largest relative energy error over two revolutions of a circular orbit
+    fn circular_energy_error(integrator: Integrator, dt: f64) -> f64 {
+        let mut positions = [DVec3::new(RADIUS, 0., 0.)];
+        let mut velocities = [DVec3::new(0., (MU / RADIUS).sqrt(), 0.)];
+        let start = energy(positions[0], velocities[0]);
+        let period = std::f64::consts::TAU * (RADIUS.powi(3) / MU).sqrt();
+
+        let mut worst: f64 = 0.;
+        for _ in 0..(2. * period / dt) as usize {
+            integrator.step(&mut positions, &mut velocities, dt, gravity);
+            let error = ((energy(positions[0], velocities[0]) - start) / start).abs();
+            worst = worst.max(error);
+        }
+        worst
+    }
+
+    #[test]
+    fn energy_error_is_bounded() {
+        for integrator in Integrator::ALL {
+            let error = circular_energy_error(integrator, 30.);
+            assert!(error < 1e-4, "{} drifted by {error}", integrator.label());
+        }
+    }
+
+    #[test]
+    fn fourth_order_methods_beat_verlet() {
+        let verlet = circular_energy_error(Integrator::VelocityVerlet, 60.);
+        for integrator in [Integrator::Yoshida4, Integrator::Rk4] {
+            let error = circular_energy_error(integrator, 60.);
+            assert!(
+                error < verlet / 10.,
+                "{} drifted by {error}, Verlet by {verlet}",
+                integrator.label()
+            );
+        }
+    }
+
+    #[test]
+    fn adaptive_rk45_meets_tolerance() {
+        // Half an hour around the periapsis of an eccentric orbit in a single step
+        let periapsis = RADIUS;
+        let speed = (MU * 1.5 / periapsis).sqrt();
+        let (position, velocity) = (DVec3::new(periapsis, 0., 0.), DVec3::new(0., speed, 0.));
+        let (start, start_velocity) = propagate_kepler(position, velocity, MU, -900.).unwrap();
+        let dt = 1800.;
+
+        let (mut positions, mut velocities) = ([start], [start_velocity]);
+        let substeps = adaptive_rk45(&mut positions, &mut velocities, dt, &gravity);
+
+        // Every substep but the last covers at least the smallest allowed share of the step
+        assert!(substeps > 1, "the step was not split");
+        assert!(substeps <= RK45_MAX_SUBSTEPS, "{substeps} substeps");
+
+        let (expected, _) = propagate_kepler(start, start_velocity, MU, dt).unwrap();
+        let error = (positions[0] - expected).length() / expected.length();
+        // The tolerance holds per substep, their errors add up over the step
+        assert!(
+            error < 10. * RK45_TOLERANCE,
+            "error {error} after {substeps} substeps"
+        );
+    }
+}
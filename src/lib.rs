@@ -1,62 +1,188 @@
 use crate::config::Config;
-use crate::systems::{objectives, ui, user_control};
+use crate::input::{ActionState, InputMap};
+use crate::scenario::{Scenario, ScenarioLoader};
+use crate::systems::{
+    appearance, flight_computer, maneuver, objectives, orbit, save_game, staging, transfer, ui,
+    user_control, warp,
+};
+use bevy::input::InputSystem;
 use bevy::prelude::*;
+use systems::physics::PhysicsStep;
 use systems::{camera, physics, prediction, world_setup};
 
-mod components;
-mod config;
-mod constants;
-mod systems;
+pub mod components;
+pub mod config;
+pub mod constants;
+pub mod flight_program;
+pub mod input;
+pub mod integrator;
+pub mod orbital;
+pub mod persistence;
+pub mod planner;
+pub mod scenario;
+pub mod systems;
 
+/// The full interactive game: window, simulation and presentation
 pub struct Game;
 
 impl Plugin for Game {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Config {
-            dt: 1. / 64.,
-            time_multiplier: 1,
-        });
+        app.add_plugins(DefaultPlugins);
+        app.add_plugins((SimulationPlugin::default(), PresentationPlugin));
+    }
+}
+
+/// Windowless core of the game
+///
+/// Contains the world setup, the `FixedUpdate` physics and the objectives, and
+/// only relies on what `MinimalPlugins` and `AssetPlugin` provide, so it can be
+/// stepped in CI, integration tests and batch runs on machines without a GPU.
+pub struct SimulationPlugin {
+    /// Asset path of the scenario the world is built from
+    pub scenario: String,
+}
+
+impl Default for SimulationPlugin {
+    fn default() -> Self {
+        Self {
+            scenario: "scenarios/default.scenario.ron".to_string(),
+        }
+    }
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Scenario>();
+        app.init_asset_loader::<ScenarioLoader>();
+        app.insert_resource(world_setup::ScenarioPath(self.scenario.clone()));
+
+        app.insert_resource(Config::default());
 
-        app.insert_resource(camera::DragState::default());
-        app.insert_resource(camera::CameraOffset::default());
         app.insert_resource(Time::<Fixed>::from_hz(64.));
-        app.add_plugins(DefaultPlugins);
+        app.insert_resource(physics::MissionClock::default());
+        app.insert_resource(physics::ConservationDiagnostics::default());
+        app.insert_resource(physics::FloatingOrigin::default());
+        app.insert_resource(physics::SoiHierarchy::default());
+        app.add_systems(Startup, world_setup::load_scenario);
+        app.add_systems(PreUpdate, world_setup::create_world);
+
         app.add_systems(
-            Startup,
+            PhysicsStep,
             (
-                world_setup::create_world,
-                camera::create_camera,
-                ui::create_ui,
+                physics::autopilot_system,
+                flight_computer::flight_computer_system,
+                physics::attitude_system,
+                physics::propulsion_system,
+                physics::atmosphere_system,
+                physics::gravity_system,
+                physics::soi_system,
+                physics::collision_system,
             )
                 .chain(),
         );
-
         app.add_systems(
             FixedUpdate,
             (
-                physics::gravity_system,
-                physics::propulsion_system,
-                physics::apply_force_system,
-                physics::collision_system,
+                physics::physics_step_system,
+                orbit::orbit_system,
+                objectives::objectives_system,
+                physics::diagnostics_system,
             )
                 .chain(),
         );
+        // Transforms are derived once per frame, after all fixed steps have run
+        app.add_systems(
+            RunFixedMainLoop,
+            physics::floating_origin_system.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+        );
 
+        app.add_event::<physics::ContactEvent>();
+        app.add_event::<physics::SoiChangeEvent>();
+        app.add_event::<save_game::SaveRequest>();
+        app.add_event::<save_game::LoadRequest>();
+        app.add_event::<staging::StageRequest>();
+        app.add_event::<flight_computer::WarpLimitRequest>();
         app.add_systems(
             Update,
             (
-                prediction::calculate_predictions_system,
-                prediction::render_trajectory_predictions,
-                user_control::time_warp_system,
-                user_control::steering_system,
-                user_control::thrust_adjust_system,
+                physics::log_diagnostics_system,
+                staging::staging_system,
+                flight_computer::warp_limit_system,
+                (save_game::save_game_system, save_game::load_game_system).chain(),
+            ),
+        );
+    }
+}
+
+/// Rendering, camera, HUD and player input on top of [`SimulationPlugin`]
+///
+/// Expects the window, input and render plugins from `DefaultPlugins`.
+pub struct PresentationPlugin;
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(camera::DragState::default());
+        app.insert_resource(camera::CameraOffset::default());
+        app.insert_resource(maneuver::ManeuverEditor::default());
+        app.insert_resource(warp::WarpTo::default());
+        app.init_resource::<InputMap>();
+        app.init_resource::<ActionState>();
+        app.add_systems(
+            PreUpdate,
+            input::update_action_state_system.after(InputSystem),
+        );
+        app.add_systems(
+            Startup,
+            (
+                camera::create_camera,
+                ui::create_ui,
+                ui::create_diagnostics_ui,
+            ),
+        );
+
+        app.add_systems(
+            Update,
+            (
+                appearance::attach_meshes_system,
+                (
+                    maneuver::expire_maneuver_nodes_system,
+                    prediction::request_predictions_system,
+                    prediction::collect_predictions_system,
+                    prediction::update_prediction_points_system,
+                    (
+                        prediction::render_trajectory_predictions,
+                        maneuver::render_maneuvers_system,
+                    ),
+                )
+                    .chain(),
+                maneuver::maneuver_input_system.before(camera::pan_camera),
+                transfer::transfer_planner_system,
+                (
+                    (
+                        warp::warp_to_input_system,
+                        warp::warp_interrupt_system,
+                        user_control::time_warp_system,
+                    )
+                        .chain()
+                        .before(flight_computer::warp_limit_system),
+                    user_control::steering_system,
+                    user_control::autopilot_input_system,
+                    user_control::flight_computer_input_system,
+                    user_control::thrust_adjust_system,
+                    user_control::staging_input_system,
+                    user_control::save_load_input_system,
+                    user_control::prediction_mode_system,
+                    user_control::integrator_select_system,
+                    user_control::diagnostics_toggle_system,
+                ),
                 camera::recenter_camera_on_user,
                 camera::zoom_camera,
                 camera::pan_camera,
                 camera::camera_follow_user,
                 camera::ignore_camera_scale_for_users,
-                objectives::objectives_system,
                 ui::update_ui_system,
+                ui::render_diagnostics_system,
+                ui::render_attitude_indicators_system,
             ),
         );
     }
@@ -0,0 +1,431 @@
+use crate::constants::{KEPLER_MAX_ITERATIONS, KEPLER_TOLERANCE};
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+/// Classical orbital elements of a two-body orbit in the XY plane
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitalElements {
+    /// Semi-major axis in meters, negative for hyperbolic orbits
+    pub semi_major_axis: f32,
+    /// Eccentricity, below 1 for closed orbits
+    pub eccentricity: f32,
+    /// Closest distance to the focus in meters
+    pub periapsis: f32,
+    /// Farthest distance from the focus in meters, `None` for open orbits
+    pub apoapsis: Option<f32>,
+    /// Angle from the +X axis to the periapsis in radians
+    pub argument_of_periapsis: f32,
+    /// Angle from the periapsis to the current position in radians, within [0, TAU)
+    pub true_anomaly: f32,
+    /// Orbital period in seconds, `None` for open orbits
+    pub period: Option<f32>,
+    /// Average rate of the mean anomaly in radians per second
+    pub mean_motion: f32,
+    /// Whether the orbit runs clockwise
+    pub retrograde: bool,
+}
+
+impl OrbitalElements {
+    /// Computes the elements from a position and velocity relative to the focus
+    ///
+    /// `mu` is the standard gravitational parameter `G * (M + m)`.
+    pub fn from_state_vectors(position: Vec3, velocity: Vec3, mu: f32) -> Self {
+        let r = position.truncate();
+        let v = velocity.truncate();
+        let distance = r.length();
+        let speed_sq = v.length_squared();
+
+        // z-component of the specific angular momentum
+        let angular_momentum = r.perp_dot(v);
+        let retrograde = angular_momentum < 0.;
+
+        let specific_energy = speed_sq / 2. - mu / distance;
+        let semi_major_axis = -mu / (2. * specific_energy);
+
+        let eccentricity_vec = ((speed_sq - mu / distance) * r - r.dot(v) * v) / mu;
+        let eccentricity = eccentricity_vec.length();
+
+        let semi_latus_rectum = angular_momentum * angular_momentum / mu;
+        let periapsis = semi_latus_rectum / (1. + eccentricity);
+        let apoapsis = (eccentricity < 1.).then(|| semi_latus_rectum / (1. - eccentricity));
+
+        // Circular orbits have no periapsis, measure from the +X axis instead
+        let argument_of_periapsis = if eccentricity > 1e-6 {
+            eccentricity_vec.to_angle()
+        } else {
+            0.
+        };
+
+        let mut true_anomaly = r.to_angle() - argument_of_periapsis;
+        if retrograde {
+            true_anomaly = -true_anomaly;
+        }
+
+        let period = (eccentricity < 1.).then(|| TAU * (semi_major_axis.powi(3) / mu).sqrt());
+        let mean_motion = (mu / semi_major_axis.abs().powi(3)).sqrt();
+
+        Self {
+            semi_major_axis,
+            eccentricity,
+            periapsis,
+            apoapsis,
+            argument_of_periapsis,
+            true_anomaly: true_anomaly.rem_euclid(TAU),
+            period,
+            mean_motion,
+            retrograde,
+        }
+    }
+
+    /// Whether the orbit is closed (elliptical or circular)
+    pub fn is_closed(&self) -> bool {
+        self.eccentricity < 1.
+    }
+
+    /// Seconds until the orbit next reaches the given true anomaly
+    ///
+    /// `None` on open orbits that have already passed it or never reach it.
+    pub fn time_to_true_anomaly(&self, true_anomaly: f32) -> Option<f32> {
+        let e = self.eccentricity;
+        if let Some(period) = self.period {
+            let mean_anomaly = |true_anomaly: f32| {
+                let eccentric_anomaly = 2.
+                    * ((1. - e).sqrt() * (true_anomaly / 2.).sin())
+                        .atan2((1. + e).sqrt() * (true_anomaly / 2.).cos());
+                eccentric_anomaly - e * eccentric_anomaly.sin()
+            };
+            let remaining =
+                (mean_anomaly(true_anomaly) - mean_anomaly(self.true_anomaly)).rem_euclid(TAU);
+            return Some(remaining / TAU * period);
+        }
+
+        // Open orbits only span the true anomalies between the asymptotes
+        let limit = (-1. / e).acos();
+        let mean_anomaly = |true_anomaly: f32| {
+            let true_anomaly = (true_anomaly + PI).rem_euclid(TAU) - PI;
+            (true_anomaly.abs() < limit).then(|| {
+                let hyperbolic_anomaly =
+                    2. * (((e - 1.) / (e + 1.)).sqrt() * (true_anomaly / 2.).tan()).atanh();
+                e * hyperbolic_anomaly.sinh() - hyperbolic_anomaly
+            })
+        };
+        let remaining = mean_anomaly(true_anomaly)? - mean_anomaly(self.true_anomaly)?;
+        (remaining >= 0.).then(|| remaining / self.mean_motion)
+    }
+}
+
+/// Position and velocity relative to the focus on the orbit described by the elements
+///
+/// Angles are in radians, `retrograde` orbits run clockwise.
+pub fn state_from_elements(
+    mu: f32,
+    semi_major_axis: f32,
+    eccentricity: f32,
+    argument_of_periapsis: f32,
+    true_anomaly: f32,
+    retrograde: bool,
+) -> (Vec3, Vec3) {
+    let p = semi_major_axis * (1. - eccentricity * eccentricity);
+    let r = p / (1. + eccentricity * true_anomaly.cos());
+    let direction = if retrograde { -1. } else { 1. };
+
+    // Perifocal frame: x towards periapsis, y along the direction of motion
+    let position = Vec2::new(r * true_anomaly.cos(), direction * r * true_anomaly.sin());
+    let speed_factor = (mu / p).sqrt();
+    let velocity = Vec2::new(
+        -speed_factor * true_anomaly.sin(),
+        direction * speed_factor * (eccentricity + true_anomaly.cos()),
+    );
+
+    let rotation = Vec2::from_angle(argument_of_periapsis);
+    (
+        rotation.rotate(position).extend(0.),
+        rotation.rotate(velocity).extend(0.),
+    )
+}
+
+/// Prograde and radial-out unit vectors for burns at the given relative state
+///
+/// Radial-out is perpendicular to prograde and points away from the central body.
+pub fn maneuver_frame(position: Vec3, velocity: Vec3) -> (Vec3, Vec3) {
+    let prograde = velocity.normalize_or_zero();
+    let radial = prograde.truncate().perp().extend(0.);
+    if radial.dot(position) < 0. {
+        (prograde, -radial)
+    } else {
+        (prograde, radial)
+    }
+}
+
+/// Moves a relative state `dt` seconds along its two-body orbit
+///
+/// Uses the universal anomaly, so closed, parabolic and hyperbolic orbits are
+/// all handled, in double precision as the result replaces integrated states.
+/// `None` when Newton's method doesn't converge within `KEPLER_MAX_ITERATIONS`.
+pub fn propagate_kepler(
+    position: DVec3,
+    velocity: DVec3,
+    mu: f64,
+    dt: f64,
+) -> Option<(DVec3, DVec3)> {
+    let r0 = position.length();
+    let sqrt_mu = mu.sqrt();
+    let radial_velocity = position.dot(velocity) / r0;
+    // Reciprocal of the semi-major axis, negative for hyperbolic orbits
+    let alpha = 2. / r0 - velocity.length_squared() / mu;
+
+    // Newton's method on the universal Kepler equation
+    let mut chi = if alpha >= 0. {
+        sqrt_mu * alpha * dt
+    } else {
+        // The elliptic guess overshoots on hyperbolas by far more than Newton can walk back
+        let semi_major_axis = 1. / alpha;
+        let sign = dt.signum();
+        sign * (-semi_major_axis).sqrt()
+            * (-2. * mu * alpha * dt
+                / (position.dot(velocity)
+                    + sign * (-mu * semi_major_axis).sqrt() * (1. - r0 * alpha)))
+                .ln()
+    };
+    let mut converged = false;
+    for _ in 0..KEPLER_MAX_ITERATIONS {
+        let z = alpha * chi * chi;
+        let (c, s) = stumpff(z);
+        let f = r0 * radial_velocity / sqrt_mu * chi * chi * c
+            + (1. - alpha * r0) * chi.powi(3) * s
+            + r0 * chi
+            - sqrt_mu * dt;
+        let df = r0 * radial_velocity / sqrt_mu * chi * (1. - z * s)
+            + (1. - alpha * r0) * chi * chi * c
+            + r0;
+        let step = f / df;
+        chi -= step;
+        if step.abs() <= KEPLER_TOLERANCE * chi.abs().max(1.) {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        return None;
+    }
+
+    let z = alpha * chi * chi;
+    let (c, s) = stumpff(z);
+    let f = 1. - chi * chi / r0 * c;
+    let g = dt - chi.powi(3) / sqrt_mu * s;
+    let new_position = f * position + g * velocity;
+    let r = new_position.length();
+    let f_dot = sqrt_mu / (r * r0) * (z * s - 1.) * chi;
+    let g_dot = 1. - chi * chi / r * c;
+    Some((new_position, f_dot * position + g_dot * velocity))
+}
+
+/// Stumpff functions `C(z)` and `S(z)` of the universal anomaly
+fn stumpff(z: f64) -> (f64, f64) {
+    if z > 1e-6 {
+        let root = z.sqrt();
+        ((1. - root.cos()) / z, (root - root.sin()) / root.powi(3))
+    } else if z < -1e-6 {
+        let root = (-z).sqrt();
+        ((root.cosh() - 1.) / -z, (root.sinh() - root) / root.powi(3))
+    } else {
+        // Series expansion, the closed forms cancel catastrophically near zero
+        (
+            0.5 - z / 24. + z * z / 720.,
+            1. / 6. - z / 120. + z * z / 5040.,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MU: f32 = 4.0e14;
+
+    fn assert_close(actual: f32, expected: f32, relative: f32) {
+        assert!(
+            (actual - expected).abs() <= relative * expected.abs(),
+            "{actual} is not within {relative} of {expected}"
+        );
+    }
+
+    #[test]
+    fn circular_orbit_elements() {
+        let radius = 7.0e6;
+        let speed = (MU / radius).sqrt();
+        let elements = OrbitalElements::from_state_vectors(Vec3::X * radius, Vec3::Y * speed, MU);
+
+        assert!(elements.eccentricity < 1e-4);
+        assert!(elements.is_closed());
+        assert!(!elements.retrograde);
+        assert_close(elements.semi_major_axis, radius, 1e-4);
+        assert_close(elements.periapsis, radius, 1e-4);
+        assert_close(elements.apoapsis.unwrap(), radius, 1e-4);
+    }
+
+    #[test]
+    fn elliptic_round_trip() {
+        let (semi_major_axis, eccentricity) = (1.0e7, 0.3);
+        for (true_anomaly, retrograde) in [(0.4, false), (2.5, false), (4.0, true)] {
+            let (position, velocity) = state_from_elements(
+                MU,
+                semi_major_axis,
+                eccentricity,
+                1.2,
+                true_anomaly,
+                retrograde,
+            );
+            let elements = OrbitalElements::from_state_vectors(position, velocity, MU);
+
+            assert_close(elements.semi_major_axis, semi_major_axis, 1e-4);
+            assert_close(elements.eccentricity, eccentricity, 1e-4);
+            assert_close(
+                elements.periapsis,
+                semi_major_axis * (1. - eccentricity),
+                1e-4,
+            );
+            assert_close(
+                elements.apoapsis.unwrap(),
+                semi_major_axis * (1. + eccentricity),
+                1e-4,
+            );
+            assert_close(elements.argument_of_periapsis, 1.2, 1e-4);
+            assert_close(elements.true_anomaly, true_anomaly, 1e-4);
+            assert_eq!(elements.retrograde, retrograde);
+        }
+    }
+
+    #[test]
+    fn half_period_to_apoapsis() {
+        let (position, velocity) = state_from_elements(MU, 1.0e7, 0.3, 0., 0., false);
+        let elements = OrbitalElements::from_state_vectors(position, velocity, MU);
+        let period = elements.period.unwrap();
+
+        assert_close(period, TAU * (1.0e21_f32 / MU).sqrt(), 1e-4);
+        assert_close(
+            elements.time_to_true_anomaly(PI).unwrap(),
+            period / 2.,
+            1e-4,
+        );
+    }
+
+    #[test]
+    fn hyperbolic_time_to_periapsis() {
+        // Inbound on an open orbit, the periapsis lies ahead and the past anomalies don't
+        let (position, velocity) = state_from_elements(MU, -1.0e7, 1.5, 0., -1.0, false);
+        let elements = OrbitalElements::from_state_vectors(position, velocity, MU);
+        assert!(elements.period.is_none());
+
+        let time = elements.time_to_true_anomaly(0.).unwrap();
+        let hyperbolic_anomaly = 2. * ((0.5_f32 / 2.5).sqrt() * (-0.5_f32).tan()).atanh();
+        let mean_anomaly = 1.5 * hyperbolic_anomaly.sinh() - hyperbolic_anomaly;
+        assert_close(time, -mean_anomaly / elements.mean_motion, 1e-3);
+        assert!(elements.time_to_true_anomaly(-1.5).is_none());
+    }
+
+    /// Central gravity of a point mass, for integrating reference trajectories
+    fn integrate(position: DVec3, velocity: DVec3, mu: f64, duration: f64) -> (DVec3, DVec3) {
+        let steps = 10_000;
+        let (mut positions, mut velocities) = ([position], [velocity]);
+        for _ in 0..steps {
+            crate::integrator::Integrator::Rk4.step(
+                &mut positions,
+                &mut velocities,
+                duration / steps as f64,
+                |positions| {
+                    positions
+                        .iter()
+                        .map(|p| -mu * *p / p.length().powi(3))
+                        .collect()
+                },
+            );
+        }
+        (positions[0], velocities[0])
+    }
+
+    #[test]
+    fn kepler_circular_full_period() {
+        let mu = MU as f64;
+        let radius = 7.0e6;
+        let position = DVec3::new(radius, 0., 0.);
+        let velocity = DVec3::new(0., (mu / radius).sqrt(), 0.);
+        let period = std::f64::consts::TAU * (radius.powi(3) / mu).sqrt();
+
+        let (end_position, end_velocity) =
+            propagate_kepler(position, velocity, mu, period).unwrap();
+        assert!((end_position - position).length() < 1e-6 * radius);
+        assert!((end_velocity - velocity).length() < 1e-6 * velocity.length());
+
+        // Half way round it sits on the opposite side, moving the other way
+        let (half_position, half_velocity) =
+            propagate_kepler(position, velocity, mu, period / 2.).unwrap();
+        assert!((half_position + position).length() < 1e-6 * radius);
+        assert!((half_velocity + velocity).length() < 1e-6 * velocity.length());
+    }
+
+    #[test]
+    fn kepler_elliptic_matches_integration() {
+        let mu = MU as f64;
+        let (position, velocity) = state_from_elements(MU, 1.0e7, 0.5, 0.3, 1.0, false);
+        let (position, velocity) = (position.as_dvec3(), velocity.as_dvec3());
+
+        let (kepler_position, kepler_velocity) =
+            propagate_kepler(position, velocity, mu, 2000.).unwrap();
+        let (numeric_position, numeric_velocity) = integrate(position, velocity, mu, 2000.);
+        assert!((kepler_position - numeric_position).length() < 1e-6 * position.length());
+        assert!((kepler_velocity - numeric_velocity).length() < 1e-6 * velocity.length());
+    }
+
+    #[test]
+    fn kepler_hyperbolic_matches_integration() {
+        let mu = MU as f64;
+        let (position, velocity) = state_from_elements(MU, -1.0e7, 1.8, 0., -1.2, false);
+        let (position, velocity) = (position.as_dvec3(), velocity.as_dvec3());
+
+        // Through the periapsis and out again
+        let (kepler_position, kepler_velocity) =
+            propagate_kepler(position, velocity, mu, 3000.).unwrap();
+        let (numeric_position, numeric_velocity) = integrate(position, velocity, mu, 3000.);
+        assert!((kepler_position - numeric_position).length() < 1e-6 * position.length());
+        assert!((kepler_velocity - numeric_velocity).length() < 1e-6 * velocity.length());
+
+        // Energy and angular momentum are those of the starting state
+        let energy = |p: DVec3, v: DVec3| v.length_squared() / 2. - mu / p.length();
+        let momentum = |p: DVec3, v: DVec3| p.truncate().perp_dot(v.truncate());
+        let start_energy = energy(position, velocity);
+        assert!(start_energy > 0.);
+        assert!(
+            (energy(kepler_position, kepler_velocity) - start_energy).abs() < 1e-9 * start_energy
+        );
+        assert!(
+            (momentum(kepler_position, kepler_velocity) - momentum(position, velocity)).abs()
+                < 1e-9 * momentum(position, velocity).abs()
+        );
+
+        // Propagating backwards undoes it
+        let (back_position, _) =
+            propagate_kepler(kepler_position, kepler_velocity, mu, -3000.).unwrap();
+        assert!((back_position - position).length() < 1e-6 * position.length());
+    }
+
+    #[test]
+    fn kepler_hyperbolic_long_coast() {
+        // Far beyond the reach of the elliptic first guess
+        let mu = MU as f64;
+        let (position, velocity) = (DVec3::X * 7.0e6, DVec3::new(-5.0e3, 3.0e4, 0.));
+        let (end_position, end_velocity) = propagate_kepler(position, velocity, mu, 1.0e9).unwrap();
+
+        let energy = |p: DVec3, v: DVec3| v.length_squared() / 2. - mu / p.length();
+        let start_energy = energy(position, velocity);
+        assert!((energy(end_position, end_velocity) - start_energy).abs() < 1e-9 * start_energy);
+        assert!(end_position.length() > 1e3 * position.length());
+    }
+
+    #[test]
+    fn kepler_reports_unsolved_equations() {
+        let velocity = DVec3::new(f64::NAN, 7.5e3, 0.);
+        assert!(propagate_kepler(DVec3::X * 7.0e6, velocity, MU as f64, 1000.).is_none());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::components::attitude::AttitudeHold;
+use crate::components::damage::DestructionCause;
+use crate::components::objectives::Objective;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Slot used by the quicksave and quickload keys
+pub const QUICKSAVE_SLOT: &str = "quicksave";
+/// Numbered slots `slot1` to `slot4` bound to keys
+pub const SAVE_SLOTS: usize = 4;
+
+/// Snapshot of the whole simulation
+///
+/// Entities are stored in a flat list, references between them (such as
+/// `PhysicsObject::central_body`) are saved as indices into that list so they
+/// can be remapped to fresh entities when loading.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveGame {
+    /// Time warp stage, index into `DT_STAGES`
+    pub warp_stage: usize,
+    /// Mission time in seconds
+    #[serde(default)]
+    pub mission_time: f64,
+    pub objects: Vec<SavedObject>,
+}
+
+/// A single simulated object
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedObject {
+    pub name: String,
+    pub position: [f64; 3],
+    /// Rotation quaternion as `[x, y, z, w]`
+    pub rotation: [f32; 4],
+    pub velocity: [f64; 3],
+    pub mass: f32,
+    pub radius: f32,
+    /// Index of the central body in `SaveGame::objects`
+    pub central_body: Option<usize>,
+    pub appearance: SavedAppearance,
+    /// Whether this is the player-controlled spacecraft
+    pub is_user: bool,
+    pub predict_trajectory: bool,
+    pub propulsion: Option<SavedPropulsion>,
+    pub objectives: Option<SavedObjectives>,
+    #[serde(default)]
+    pub maneuver_nodes: Vec<SavedManeuverNode>,
+    #[serde(default)]
+    pub atmosphere: Option<SavedAtmosphere>,
+    #[serde(default)]
+    pub aerodynamics: Option<SavedAerodynamics>,
+    #[serde(default)]
+    pub destroyed: Option<DestructionCause>,
+    /// Whether this is a jettisoned stage
+    #[serde(default)]
+    pub is_debris: bool,
+    #[serde(default)]
+    pub stages: Option<SavedStages>,
+    #[serde(default)]
+    pub attitude_control: Option<SavedAttitudeControl>,
+    /// Index of the selected target body in `SaveGame::objects`
+    #[serde(default)]
+    pub target: Option<usize>,
+    /// Whether the flight computer flies the mission, programs restart from
+    /// their first phase on load
+    #[serde(default)]
+    pub flight_computer: bool,
+    /// Planet or moon details, rebuilt from the other fields for older saves
+    #[serde(default)]
+    pub celestial_body: Option<SavedCelestialBody>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedManeuverNode {
+    /// Mission time of the burn in seconds
+    pub time: f64,
+    pub prograde: f32,
+    pub radial: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SavedShape {
+    Circle(f32),
+    Triangle([[f32; 2]; 3]),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedAppearance {
+    pub shape: SavedShape,
+    /// sRGBA color
+    pub color: [f32; 4],
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedPropulsion {
+    pub max_thrust: f32,
+    pub thrust_percentage: f32,
+    pub fuel: f32,
+    pub max_fuel: f32,
+    /// Specific impulse in seconds
+    pub specific_impulse: f32,
+    /// Mass without fuel in kg
+    pub dry_mass: f32,
+}
+
+/// Stages of a vessel besides the firing one
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedStages {
+    /// Mass of the firing stage's structure in kg
+    pub active_dry_mass: f32,
+    /// Stages still attached, the next one to fire first
+    pub upcoming: Vec<SavedStage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedStage {
+    pub dry_mass: f32,
+    pub fuel: f32,
+    pub max_fuel: f32,
+    pub max_thrust: f32,
+    pub specific_impulse: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedAttitudeControl {
+    /// Spin rate in rad/s
+    pub angular_velocity: f32,
+    pub wheel_torque: f32,
+    pub rcs_torque: f32,
+    pub monopropellant: f32,
+    pub max_monopropellant: f32,
+    pub monopropellant_rate: f32,
+    pub sas: bool,
+    #[serde(default)]
+    pub hold: AttitudeHold,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedAtmosphere {
+    pub sea_level_density: f32,
+    pub scale_height: f32,
+    pub height: f32,
+    pub heating_coefficient: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedCelestialBody {
+    /// Index of the parent body in `SaveGame::objects`
+    pub parent: Option<usize>,
+    pub min_warp_altitude: f32,
+    pub limited_warp_altitude: f32,
+    /// sRGBA display color
+    pub color: [f32; 4],
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedAerodynamics {
+    pub drag_coefficient: f32,
+    pub reference_area: f32,
+    pub nose_radius: f32,
+    pub thermal_time_constant: f32,
+    pub max_temperature: f32,
+    pub max_g_load: f32,
+    /// Hull temperature in Kelvin
+    pub temperature: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedObjectives {
+    pub current: Objective,
+    pub upcoming: Vec<Objective>,
+    pub completed: Vec<Objective>,
+    pub is_completed: bool,
+    pub completion_time: Option<f32>,
+    /// Mission seconds spent outside the moon's sphere of influence
+    #[serde(alias = "leo_elapsed")]
+    pub escape_elapsed: f32,
+    /// Degrees travelled in a valid orbit
+    #[serde(default)]
+    pub orbit_sweep: f32,
+}
+
+#[derive(Debug, Error)]
+pub enum SaveGameError {
+    #[error("invalid save slot name {0:?}")]
+    InvalidSlot(String),
+    #[error("save slot {0:?} does not exist")]
+    Missing(String),
+    #[error("could not access save storage: {0}")]
+    Storage(String),
+    #[error("could not write save: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("could not read save: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+}
+
+impl SaveGame {
+    /// Writes the save into the named slot
+    pub fn store(&self, slot: &str) -> Result<(), SaveGameError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        storage::write(validate_slot(slot)?, &text)
+    }
+
+    /// Reads the save from the named slot
+    pub fn restore(slot: &str) -> Result<Self, SaveGameError> {
+        let text = storage::read(validate_slot(slot)?)?;
+        Ok(ron::from_str(&text)?)
+    }
+}
+
+/// Slot names become file names or storage keys, so keep them simple
+fn validate_slot(slot: &str) -> Result<&str, SaveGameError> {
+    let valid = !slot.is_empty()
+        && slot
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(slot)
+    } else {
+        Err(SaveGameError::InvalidSlot(slot.to_string()))
+    }
+}
+
+/// Saves are plain files next to the executable's working directory
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use super::SaveGameError;
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+
+    const SAVE_DIR: &str = "saves";
+
+    fn path(slot: &str) -> PathBuf {
+        PathBuf::from(SAVE_DIR).join(format!("{slot}.save.ron"))
+    }
+
+    pub fn write(slot: &str, text: &str) -> Result<(), SaveGameError> {
+        std::fs::create_dir_all(SAVE_DIR).map_err(|e| SaveGameError::Storage(e.to_string()))?;
+        std::fs::write(path(slot), text).map_err(|e| SaveGameError::Storage(e.to_string()))
+    }
+
+    pub fn read(slot: &str) -> Result<String, SaveGameError> {
+        std::fs::read_to_string(path(slot)).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => SaveGameError::Missing(slot.to_string()),
+            _ => SaveGameError::Storage(e.to_string()),
+        })
+    }
+}
+
+/// Saves live in the browser's local storage
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use super::SaveGameError;
+
+    const KEY_PREFIX: &str = "cepwa3-bevy/save/";
+
+    fn local_storage() -> Result<web_sys::Storage, SaveGameError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| SaveGameError::Storage("local storage unavailable".to_string()))
+    }
+
+    pub fn write(slot: &str, text: &str) -> Result<(), SaveGameError> {
+        local_storage()?
+            .set_item(&format!("{KEY_PREFIX}{slot}"), text)
+            .map_err(|e| SaveGameError::Storage(format!("{e:?}")))
+    }
+
+    pub fn read(slot: &str) -> Result<String, SaveGameError> {
+        local_storage()?
+            .get_item(&format!("{KEY_PREFIX}{slot}"))
+            .map_err(|e| SaveGameError::Storage(format!("{e:?}")))?
+            .ok_or_else(|| SaveGameError::Missing(slot.to_string()))
+    }
+}
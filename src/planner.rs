@@ -0,0 +1,334 @@
+use crate::constants::{TRANSFER_BI_ELLIPTIC_RATIO, TRANSFER_MIN_LEAD_TIME};
+use crate::flight_program::RelativeState;
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+/// An impulsive burn of a transfer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransferBurn {
+    /// Seconds from now until the burn
+    pub time: f32,
+    /// Delta-v along the velocity relative to the body orbited at the time, in
+    /// m/s, negative burns retrograde
+    pub prograde: f32,
+}
+
+/// Burns that take a vessel onto another orbit or towards another body
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    /// Burns in time order
+    pub burns: Vec<TransferBurn>,
+    /// Seconds from the first burn until arrival
+    pub duration: f32,
+}
+
+impl Transfer {
+    /// Seconds until the first burn
+    pub fn wait(&self) -> f32 {
+        self.burns.first().map_or(0., |burn| burn.time)
+    }
+
+    /// Total delta-v of all burns in m/s
+    pub fn delta_v(&self) -> f32 {
+        self.burns.iter().map(|burn| burn.prograde.abs()).sum()
+    }
+
+    /// The same transfer starting `wait` seconds later
+    fn delayed(mut self, wait: f32) -> Self {
+        for burn in &mut self.burns {
+            burn.time += wait;
+        }
+        self
+    }
+}
+
+/// Speed at distance `r` on an orbit with the given semi-major axis (vis-viva)
+pub fn orbital_speed(mu: f32, r: f32, semi_major_axis: f32) -> f32 {
+    (mu * (2. / r - 1. / semi_major_axis)).max(0.).sqrt()
+}
+
+/// Speed of a circular orbit of radius `r`
+pub fn circular_speed(mu: f32, r: f32) -> f32 {
+    (mu / r).sqrt()
+}
+
+/// Hohmann transfer from distance `r1` to a circular orbit of radius `r2`
+///
+/// `v1` is the speed at `r1` before the first burn, the circular speed when
+/// starting from a circular orbit. Burns at once and again half an ellipse
+/// later.
+pub fn hohmann(mu: f32, r1: f32, v1: f32, r2: f32) -> Transfer {
+    let semi_major_axis = (r1 + r2) / 2.;
+    let duration = PI * (semi_major_axis.powi(3) / mu).sqrt();
+    Transfer {
+        burns: vec![
+            TransferBurn {
+                time: 0.,
+                prograde: orbital_speed(mu, r1, semi_major_axis) - v1,
+            },
+            TransferBurn {
+                time: duration,
+                prograde: circular_speed(mu, r2) - orbital_speed(mu, r2, semi_major_axis),
+            },
+        ],
+        duration,
+    }
+}
+
+/// Bi-elliptic transfer from distance `r1` to a circular orbit of radius `r2`
+/// by way of an apoapsis at `rb`
+///
+/// `v1` is the speed at `r1` before the first burn. Takes three burns and far
+/// longer than a Hohmann transfer, but less delta-v once `r2` is more than
+/// about twelve times `r1`.
+pub fn bi_elliptic(mu: f32, r1: f32, v1: f32, r2: f32, rb: f32) -> Transfer {
+    let outbound = (r1 + rb) / 2.;
+    let inbound = (rb + r2) / 2.;
+    let outbound_time = PI * (outbound.powi(3) / mu).sqrt();
+    let inbound_time = PI * (inbound.powi(3) / mu).sqrt();
+    Transfer {
+        burns: vec![
+            TransferBurn {
+                time: 0.,
+                prograde: orbital_speed(mu, r1, outbound) - v1,
+            },
+            TransferBurn {
+                time: outbound_time,
+                prograde: orbital_speed(mu, rb, inbound) - orbital_speed(mu, rb, outbound),
+            },
+            TransferBurn {
+                time: outbound_time + inbound_time,
+                prograde: circular_speed(mu, r2) - orbital_speed(mu, r2, inbound),
+            },
+        ],
+        duration: outbound_time + inbound_time,
+    }
+}
+
+/// Wait for a window, taking the following one when it is too close to plan
+fn next_window(wait: f32, repeat: f32) -> f32 {
+    if wait < TRANSFER_MIN_LEAD_TIME {
+        wait + repeat
+    } else {
+        wait
+    }
+}
+
+/// +1 for orbits running counter-clockwise, -1 for clockwise ones
+fn sense(state: &RelativeState) -> f32 {
+    if state.position.perp_dot(state.velocity) < 0. {
+        -1.
+    } else {
+        1.
+    }
+}
+
+/// Hohmann and bi-elliptic transfers from the current closed orbit to a
+/// circular orbit of radius `radius` around the same body
+///
+/// Departs from periapsis when raising the orbit and from apoapsis when
+/// lowering it. The bi-elliptic apoapsis lies [`TRANSFER_BI_ELLIPTIC_RATIO`]
+/// times beyond the larger orbit, but stays within `max_radius`. Returns
+/// `None` for open orbits, and no bi-elliptic transfer without room for one.
+pub fn plan_orbit_change(
+    vessel: &RelativeState,
+    radius: f32,
+    max_radius: f32,
+) -> Option<(Transfer, Option<Transfer>)> {
+    let elements = vessel.elements();
+    let period = elements.period?;
+    let (r1, true_anomaly) = if radius >= elements.semi_major_axis {
+        (elements.periapsis, 0.)
+    } else {
+        (elements.apoapsis?, PI)
+    };
+    let v1 = orbital_speed(vessel.mu, r1, elements.semi_major_axis);
+    let wait = next_window(elements.time_to_true_anomaly(true_anomaly)?, period);
+
+    let rb = (TRANSFER_BI_ELLIPTIC_RATIO * r1.max(radius)).min(max_radius);
+    let bi_elliptic =
+        (rb > r1.max(radius)).then(|| bi_elliptic(vessel.mu, r1, v1, radius, rb).delayed(wait));
+    Some((
+        hohmann(vessel.mu, r1, v1, radius).delayed(wait),
+        bi_elliptic,
+    ))
+}
+
+/// Hohmann transfer to a body orbiting the same body as the vessel, at the
+/// next launch window
+///
+/// Both orbits are treated as circular at their current distance and must run
+/// the same way around. Only the departure burn is planned, capture is left
+/// to the pilot.
+pub fn plan_intercept(vessel: &RelativeState, target: &RelativeState) -> Option<Transfer> {
+    let direction = sense(vessel);
+    if sense(target) != direction {
+        return None;
+    }
+
+    let r1 = vessel.distance();
+    let r2 = target.distance();
+    let mut transfer = hohmann(vessel.mu, r1, vessel.velocity.length(), r2);
+    transfer.burns.truncate(1);
+
+    // Arriving half an ellipse later, the target has to be that much short of
+    // the far side when the burn starts
+    let vessel_rate = (vessel.mu / r1.powi(3)).sqrt();
+    let target_rate = (target.mu / r2.powi(3)).sqrt();
+    let relative_rate = target_rate - vessel_rate;
+    if relative_rate.abs() < f32::EPSILON {
+        return None;
+    }
+    let lead = PI - target_rate * transfer.duration;
+    let phase = direction * vessel.position.angle_to(target.position);
+    let synodic_period = TAU / relative_rate.abs();
+    let wait = ((lead - phase) / relative_rate).rem_euclid(synodic_period);
+
+    Some(transfer.delayed(next_window(wait, synodic_period)))
+}
+
+/// Escape from the body the vessel orbits onto a Hohmann transfer to a
+/// circular orbit of radius `radius` around that body's primary
+///
+/// `vessel` is relative to the body and `body` relative to its primary, both
+/// parking orbits are treated as circular, and `soi_radius` is the radius of
+/// the body's sphere of influence. The departure burn is timed so the
+/// escape hyperbola leaves along the transfer, and the arrival burn
+/// circularizes at its far end.
+pub fn plan_ejection(
+    vessel: &RelativeState,
+    body: &RelativeState,
+    soi_radius: f32,
+    radius: f32,
+) -> Option<Transfer> {
+    let transfer = hohmann(body.mu, body.distance(), body.velocity.length(), radius);
+    let excess = transfer.burns[0].prograde;
+    let excess_direction = body.velocity.normalize_or_zero() * excess.signum();
+    if excess_direction == Vec2::ZERO {
+        return None;
+    }
+
+    let parking = vessel.distance();
+    let eccentricity = 1. + parking * excess.powi(2) / vessel.mu;
+    let asymptote = (-1. / eccentricity).acos();
+    let direction = sense(vessel);
+    let vessel_rate = vessel.velocity.length() / parking;
+    let body_rate = sense(body) * body.velocity.length() / body.distance();
+
+    // The way out turns with the body's orbit while the vessel waits for it
+    let mut wait = 0.;
+    for _ in 0..3 {
+        let escape = Vec2::from_angle(body_rate * wait).rotate(excess_direction);
+        let burn_point = Vec2::from_angle(-direction * asymptote).rotate(escape);
+        wait = (direction * vessel.position.angle_to(burn_point)).rem_euclid(TAU) / vessel_rate;
+    }
+    let wait = next_window(wait, TAU / vessel_rate);
+
+    // Time on the hyperbola until the primary takes over, from the hyperbolic
+    // anomaly at the edge of the body's sphere of influence
+    let semi_major_axis = vessel.mu / excess.powi(2);
+    let anomaly = ((soi_radius / semi_major_axis + 1.) / eccentricity)
+        .max(1.)
+        .acosh();
+    let escape_time =
+        (semi_major_axis.powi(3) / vessel.mu).sqrt() * (eccentricity * anomaly.sinh() - anomaly);
+
+    let departure = (excess.powi(2) + 2. * vessel.mu / parking).sqrt() - vessel.velocity.length();
+    let duration = escape_time + transfer.duration;
+    Some(Transfer {
+        burns: vec![
+            TransferBurn {
+                time: wait,
+                prograde: departure,
+            },
+            TransferBurn {
+                time: wait + duration,
+                prograde: transfer.burns[1].prograde,
+            },
+        ],
+        duration,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Earth's gravitational parameter in m³/s²
+    const MU: f32 = 3.986e14;
+    const LEO: f32 = 6_678_000.;
+    const GEO: f32 = 42_164_000.;
+
+    fn circular_state(radius: f32) -> RelativeState {
+        RelativeState {
+            position: Vec2::new(radius, 0.),
+            velocity: Vec2::new(0., circular_speed(MU, radius)),
+            mu: MU,
+            radius: 6_371_000.,
+        }
+    }
+
+    #[test]
+    fn hohmann_leo_to_geo() {
+        let transfer = hohmann(MU, LEO, circular_speed(MU, LEO), GEO);
+
+        assert_eq!(transfer.burns.len(), 2);
+        assert!((transfer.burns[0].prograde - 2426.).abs() < 5.);
+        assert!((transfer.burns[1].prograde - 1467.).abs() < 5.);
+        assert!((transfer.delta_v() - 3893.).abs() < 10.);
+        // Five and a quarter hours to the far side of the transfer ellipse
+        assert!((transfer.duration - 18_990.).abs() < 30.);
+        assert_eq!(transfer.burns[1].time, transfer.duration);
+    }
+
+    #[test]
+    fn hohmann_lowering_burns_retrograde() {
+        let transfer = hohmann(MU, GEO, circular_speed(MU, GEO), LEO);
+        assert!(transfer.burns.iter().all(|burn| burn.prograde < 0.));
+        assert!((transfer.delta_v() - 3893.).abs() < 10.);
+    }
+
+    #[test]
+    fn bi_elliptic_only_pays_off_for_large_ratios() {
+        let v1 = circular_speed(MU, LEO);
+        let compare = |ratio: f32, apoapsis_ratio: f32| {
+            let r2 = ratio * LEO;
+            let hohmann = hohmann(MU, LEO, v1, r2).delta_v();
+            let bi_elliptic = bi_elliptic(MU, LEO, v1, r2, apoapsis_ratio * LEO).delta_v();
+            bi_elliptic < hohmann
+        };
+
+        // Below a ratio of 11.94 no intermediate apoapsis helps
+        for apoapsis_ratio in [20., 100., 1000., 10_000.] {
+            assert!(!compare(11., apoapsis_ratio));
+        }
+        // Just above it only a very distant apoapsis does
+        assert!(!compare(12.5, 20.));
+        assert!(compare(12.5, 10_000.));
+        // Beyond 15.58 any apoapsis beyond the target orbit does
+        assert!(compare(20., 25.));
+    }
+
+    #[test]
+    fn orbit_change_from_circular_orbit() {
+        let vessel = circular_state(LEO);
+        let (hohmann, bi_elliptic) = plan_orbit_change(&vessel, GEO, f32::INFINITY).unwrap();
+
+        assert!((hohmann.delta_v() - 3893.).abs() < 10.);
+        assert!(hohmann.wait() >= TRANSFER_MIN_LEAD_TIME);
+        let bi_elliptic = bi_elliptic.unwrap();
+        assert_eq!(bi_elliptic.burns.len(), 3);
+        assert_eq!(bi_elliptic.wait(), hohmann.wait());
+
+        // No room for an apoapsis beyond the target orbit
+        let (_, bi_elliptic) = plan_orbit_change(&vessel, GEO, GEO).unwrap();
+        assert!(bi_elliptic.is_none());
+    }
+
+    #[test]
+    fn orbit_change_needs_closed_orbit() {
+        let mut vessel = circular_state(LEO);
+        vessel.velocity *= 1.5;
+        assert!(plan_orbit_change(&vessel, GEO, f32::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,458 @@
+use crate::components::aerodynamics::Aerodynamics;
+use crate::components::atmosphere::Atmosphere;
+use crate::components::attitude::AttitudeControl;
+use crate::components::celestial_body::CelestialBody;
+use crate::components::objectives::Objective;
+use crate::components::staging::Stage;
+use crate::constants::{DISTANCE_SCALE, G, PLANET_SCALE};
+use crate::orbital::state_from_elements;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// A complete starting setup for the simulation
+///
+/// Celestial bodies are described in real-world SI units and scaled down with
+/// `PLANET_SCALE`/`DISTANCE_SCALE` when spawned. The vessel's mass, size and
+/// engines are used as-is.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct Scenario {
+    /// Human readable scenario title
+    pub name: String,
+    /// Celestial bodies, parents must be listed before their children
+    pub bodies: Vec<BodyDefinition>,
+    /// The player-controlled spacecraft
+    pub vessel: VesselDefinition,
+    /// Objectives the player works through, in order
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+}
+
+/// A planet or moon
+#[derive(Deserialize, Debug, Clone)]
+pub struct BodyDefinition {
+    pub name: String,
+    /// Mass in kg
+    pub mass: f32,
+    /// Radius in meters
+    pub radius: f32,
+    /// sRGB display color
+    pub color: [f32; 3],
+    /// Name of the body this one orbits
+    #[serde(default)]
+    pub parent: Option<String>,
+    pub initial: InitialState,
+    /// Pick this body's velocity so that the total momentum of the system is zero
+    #[serde(default)]
+    pub balance_momentum: bool,
+    /// Draw the predicted trajectory of this body
+    #[serde(default)]
+    pub predict_trajectory: bool,
+    #[serde(default)]
+    pub atmosphere: Option<AtmosphereDefinition>,
+    /// Time warp limits near the body, the planet or moon defaults when left out
+    #[serde(default)]
+    pub warp_limits: Option<WarpLimitsDefinition>,
+}
+
+/// Air around a body, heights in meters are scaled like the body's radius
+#[derive(Deserialize, Debug, Clone)]
+pub struct AtmosphereDefinition {
+    /// Surface air density in kg/m³
+    pub sea_level_density: f32,
+    pub scale_height: f32,
+    /// Top of the atmosphere above the surface
+    pub height: f32,
+    /// Sutton-Graves heating constant, defaults to Earth's air
+    #[serde(default = "default_heating_coefficient")]
+    pub heating_coefficient: f32,
+}
+
+/// Altitudes that restrict time warp near a body, scaled like the body's radius
+#[derive(Deserialize, Debug, Clone)]
+pub struct WarpLimitsDefinition {
+    /// Below this altitude time warp is disabled
+    pub min_altitude: f32,
+    /// Below this altitude only limited time warp is available
+    pub limited_altitude: f32,
+}
+
+fn default_heating_coefficient() -> f32 {
+    1.7415e-4
+}
+
+/// The player's spacecraft
+#[derive(Deserialize, Debug, Clone)]
+pub struct VesselDefinition {
+    /// Collision radius in meters
+    pub radius: f32,
+    /// sRGB display color
+    pub color: [f32; 3],
+    /// Body the vessel starts next to
+    pub parent: String,
+    pub initial: InitialState,
+    /// Stages in firing order, the vessel starts with all of them stacked
+    pub stages: Vec<StageDefinition>,
+    #[serde(default)]
+    pub aerodynamics: AerodynamicsDefinition,
+    #[serde(default)]
+    pub attitude_control: AttitudeControlDefinition,
+}
+
+/// Drag and reentry limits of a vessel
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AerodynamicsDefinition {
+    pub drag_coefficient: f32,
+    /// Cross-section in m²
+    pub reference_area: f32,
+    /// Heat shield radius in meters
+    pub nose_radius: f32,
+    /// Seconds for the hull to heat up or cool down
+    pub thermal_time_constant: f32,
+    /// Hull temperature limit in Kelvin
+    pub max_temperature: f32,
+    /// Acceleration limit in g
+    pub max_g_load: f32,
+}
+
+impl Default for AerodynamicsDefinition {
+    fn default() -> Self {
+        let aero = Aerodynamics::default();
+        Self {
+            drag_coefficient: aero.drag_coefficient,
+            reference_area: aero.reference_area,
+            nose_radius: aero.nose_radius,
+            thermal_time_constant: aero.thermal_time_constant,
+            max_temperature: aero.max_temperature,
+            max_g_load: aero.max_g_load,
+        }
+    }
+}
+
+impl AerodynamicsDefinition {
+    pub fn to_component(&self) -> Aerodynamics {
+        Aerodynamics {
+            drag_coefficient: self.drag_coefficient,
+            reference_area: self.reference_area,
+            nose_radius: self.nose_radius,
+            thermal_time_constant: self.thermal_time_constant,
+            max_temperature: self.max_temperature,
+            max_g_load: self.max_g_load,
+            ..default()
+        }
+    }
+}
+
+/// Reaction wheels and RCS thrusters of a vessel
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AttitudeControlDefinition {
+    /// Reaction wheel torque in N·m
+    pub wheel_torque: f32,
+    /// RCS thruster torque in N·m
+    pub rcs_torque: f32,
+    /// RCS propellant in kg, the vessel starts with a full tank
+    pub monopropellant: f32,
+    /// Monopropellant used per second at full RCS torque, in kg/s
+    pub monopropellant_rate: f32,
+    /// Whether SAS starts switched on
+    pub sas: bool,
+}
+
+impl Default for AttitudeControlDefinition {
+    fn default() -> Self {
+        let control = AttitudeControl::default();
+        Self {
+            wheel_torque: control.wheel_torque,
+            rcs_torque: control.rcs_torque,
+            monopropellant: control.max_monopropellant,
+            monopropellant_rate: control.monopropellant_rate,
+            sas: control.sas,
+        }
+    }
+}
+
+impl AttitudeControlDefinition {
+    pub fn to_component(&self) -> AttitudeControl {
+        AttitudeControl {
+            wheel_torque: self.wheel_torque,
+            rcs_torque: self.rcs_torque,
+            monopropellant: self.monopropellant,
+            max_monopropellant: self.monopropellant,
+            monopropellant_rate: self.monopropellant_rate,
+            sas: self.sas,
+            ..default()
+        }
+    }
+}
+
+/// Structure, engine and tank of one vessel stage
+#[derive(Deserialize, Debug, Clone)]
+pub struct StageDefinition {
+    /// Mass of the stage without fuel in kg
+    pub dry_mass: f32,
+    /// Maximum thrust force in Newtons
+    pub max_thrust: f32,
+    /// Starting fuel amount in kg
+    pub fuel: f32,
+    /// Maximum fuel capacity in kg
+    pub max_fuel: f32,
+    /// Specific impulse in seconds, sets the exhaust velocity and fuel consumption
+    pub specific_impulse: f32,
+}
+
+impl StageDefinition {
+    pub fn to_stage(&self) -> Stage {
+        Stage {
+            dry_mass: self.dry_mass,
+            fuel: self.fuel,
+            max_fuel: self.max_fuel,
+            max_thrust: self.max_thrust,
+            specific_impulse: self.specific_impulse,
+        }
+    }
+}
+
+/// Where an object starts, relative to its parent body when it has one
+#[derive(Deserialize, Debug, Clone)]
+pub enum InitialState {
+    /// At rest at the parent's position (or the origin)
+    Rest,
+    /// Explicit position (m) and velocity (m/s)
+    State {
+        position: [f32; 2],
+        velocity: [f32; 2],
+    },
+    /// Keplerian orbit around the parent, angles in degrees
+    Orbit {
+        /// Semi-major axis in meters
+        semi_major_axis: f32,
+        #[serde(default)]
+        eccentricity: f32,
+        #[serde(default)]
+        argument_of_periapsis: f32,
+        #[serde(default)]
+        true_anomaly: f32,
+        /// Orbit clockwise instead of counter-clockwise
+        #[serde(default)]
+        retrograde: bool,
+    },
+    /// Resting on the parent's surface at the given angle in degrees
+    Surface { angle: f32 },
+}
+
+/// Position and velocity of a spawned object in simulation units
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResolvedState {
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+/// Scaled mass and radius of a parent body together with its resolved state
+#[derive(Clone, Copy, Debug)]
+pub struct ParentBody {
+    pub mass: f32,
+    pub radius: f32,
+    pub state: ResolvedState,
+}
+
+impl InitialState {
+    /// Converts the description into simulation units
+    ///
+    /// `distance_scale` and `speed_scale` are applied to explicit positions and
+    /// velocities, orbits are derived from the already scaled parent mass.
+    pub fn resolve(
+        &self,
+        parent: Option<&ParentBody>,
+        mass: f32,
+        distance_scale: f32,
+        speed_scale: f32,
+    ) -> ResolvedState {
+        let origin = parent.map(|p| p.state).unwrap_or_default();
+
+        let relative = match *self {
+            InitialState::Rest => ResolvedState::default(),
+            InitialState::State { position, velocity } => ResolvedState {
+                position: Vec2::from(position).extend(0.) * distance_scale,
+                velocity: Vec2::from(velocity).extend(0.) * speed_scale,
+            },
+            InitialState::Orbit {
+                semi_major_axis,
+                eccentricity,
+                argument_of_periapsis,
+                true_anomaly,
+                retrograde,
+            } => {
+                let parent_mass = parent.map_or(0., |p| p.mass);
+                let mu = G * (parent_mass + mass);
+                let (position, velocity) = state_from_elements(
+                    mu,
+                    semi_major_axis * distance_scale,
+                    eccentricity,
+                    argument_of_periapsis.to_radians(),
+                    true_anomaly.to_radians(),
+                    retrograde,
+                );
+                ResolvedState { position, velocity }
+            }
+            InitialState::Surface { angle } => {
+                let radius = parent.map_or(0., |p| p.radius);
+                let (sin, cos) = angle.to_radians().sin_cos();
+                ResolvedState {
+                    position: Vec3::new(cos, sin, 0.) * radius,
+                    velocity: Vec3::ZERO,
+                }
+            }
+        };
+
+        ResolvedState {
+            position: origin.position + relative.position,
+            velocity: origin.velocity + relative.velocity,
+        }
+    }
+}
+
+impl BodyDefinition {
+    pub fn scaled_mass(&self) -> f32 {
+        self.mass * PLANET_SCALE.powi(3)
+    }
+
+    pub fn scaled_radius(&self) -> f32 {
+        self.radius * PLANET_SCALE
+    }
+
+    /// The body's [`CelestialBody`] component, given its spawned parent
+    pub fn celestial_body(&self, parent: Option<Entity>) -> CelestialBody {
+        let mut body = CelestialBody::new(
+            self.name.clone(),
+            self.scaled_radius(),
+            parent,
+            srgb(self.color),
+        );
+        if let Some(limits) = &self.warp_limits {
+            body.min_warp_altitude = limits.min_altitude * PLANET_SCALE;
+            body.limited_warp_altitude = limits.limited_altitude * PLANET_SCALE;
+        }
+        body
+    }
+
+    pub fn scaled_atmosphere(&self) -> Option<Atmosphere> {
+        self.atmosphere.as_ref().map(|atmosphere| Atmosphere {
+            sea_level_density: atmosphere.sea_level_density,
+            scale_height: atmosphere.scale_height * PLANET_SCALE,
+            height: atmosphere.height * PLANET_SCALE,
+            heating_coefficient: atmosphere.heating_coefficient,
+        })
+    }
+}
+
+/// Factor applied to body speeds so orbits stay stable after scaling
+pub fn speed_scale() -> f32 {
+    (PLANET_SCALE.powi(3) / DISTANCE_SCALE).sqrt()
+}
+
+pub fn srgb(color: [f32; 3]) -> Color {
+    Color::srgb(color[0], color[1], color[2])
+}
+
+#[derive(Debug, Error)]
+pub enum ScenarioLoaderError {
+    #[error("could not read scenario: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse scenario: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+/// Loads `.scenario.ron` files
+#[derive(Default)]
+pub struct ScenarioLoader;
+
+impl AssetLoader for ScenarioLoader {
+    type Asset = Scenario;
+    type Settings = ();
+    type Error = ScenarioLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["scenario.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARENT: ParentBody = ParentBody {
+        mass: 1.0e22,
+        radius: 100_000.,
+        state: ResolvedState {
+            position: Vec3::new(1.0e6, 0., 0.),
+            velocity: Vec3::new(0., 500., 0.),
+        },
+    };
+
+    #[test]
+    fn default_scenario_parses() {
+        let text = include_str!("../assets/scenarios/default.scenario.ron");
+        let scenario: Scenario = ron::from_str(text).unwrap();
+
+        assert!(!scenario.objectives.is_empty());
+        for (index, body) in scenario.bodies.iter().enumerate() {
+            if let Some(parent) = &body.parent {
+                assert!(
+                    scenario.bodies[..index].iter().any(|b| &b.name == parent),
+                    "{} is listed before its parent {parent}",
+                    body.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn surface_state_sits_on_the_parent() {
+        let state = InitialState::Surface { angle: 90. }.resolve(Some(&PARENT), 1000., 1., 1.);
+        let offset = state.position - PARENT.state.position;
+        assert!(offset.abs_diff_eq(Vec3::new(0., PARENT.radius, 0.), 1e-2));
+        assert_eq!(state.velocity, PARENT.state.velocity);
+    }
+
+    #[test]
+    fn orbit_state_is_circular_at_zero_eccentricity() {
+        let semi_major_axis = 400_000.;
+        let orbit = InitialState::Orbit {
+            semi_major_axis,
+            eccentricity: 0.,
+            argument_of_periapsis: 0.,
+            true_anomaly: 45.,
+            retrograde: false,
+        };
+        let state = orbit.resolve(Some(&PARENT), 1000., 1., 1.);
+
+        let offset = state.position - PARENT.state.position;
+        let velocity = state.velocity - PARENT.state.velocity;
+        let speed = (G * (PARENT.mass + 1000.) / semi_major_axis).sqrt();
+        assert!((offset.length() - semi_major_axis).abs() < 1.);
+        assert!((velocity.length() - speed).abs() < 1e-3 * speed);
+        assert!(offset.dot(velocity).abs() < 1e-3 * offset.length() * speed);
+        // Counter-clockwise unless retrograde
+        assert!(offset.truncate().perp_dot(velocity.truncate()) > 0.);
+    }
+
+    #[test]
+    fn malformed_scenario_is_an_error() {
+        assert!(ron::from_str::<Scenario>("(name: \"empty\")").is_err());
+    }
+}
@@ -0,0 +1,23 @@
+use crate::components::appearance::{Appearance, Shape};
+use bevy::prelude::*;
+use bevy::render::mesh::Triangle2dMeshBuilder;
+
+/// Builds render meshes and materials for newly spawned objects
+pub fn attach_meshes_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(Entity, &Appearance), Without<Mesh2d>>,
+) {
+    for (entity, appearance) in &query {
+        let mesh = match appearance.shape {
+            Shape::Circle(radius) => meshes.add(Circle::new(radius)),
+            Shape::Triangle([a, b, c]) => meshes.add(Triangle2dMeshBuilder::new(a, b, c)),
+        };
+
+        commands.entity(entity).insert((
+            Mesh2d(mesh),
+            MeshMaterial2d(materials.add(appearance.color)),
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::components::markers::User;
+use crate::input::{ActionState, Binding, InputAction, InputMap};
+use crate::systems::maneuver::ManeuverEditor;
 use bevy::input::ButtonState;
-use bevy::input::keyboard::KeyCode;
 use bevy::input::mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
@@ -23,18 +24,12 @@ impl Default for CameraOffset {
     }
 }
 
-pub fn create_camera(mut commands: Commands, query: Query<&Transform, With<User>>) {
-    let (camera_pos, scale) = if let Some(user_transform) = query.iter().next() {
-        (user_transform.translation, 1.)
-    } else {
-        (Vec3::ZERO, 3e4)
-    };
-
+// The user is spawned once the scenario has loaded, camera_follow_user moves the camera there
+pub fn create_camera(mut commands: Commands) {
     commands.spawn((
         Camera2d,
-        Transform::from_translation(camera_pos),
         Projection::from(OrthographicProjection {
-            scale, // initial zoom level
+            scale: 1., // initial zoom level
             ..OrthographicProjection::default_2d()
         }),
     ));
@@ -78,12 +73,16 @@ pub fn pan_camera(
     mut drag: ResMut<DragState>,
     mut camera_offset: ResMut<CameraOffset>,
     mut ev_mb: EventReader<MouseButtonInput>,
+    input_map: Res<InputMap>,
+    maneuver_editor: Res<ManeuverEditor>,
     window: Single<&Window, With<PrimaryWindow>>,
     q_cam: Query<&Projection, With<Camera2d>>,
 ) {
     for ev in ev_mb.read() {
-        if ev.button == MouseButton::Middle || ev.button == MouseButton::Left {
+        if input_map.is_bound(InputAction::PanCamera, Binding::Mouse(ev.button)) {
             match ev.state {
+                // clicks on maneuver nodes and the trajectory are not pans
+                ButtonState::Pressed if maneuver_editor.pointer_captured => {}
                 ButtonState::Pressed => {
                     // start dragging: record current cursor as the last position
                     drag.1 = window.cursor_position().unwrap_or(Vec2::ZERO);
@@ -142,12 +141,9 @@ pub fn camera_follow_user(
     }
 }
 
-// Reset camera offset to center on user when C key is pressed
-pub fn recenter_camera_on_user(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut camera_offset: ResMut<CameraOffset>,
-) {
-    if keyboard.just_pressed(KeyCode::KeyC) {
+// Reset camera offset to center on user when the recenter action (C) fires
+pub fn recenter_camera_on_user(actions: Res<ActionState>, mut camera_offset: ResMut<CameraOffset>) {
+    if actions.just_pressed(InputAction::RecenterCamera) {
         camera_offset.0 = Vec2::ZERO;
     }
 }
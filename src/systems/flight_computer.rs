@@ -0,0 +1,172 @@
+use crate::components::attitude::{AttitudeControl, AttitudeHold};
+use crate::components::damage::Destroyed;
+use crate::components::flight_computer::FlightComputer;
+use crate::components::landing::SurfaceContact;
+use crate::components::physics_object::PhysicsObject;
+use crate::components::propulsion::Propulsion;
+use crate::components::staging::Stages;
+use crate::components::target::Target;
+use crate::config::Config;
+use crate::constants::{FLIGHT_PROGRAM_ALIGNMENT_DEGREES, FLIGHT_PROGRAM_BURN_WARP_STAGE};
+use crate::flight_program::{AttitudeCommand, RelativeState, VesselState, program_for};
+use crate::systems::objectives::ObjectiveTracker;
+use crate::systems::physics::{MissionClock, hold_direction};
+use crate::systems::staging::StageRequest;
+use bevy::prelude::*;
+
+/// Asks for the time warp to drop to at most the given stage
+///
+/// Applied between frames by [`warp_limit_system`], retuning `Time<Fixed>`
+/// inside the fixed loop would leave the steps still due this frame at the old warp.
+#[derive(Event)]
+pub struct WarpLimitRequest(pub usize);
+
+type FlightComputerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut FlightComputer,
+        &'static PhysicsObject,
+        &'static Transform,
+        &'static mut Propulsion,
+        &'static mut AttitudeControl,
+        Option<&'static Stages>,
+        Option<&'static SurfaceContact>,
+        Option<&'static ObjectiveTracker>,
+        Option<&'static Target>,
+    ),
+    Without<Destroyed>,
+>;
+
+/// Steps each vessel's flight program and applies its command
+///
+/// In mission mode the computer loads the built-in program for whichever
+/// objective is being pursued. Runs after the autopilot, so a commanded
+/// direction replaces the hold mode's, and before the attitude and propulsion
+/// systems act on it. Burns ask for the time warp to drop to a stage they can be
+/// flown at.
+pub fn flight_computer_system(
+    config: Res<Config>,
+    clock: Res<MissionClock>,
+    mut vessels: FlightComputerQuery,
+    bodies: Query<&PhysicsObject>,
+    mut stage_requests: EventWriter<StageRequest>,
+    mut warp_limits: EventWriter<WarpLimitRequest>,
+) {
+    for (
+        entity,
+        mut computer,
+        phys,
+        transform,
+        mut propulsion,
+        mut control,
+        stages,
+        contact,
+        tracker,
+        target,
+    ) in &mut vessels
+    {
+        if computer.mission
+            && let Some(tracker) = tracker
+            && computer.objective.as_ref() != Some(&tracker.progress.current)
+        {
+            computer.program = program_for(&tracker.progress);
+            computer.objective = Some(tracker.progress.current.clone());
+            computer.finished = false;
+            if let Some(program) = &computer.program {
+                info!("Flight computer: {}", program.name());
+            }
+        }
+
+        if computer.finished {
+            continue;
+        }
+        let Some(central_body) = phys.central_body else {
+            continue;
+        };
+        let Ok(central) = bodies.get(central_body) else {
+            continue;
+        };
+
+        let heading = transform.rotation.mul_vec3(Vec3::Y).truncate();
+        let vessel = VesselState {
+            time: clock.elapsed,
+            dt: clock.dt,
+            relative: RelativeState::between(phys, central),
+            primary: central
+                .central_body
+                .and_then(|entity| bodies.get(entity).ok())
+                .map(|primary| RelativeState::between(central, primary)),
+            central_body,
+            heading,
+            radius: phys.radius,
+            max_acceleration: if propulsion.fuel > 0. {
+                propulsion.max_thrust / propulsion.mass()
+            } else {
+                0.
+            },
+            landed: contact.is_some_and(|contact| contact.touching == Some(central_body)),
+        };
+
+        let Some(program) = computer.program.as_mut() else {
+            continue;
+        };
+        let command = program.step(&vessel);
+        computer.phase = command.phase;
+        computer.finished = command.finished;
+
+        match command.attitude {
+            Some(AttitudeCommand::Hold(hold)) => {
+                let target = target.and_then(|Target(entity)| bodies.get(*entity).ok());
+                control.sas = true;
+                control.hold = hold;
+                control.hold_direction = hold_direction(hold, phys, Some(central), target);
+            }
+            Some(AttitudeCommand::Direction(direction)) => {
+                control.sas = true;
+                control.hold = AttitudeHold::Stability;
+                control.hold_direction = Some(direction.extend(0.));
+            }
+            None => {}
+        }
+
+        // Only fire once the nose points where the burn should go
+        let aligned = control.hold_direction.is_none_or(|direction| {
+            heading.angle_to(direction.truncate()).abs()
+                <= FLIGHT_PROGRAM_ALIGNMENT_DEGREES.to_radians()
+        });
+        propulsion.thrust_percentage = if aligned {
+            command.throttle.clamp(0., 1.)
+        } else {
+            0.
+        };
+
+        if propulsion.thrust_percentage > 0. && config.warp_stage > FLIGHT_PROGRAM_BURN_WARP_STAGE {
+            warp_limits.write(WarpLimitRequest(FLIGHT_PROGRAM_BURN_WARP_STAGE));
+        }
+
+        if propulsion.fuel > 0. {
+            computer.staging = false;
+        } else if command.throttle > 0.
+            && !computer.staging
+            && stages.is_some_and(Stages::can_stage)
+        {
+            stage_requests.write(StageRequest(entity));
+            computer.staging = true;
+        }
+    }
+}
+
+/// Lowers the time warp to the slowest stage requested during the last fixed steps
+pub fn warp_limit_system(
+    mut requests: EventReader<WarpLimitRequest>,
+    mut config: ResMut<Config>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+) {
+    if let Some(stage) = requests.read().map(|WarpLimitRequest(stage)| *stage).min()
+        && stage < config.warp_stage
+    {
+        config.set_warp_stage(stage, &mut fixed_time);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::components::maneuver::{ManeuverNode, ManeuverPlan};
+use crate::components::markers::User;
+use crate::components::trajectory_prediction::TrajectoryPrediction;
+use crate::input::{ActionState, InputAction};
+use crate::systems::physics::MissionClock;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use itertools::Itertools;
+
+/// Distance of the delta-v handles from their node, in screen pixels
+const HANDLE_DISTANCE_PX: f32 = 40.;
+/// How close the cursor must be to pick a handle, node or trajectory point, in screen pixels
+const PICK_RADIUS_PX: f32 = 10.;
+/// Delta-v added per pixel of handle drag, in m/s
+const DELTA_V_PER_PIXEL: f32 = 0.5;
+
+/// The four draggable delta-v handles of a node
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ManeuverHandle {
+    Prograde,
+    Retrograde,
+    RadialOut,
+    RadialIn,
+}
+
+impl ManeuverHandle {
+    const ALL: [ManeuverHandle; 4] = [
+        ManeuverHandle::Prograde,
+        ManeuverHandle::Retrograde,
+        ManeuverHandle::RadialOut,
+        ManeuverHandle::RadialIn,
+    ];
+
+    /// World direction of the handle given the node's prograde and radial-out vectors
+    fn direction(self, (prograde, radial): (Vec3, Vec3)) -> Vec3 {
+        match self {
+            ManeuverHandle::Prograde => prograde,
+            ManeuverHandle::Retrograde => -prograde,
+            ManeuverHandle::RadialOut => radial,
+            ManeuverHandle::RadialIn => -radial,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            ManeuverHandle::Prograde | ManeuverHandle::Retrograde => Color::srgb(0.8, 1.0, 0.2),
+            ManeuverHandle::RadialOut | ManeuverHandle::RadialIn => Color::srgb(0.2, 0.9, 1.0),
+        }
+    }
+}
+
+/// Mouse interaction state of the maneuver editor
+#[derive(Resource, Default)]
+pub struct ManeuverEditor {
+    /// Handle currently being dragged
+    pub dragging: Option<ManeuverHandle>,
+    /// Whether the current mouse press belongs to the editor rather than camera panning
+    pub pointer_captured: bool,
+    last_cursor: Vec2,
+}
+
+/// Places, selects, edits and deletes maneuver nodes with the mouse
+///
+/// Clicking the predicted trajectory adds a node, clicking a node selects it,
+/// dragging a handle of the selected node changes its delta-v and Delete
+/// removes it. Must run before camera panning so captured clicks don't pan.
+pub fn maneuver_input_system(
+    actions: Res<ActionState>,
+    clock: Res<MissionClock>,
+    mut editor: ResMut<ManeuverEditor>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform, &Projection), With<Camera2d>>,
+    mut user: Query<(&TrajectoryPrediction, &mut ManeuverPlan), With<User>>,
+) {
+    let Some((prediction, mut plan)) = user.iter_mut().next() else {
+        return;
+    };
+
+    if actions.just_pressed(InputAction::DeleteManeuver) {
+        plan.remove_selected();
+    }
+
+    if !actions.pressed(InputAction::EditManeuver) {
+        editor.dragging = None;
+        editor.pointer_captured = false;
+    }
+
+    let Some((camera, camera_transform, projection)) = camera.iter().next() else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let to_viewport =
+        |world: Vec3| -> Option<Vec2> { camera.world_to_viewport(camera_transform, world).ok() };
+    let near_cursor =
+        |world: Vec3| to_viewport(world).is_some_and(|p| p.distance(cursor) < PICK_RADIUS_PX);
+
+    let cam_scale = match projection {
+        Projection::Orthographic(ortho) => ortho.scale,
+        _ => 1.,
+    };
+
+    if actions.just_pressed(InputAction::EditManeuver) {
+        editor.last_cursor = cursor;
+
+        // Handles of the selected node take priority
+        if let Some(index) = plan.selected
+            && let (Some(Some(position)), Some(frame)) =
+                (plan.node_positions.get(index), plan.node_frames.get(index))
+        {
+            editor.dragging = ManeuverHandle::ALL.into_iter().find(|handle| {
+                near_cursor(*position + handle.direction(*frame) * HANDLE_DISTANCE_PX * cam_scale)
+            });
+        }
+
+        if editor.dragging.is_some() {
+            editor.pointer_captured = true;
+            return;
+        }
+
+        // Then existing nodes
+        if let Some(index) = plan
+            .node_positions
+            .iter()
+            .position(|position| position.is_some_and(near_cursor))
+        {
+            plan.selected = Some(index);
+            editor.pointer_captured = true;
+            return;
+        }
+
+        // Finally the part of the predicted trajectory that is still ahead
+        let elapsed = (clock.elapsed - prediction.epoch) as f32;
+        let closest = prediction
+            .points
+            .iter()
+            .zip(&prediction.samples)
+            .filter(|(_, sample)| sample.time > elapsed)
+            .filter_map(|(point, sample)| Some((to_viewport(*point)?.distance(cursor), sample)))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        if let Some((distance, sample)) = closest
+            && distance < PICK_RADIUS_PX
+        {
+            plan.add_node(ManeuverNode::new(prediction.epoch + sample.time as f64));
+            editor.pointer_captured = true;
+        }
+        return;
+    }
+
+    let Some(handle) = editor.dragging else {
+        return;
+    };
+    let Some(index) = plan.selected else {
+        return;
+    };
+    let Some(frame) = plan.node_frames.get(index).copied() else {
+        return;
+    };
+
+    // Project the cursor movement onto the handle's axis, viewport y points down
+    let delta = cursor - editor.last_cursor;
+    editor.last_cursor = cursor;
+    let along = Vec2::new(delta.x, -delta.y).dot(handle.direction(frame).truncate());
+
+    let Some(node) = plan.nodes.get_mut(index) else {
+        return;
+    };
+    let change = along * DELTA_V_PER_PIXEL;
+    match handle {
+        ManeuverHandle::Prograde => node.prograde += change,
+        ManeuverHandle::Retrograde => node.prograde -= change,
+        ManeuverHandle::RadialOut => node.radial += change,
+        ManeuverHandle::RadialIn => node.radial -= change,
+    }
+}
+
+/// Drops nodes whose burn time has passed
+pub fn expire_maneuver_nodes_system(clock: Res<MissionClock>, mut plans: Query<&mut ManeuverPlan>) {
+    for mut plan in &mut plans {
+        // Burns that are in the past have been flown (or missed)
+        let passed = plan
+            .nodes
+            .iter()
+            .take_while(|node| node.time < clock.elapsed)
+            .count();
+        if passed > 0 {
+            plan.nodes.drain(..passed);
+            plan.selected = plan
+                .selected
+                .and_then(|index| index.checked_sub(passed))
+                .filter(|index| *index < plan.nodes.len());
+        }
+    }
+}
+
+/// Draws the post-burn trajectory, the nodes and the handles of the selected node
+pub fn render_maneuvers_system(
+    mut gizmos: Gizmos,
+    plans: Query<&ManeuverPlan>,
+    camera: Query<&Projection, With<Camera2d>>,
+) {
+    let cam_scale = camera
+        .iter()
+        .find_map(|proj| match proj {
+            Projection::Orthographic(ortho) => Some(ortho.scale),
+            _ => None,
+        })
+        .unwrap_or(1.0);
+
+    for plan in &plans {
+        for (first, second) in plan.planned_points.iter().tuple_windows() {
+            gizmos.line(*first, *second, Color::srgb(1.0, 0.6, 0.1));
+        }
+
+        for (index, position) in plan.node_positions.iter().enumerate() {
+            let Some(position) = position else {
+                continue;
+            };
+
+            let selected = plan.selected == Some(index);
+            let color = if selected {
+                Color::WHITE
+            } else {
+                Color::srgb(0.6, 0.6, 0.6)
+            };
+            gizmos.circle_2d(position.truncate(), 6. * cam_scale, color);
+
+            if !selected {
+                continue;
+            }
+
+            let Some(frame) = plan.node_frames.get(index) else {
+                continue;
+            };
+            for handle in ManeuverHandle::ALL {
+                let direction = handle.direction(*frame);
+                let end = *position + direction * HANDLE_DISTANCE_PX * cam_scale;
+                gizmos.line(*position, end, handle.color());
+                gizmos.circle_2d(end.truncate(), 5. * cam_scale, handle.color());
+            }
+        }
+    }
+}
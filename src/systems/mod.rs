@@ -1,7 +1,15 @@
+pub mod appearance;
 pub mod camera;
+pub mod flight_computer;
+pub mod maneuver;
 pub mod objectives;
+pub mod orbit;
 pub mod physics;
 pub mod prediction;
+pub mod save_game;
+pub mod staging;
+pub mod transfer;
 pub mod ui;
 pub mod user_control;
+pub mod warp;
 pub mod world_setup;
@@ -1,124 +1,121 @@
+use crate::components::celestial_body::CelestialBody;
+use crate::components::damage::Destroyed;
+use crate::components::landing::SurfaceContact;
 use crate::components::markers::User;
 use crate::components::objectives::{Objective, ObjectiveProgress};
+use crate::components::orbit::Orbit;
 use crate::components::physics_object::PhysicsObject;
-use crate::constants::{EARTH_RADIUS, LEO_MIN_ALTITUDE, LEO_REQUIRED_TIME, MOON_RADIUS};
+use crate::constants::{ESCAPE_REQUIRED_TIME, LEO_MIN_ALTITUDE, ORBIT_REQUIRED_SWEEP_DEGREES};
+use crate::systems::physics::MissionClock;
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
+use std::ops::RangeInclusive;
+use std::time::Duration;
 
-/// Context about the celestial body the user is currently orbiting/near
-struct CelestialBodyContext {
-    distance: f32,
-    radius: f32,
-    position: Vec3,
-    is_moon_central: bool,
-}
-
-/// Context about the user's current state
-struct UserContext {
-    position: Vec3,
-    velocity: Vec3,
-}
+/// Vessels still able to work on their objectives
+type TrackerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static PhysicsObject,
+        &'static Orbit,
+        &'static mut ObjectiveTracker,
+        Option<&'static SurfaceContact>,
+    ),
+    (With<User>, Without<Destroyed>),
+>;
 
 fn check_earth_landing_objective(
     tracker: &mut ObjectiveTracker,
-    celestial_context: &CelestialBodyContext,
+    phys: &PhysicsObject,
+    central: Option<&CelestialBody>,
+    contact: Option<&SurfaceContact>,
 ) {
-    // For earth landing, we must be closest to Earth (not Moon)
-    if celestial_context.is_moon_central {
+    // For the landing we must be in a planet's sphere of influence, not a moon's
+    if central.is_none_or(CelestialBody::is_moon) {
         return;
     }
 
-    // Check if we're close enough to Earth to consider it a landing
-    // Use a more reasonable landing tolerance (1km above surface)
-    let landing_tolerance = 1000.0; // 1km
-    let altitude_above_surface = celestial_context.distance - celestial_context.radius;
-
-    if altitude_above_surface <= landing_tolerance {
-        tracker
-            .progress
-            .complete_current(celestial_context.distance);
-        // Earth landing achieved at altitude: {:.1} m
+    // Only a touchdown on the planet itself that the vessel survived and still
+    // rests on counts, crashes destroy the vessel and bounces lift it off again
+    if let Some(touchdown) = contact.and_then(SurfaceContact::current_touchdown)
+        && Some(touchdown.body) == phys.central_body
+        && touchdown.outcome.survived()
+    {
+        tracker.progress.complete_current(touchdown.time as f32);
     }
 }
 
 #[derive(Component)]
 pub struct ObjectiveTracker {
     pub progress: ObjectiveProgress,
-    pub check_stopwatch: Stopwatch,
-    pub leo_stopwatch: Stopwatch,
+    /// Mission time of the previous check, in seconds
+    pub last_check: Option<f64>,
+    /// Mission time spent outside the moon's sphere of influence
+    pub escape_stopwatch: Stopwatch,
+    /// Degrees of mean anomaly covered while the orbit requirements were met
+    pub orbit_sweep: f32,
 }
 
-impl Default for ObjectiveTracker {
-    fn default() -> Self {
+impl ObjectiveTracker {
+    pub fn new(progress: ObjectiveProgress) -> Self {
         Self {
-            progress: ObjectiveProgress::default(),
-            check_stopwatch: Stopwatch::new(),
-            leo_stopwatch: Stopwatch::new(),
+            progress,
+            last_check: None,
+            escape_stopwatch: Stopwatch::new(),
+            orbit_sweep: 0.0,
         }
     }
+
+    fn reset_orbit_sweep(&mut self) {
+        self.orbit_sweep = 0.0;
+    }
+}
+
+impl Default for ObjectiveTracker {
+    fn default() -> Self {
+        Self::new(ObjectiveProgress::default())
+    }
 }
 
+/// Checks the current objective once per fixed step, in mission time
+///
+/// Runs after the orbits are updated, so every check sees the state the step
+/// ended in however far the time warp carried it.
 pub fn objectives_system(
-    time: Res<Time>,
-    mut query: Query<(&Transform, &mut PhysicsObject, &mut ObjectiveTracker), With<User>>,
-    moon_query: Query<&Transform, (Without<User>, With<PhysicsObject>)>,
+    clock: Res<MissionClock>,
+    mut query: TrackerQuery,
+    bodies: Query<&CelestialBody>,
 ) {
-    for (transform, physics, mut tracker) in query.iter_mut() {
-        tracker.check_stopwatch.tick(time.delta());
+    for (phys, orbit, mut tracker, contact) in query.iter_mut() {
+        let elapsed = tracker
+            .last_check
+            .map_or(0., |last| (clock.elapsed - last).max(0.));
+        tracker.last_check = Some(clock.elapsed);
+        let elapsed = Duration::from_secs_f64(elapsed);
 
-        // Only check objectives every 0.02 seconds (50fps)
-        if tracker.check_stopwatch.elapsed_secs() < 0.02 {
-            continue;
-        }
-        tracker.check_stopwatch.reset();
-
-        let position = transform.translation;
-        let velocity = physics.vel;
-
-        // Determine central body (closest celestial body)
-        let distance_from_earth = position.length();
-        let mut closest_moon_distance = f32::INFINITY;
-        let mut closest_moon_position = Vec3::ZERO;
-
-        for moon_transform in moon_query.iter() {
-            let distance_to_moon = position.distance(moon_transform.translation);
-            if distance_to_moon < closest_moon_distance {
-                closest_moon_distance = distance_to_moon;
-                closest_moon_position = moon_transform.translation;
-            }
-        }
-
-        // Update central body based on closest object
-        let (central_body_distance, central_body_radius, central_body_position, is_moon_central) =
-            if closest_moon_distance < distance_from_earth {
-                (
-                    closest_moon_distance,
-                    MOON_RADIUS,
-                    closest_moon_position,
-                    true,
-                )
-            } else {
-                (distance_from_earth, EARTH_RADIUS, Vec3::ZERO, false)
-            };
-
-        let celestial_context = CelestialBodyContext {
-            distance: central_body_distance,
-            radius: central_body_radius,
-            position: central_body_position,
-            is_moon_central,
-        };
-
-        let user_context = UserContext { position, velocity };
+        // Judged against the body whose sphere of influence we are in, a moon
+        // being any body that orbits another
+        let central = phys.central_body.and_then(|entity| bodies.get(entity).ok());
 
         match tracker.progress.current {
             Objective::EscapeMoon => {
-                check_escape_moon_objective(&mut tracker, &celestial_context, &time);
+                check_escape_moon_objective(&mut tracker, central, elapsed);
             }
-            Objective::OrbitEarth => {
-                check_orbit_objective(&mut tracker, &celestial_context, &user_context, &time);
+            Objective::OrbitEarth {
+                min_apoapsis,
+                max_apoapsis,
+            } => {
+                check_orbit_objective(
+                    &mut tracker,
+                    central,
+                    orbit,
+                    min_apoapsis..=max_apoapsis,
+                    elapsed,
+                );
             }
             Objective::LandOnEarth => {
-                check_earth_landing_objective(&mut tracker, &celestial_context);
+                check_earth_landing_objective(&mut tracker, phys, central, contact);
             }
         }
 
@@ -131,69 +128,168 @@ pub fn objectives_system(
 
 fn check_escape_moon_objective(
     tracker: &mut ObjectiveTracker,
-    celestial_context: &CelestialBodyContext,
-    time: &Time,
+    central: Option<&CelestialBody>,
+    elapsed: Duration,
 ) {
-    // For escape moon objective, we need to NOT be orbiting the Moon anymore
-    if !celestial_context.is_moon_central {
-        // We've escaped the Moon's sphere of influence!
-        tracker.leo_stopwatch.tick(time.delta());
+    // For escape moon objective, we need to NOT be orbiting a moon anymore
+    if !central.is_some_and(CelestialBody::is_moon) {
+        // We've escaped the moon's sphere of influence!
+        tracker.escape_stopwatch.tick(elapsed);
 
-        // Check if we've been away from the Moon long enough
-        if tracker.leo_stopwatch.elapsed_secs() >= LEO_REQUIRED_TIME {
+        // Check if we've been away from the moon long enough
+        if tracker.escape_stopwatch.elapsed_secs() >= ESCAPE_REQUIRED_TIME {
             tracker
                 .progress
-                .complete_current(tracker.leo_stopwatch.elapsed_secs());
-            // Moon escape achieved! Time away from Moon: {:.1}s
+                .complete_current(tracker.escape_stopwatch.elapsed_secs());
+            // Moon escape achieved! Time away from the moon: {:.1}s
         }
     } else {
-        // Still in Moon's sphere of influence
-        tracker.leo_stopwatch.reset();
+        // Still in the moon's sphere of influence
+        tracker.escape_stopwatch.reset();
     }
 }
 
 fn check_orbit_objective(
     tracker: &mut ObjectiveTracker,
-    celestial_context: &CelestialBodyContext,
-    user_context: &UserContext,
-    time: &Time,
+    central: Option<&CelestialBody>,
+    orbit: &Orbit,
+    apoapsis_band: RangeInclusive<f32>,
+    elapsed: Duration,
 ) {
-    // For LEO objective, we must be orbiting Earth (not Moon)
-    if celestial_context.is_moon_central {
-        tracker.leo_stopwatch.reset();
+    // For LEO objective, we must be orbiting a planet, not a moon
+    if central.is_none_or(CelestialBody::is_moon) {
+        tracker.reset_orbit_sweep();
         return;
     }
 
-    // Calculate altitude above central body's surface
-    let altitude = celestial_context.distance - celestial_context.radius;
-
-    // Check if above minimum LEO altitude (40 km above Earth surface)
-    if altitude >= LEO_MIN_ALTITUDE {
-        // Check if orbit is stable by verifying velocity is roughly perpendicular to position
-        let relative_position = user_context.position - celestial_context.position;
-        let dot_product = relative_position
-            .normalize()
-            .dot(user_context.velocity.normalize())
-            .abs();
-        let is_orbital_velocity = dot_product < 0.3; // Less than ~17 degrees off perpendicular
-
-        if is_orbital_velocity {
-            // Tick the LEO stopwatch
-            tracker.leo_stopwatch.tick(time.delta());
-
-            // Check if we've been in stable LEO long enough
-            if tracker.leo_stopwatch.elapsed_secs() >= LEO_REQUIRED_TIME {
-                tracker
-                    .progress
-                    .complete_current(tracker.leo_stopwatch.elapsed_secs());
-                // Low Earth Orbit achieved! Time in orbit: {:.1}s
-            }
-        } else {
-            // Reset stopwatch if not in stable orbit
-            tracker.leo_stopwatch.reset();
+    let Some(elements) = orbit.elements else {
+        tracker.reset_orbit_sweep();
+        return;
+    };
+
+    // A closed orbit whose lowest point clears the surface by the LEO margin
+    // and whose highest point stays within the requested band
+    let periapsis_clear = orbit
+        .periapsis_altitude()
+        .is_some_and(|altitude| altitude >= LEO_MIN_ALTITUDE);
+    let apoapsis_in_band = orbit
+        .apoapsis_altitude()
+        .is_some_and(|altitude| apoapsis_band.contains(&altitude));
+
+    let Some(period) = elements
+        .period
+        .filter(|_| periapsis_clear && apoapsis_in_band)
+    else {
+        tracker.reset_orbit_sweep();
+        return;
+    };
+
+    // Accumulate how far around the orbit we have travelled from the mission
+    // time that passed, which no time warp can alias
+    tracker.orbit_sweep += elapsed.as_secs_f32() / period * 360.;
+
+    if tracker.orbit_sweep >= ORBIT_REQUIRED_SWEEP_DEGREES {
+        let sweep = tracker.orbit_sweep;
+        tracker.progress.complete_current(sweep);
+        // Planet orbit achieved! Degrees travelled in orbit: {:.1}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::landing::{LandingOutcome, Touchdown};
+    use crate::constants::LEO_MAX_APOAPSIS_ALTITUDE;
+    use crate::orbital::OrbitalElements;
+    use bevy::math::{DVec3, Vec3};
+
+    const RADIUS: f32 = 318_550.;
+    const MU: f32 = 5.0e11;
+
+    /// Tracker working on the orbit objective and a circular orbit 60 km up
+    fn orbiting() -> (ObjectiveTracker, Orbit) {
+        let progress = ObjectiveProgress::new(vec![Objective::orbit_earth()]).unwrap();
+        let radius = RADIUS + 60_000.;
+        let elements = OrbitalElements::from_state_vectors(
+            Vec3::X * radius,
+            Vec3::Y * (MU / radius).sqrt(),
+            MU,
+        );
+        let orbit = Orbit {
+            elements: Some(elements),
+            central_radius: RADIUS,
+        };
+        (ObjectiveTracker::new(progress), orbit)
+    }
+
+    fn check(tracker: &mut ObjectiveTracker, body: &CelestialBody, orbit: &Orbit, seconds: f32) {
+        let band = LEO_MIN_ALTITUDE..=LEO_MAX_APOAPSIS_ALTITUDE;
+        check_orbit_objective(
+            tracker,
+            Some(body),
+            orbit,
+            band,
+            Duration::from_secs_f32(seconds),
+        );
+    }
+
+    #[test]
+    fn orbit_sweep_follows_mission_time() {
+        let planet = CelestialBody::new("Earth", RADIUS, None, Color::WHITE);
+        let (mut tracker, orbit) = orbiting();
+        let period = orbit.elements.unwrap().period.unwrap();
+
+        check(&mut tracker, &planet, &orbit, period / 8.);
+        assert!((tracker.orbit_sweep - 45.).abs() < 0.1);
+        assert!(!tracker.progress.is_completed);
+
+        // A single check at top warp can span more than the whole orbit
+        let (mut tracker, orbit) = orbiting();
+        check(&mut tracker, &planet, &orbit, 1.4 * period);
+        assert!(tracker.progress.is_completed);
+    }
+
+    #[test]
+    fn moons_do_not_count_for_orbits() {
+        let moon = CelestialBody::new("Luna", RADIUS, Some(Entity::PLACEHOLDER), Color::WHITE);
+        let (mut tracker, orbit) = orbiting();
+
+        check(&mut tracker, &moon, &orbit, 1000.);
+        assert_eq!(tracker.orbit_sweep, 0.);
+        assert!(!tracker.progress.is_completed);
+    }
+
+    /// A gentle touchdown on `body` that the vessel still rests on
+    fn resting_on(body: Entity) -> SurfaceContact {
+        SurfaceContact {
+            touching: Some(body),
+            last_touchdown: Some(Touchdown {
+                body,
+                time: 10.,
+                impact_speed: 1.,
+                vertical_speed: 1.,
+                horizontal_speed: 0.,
+                tilt: 0.,
+                outcome: LandingOutcome::Landed,
+            }),
         }
-    } else {
-        // Reset LEO stopwatch if we leave the altitude range
-        tracker.leo_stopwatch.reset();
+    }
+
+    #[test]
+    fn only_touchdowns_on_the_planet_land() {
+        let earth = Entity::from_raw(1);
+        let moon = Entity::from_raw(2);
+        let planet = CelestialBody::new("Earth", RADIUS, None, Color::WHITE);
+        let phys = PhysicsObject::new(1000., 10., DVec3::ZERO, DVec3::ZERO, Some(earth));
+        let landing =
+            || ObjectiveTracker::new(ObjectiveProgress::new(vec![Objective::LandOnEarth]).unwrap());
+
+        let mut tracker = landing();
+        check_earth_landing_objective(&mut tracker, &phys, Some(&planet), Some(&resting_on(moon)));
+        assert!(!tracker.progress.is_completed);
+
+        let mut tracker = landing();
+        check_earth_landing_objective(&mut tracker, &phys, Some(&planet), Some(&resting_on(earth)));
+        assert!(tracker.progress.is_completed);
     }
 }
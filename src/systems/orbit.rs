@@ -0,0 +1,25 @@
+use crate::components::orbit::Orbit;
+use crate::components::physics_object::PhysicsObject;
+use crate::constants::G;
+use crate::orbital::OrbitalElements;
+use bevy::prelude::*;
+
+/// Recomputes every object's orbital elements relative to its central body
+pub fn orbit_system(mut query: Query<(&PhysicsObject, &mut Orbit)>, bodies: Query<&PhysicsObject>) {
+    for (phys, mut orbit) in &mut query {
+        let central = phys.central_body.and_then(|entity| bodies.get(entity).ok());
+
+        let Some(central_phys) = central else {
+            orbit.elements = None;
+            continue;
+        };
+
+        let mu = G * (central_phys.mass + phys.mass);
+        orbit.elements = Some(OrbitalElements::from_state_vectors(
+            (phys.position - central_phys.position).as_vec3(),
+            (phys.vel - central_phys.vel).as_vec3(),
+            mu,
+        ));
+        orbit.central_radius = central_phys.radius;
+    }
+}
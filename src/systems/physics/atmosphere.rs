@@ -0,0 +1,179 @@
+use crate::components::aerodynamics::Aerodynamics;
+use crate::components::atmosphere::Atmosphere;
+use crate::components::damage::{Destroyed, DestructionCause};
+use crate::components::physics_object::PhysicsObject;
+use crate::constants::{AMBIENT_TEMPERATURE, HULL_EMISSIVITY, STANDARD_GRAVITY, STEFAN_BOLTZMANN};
+use crate::systems::physics::MissionClock;
+use bevy::prelude::*;
+
+type VesselQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut PhysicsObject,
+        &'static mut Aerodynamics,
+        Has<Destroyed>,
+    ),
+    Without<Atmosphere>,
+>;
+
+/// Applies atmospheric drag, heats the hull and breaks up vessels pushed past
+/// their limits
+///
+/// Runs after propulsion so the g-load includes thrust, and before gravity so
+/// the drag force is integrated in the same step.
+pub fn atmosphere_system(
+    mut commands: Commands,
+    clock: Res<MissionClock>,
+    mut vessels: VesselQuery,
+    bodies: Query<(&PhysicsObject, &Atmosphere)>,
+) {
+    for (entity, mut phys, mut aero, destroyed) in &mut vessels {
+        // The atmosphere co-moves with its body, only the densest one applies
+        let air = bodies
+            .iter()
+            .map(|(body, atmosphere)| {
+                let offset = (phys.position - body.position).as_vec3();
+                let altitude = offset.length() - body.radius;
+                let airspeed = (phys.vel - body.vel).as_vec3();
+                (atmosphere, altitude, airspeed)
+            })
+            .filter(|(atmosphere, altitude, _)| atmosphere.density(*altitude) > 0.)
+            .max_by(|(a, a_alt, _), (b, b_alt, _)| a.density(*a_alt).total_cmp(&b.density(*b_alt)));
+
+        let (drag, heat_flux) = air.map_or((Vec3::ZERO, 0.), |(atmosphere, altitude, airspeed)| {
+            let speed = airspeed.length();
+            let density = atmosphere.density(altitude);
+            let drag_accel =
+                0.5 * density * speed * aero.drag_coefficient * aero.reference_area / phys.mass;
+            // Drag can stop the vessel relative to the air but never push it backwards
+            let drag_accel = drag_accel.min(1. / clock.dt);
+            (
+                -airspeed * drag_accel * phys.mass,
+                atmosphere.heat_flux(altitude, speed, aero.nose_radius),
+            )
+        });
+        phys.applied_force += drag;
+
+        // The hull relaxes towards the temperature at which it radiates away the incoming heat
+        let equilibrium = (heat_flux / (HULL_EMISSIVITY * STEFAN_BOLTZMANN))
+            .powf(0.25)
+            .max(AMBIENT_TEMPERATURE);
+        let blend = 1. - (-clock.dt / aero.thermal_time_constant).exp();
+        aero.temperature += (equilibrium - aero.temperature) * blend;
+        aero.heat_flux = heat_flux;
+        aero.g_load = phys.applied_force.length() / phys.mass / STANDARD_GRAVITY;
+
+        if destroyed {
+            continue;
+        }
+
+        let cause = if aero.temperature > aero.max_temperature {
+            Some(DestructionCause::Overheated)
+        } else if aero.g_load > aero.max_g_load {
+            Some(DestructionCause::Overstressed)
+        } else {
+            None
+        };
+
+        if let Some(cause) = cause {
+            warn!(
+                "Vessel {} at {:.0}K and {:.1}g",
+                cause.label(),
+                aero.temperature,
+                aero.g_load
+            );
+            commands.entity(entity).insert(Destroyed(cause));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::math::DVec3;
+
+    const RADIUS: f32 = 600_000.;
+    const AIR: Atmosphere = Atmosphere {
+        sea_level_density: 1.2,
+        scale_height: 5_600.,
+        height: 70_000.,
+        heating_coefficient: 1.7e-4,
+    };
+
+    /// Runs one second of the atmosphere step on a vessel `altitude` meters
+    /// above a resting planet, flying sideways at `speed`
+    fn fly(altitude: f32, speed: f64) -> (PhysicsObject, Aerodynamics, bool) {
+        let mut world = World::new();
+        world.insert_resource(MissionClock {
+            dt: 1.,
+            ..default()
+        });
+        world.spawn((
+            PhysicsObject::new(5.0e22, RADIUS, DVec3::ZERO, DVec3::ZERO, None),
+            AIR,
+        ));
+        let position = DVec3::Y * (RADIUS + altitude) as f64;
+        let vessel = world
+            .spawn((
+                PhysicsObject::new(10_000., 5., position, DVec3::X * speed, None),
+                Aerodynamics::default(),
+            ))
+            .id();
+        world.run_system_once(atmosphere_system).unwrap();
+
+        let entity = world.entity(vessel);
+        (
+            entity.get::<PhysicsObject>().unwrap().clone(),
+            entity.get::<Aerodynamics>().unwrap().clone(),
+            entity.contains::<Destroyed>(),
+        )
+    }
+
+    #[test]
+    fn drag_opposes_the_airspeed() {
+        let (phys, aero, destroyed) = fly(40_000., 200.);
+        let drag = 0.5
+            * AIR.density(40_000.)
+            * 200_f32.powi(2)
+            * aero.drag_coefficient
+            * aero.reference_area;
+
+        assert!(phys.applied_force.x < 0.);
+        assert!((phys.applied_force.x + drag).abs() < 1e-3 * drag);
+        assert_eq!(phys.applied_force.y, 0.);
+        assert!((aero.g_load - drag / phys.mass / STANDARD_GRAVITY).abs() < 1e-6);
+        assert!(!destroyed);
+    }
+
+    #[test]
+    fn heating_follows_sutton_graves() {
+        let (_, aero, _) = fly(40_000., 3_000.);
+        let flux = AIR.heat_flux(40_000., 3_000., aero.nose_radius);
+        let equilibrium = (flux / (HULL_EMISSIVITY * STEFAN_BOLTZMANN)).powf(0.25);
+
+        assert!((aero.heat_flux - flux).abs() < 1e-3 * flux);
+        // One second closes only part of the gap to the equilibrium
+        assert!(aero.temperature > AMBIENT_TEMPERATURE);
+        assert!(aero.temperature < equilibrium);
+    }
+
+    #[test]
+    fn vacuum_has_no_drag_or_heating() {
+        let (phys, aero, _) = fly(AIR.height + 1., 2_000.);
+
+        assert_eq!(phys.applied_force, Vec3::ZERO);
+        assert_eq!(aero.heat_flux, 0.);
+        assert_eq!(aero.g_load, 0.);
+    }
+
+    #[test]
+    fn excessive_drag_breaks_up_the_vessel() {
+        let (_, aero, destroyed) = fly(0., 1_000.);
+
+        assert!(aero.g_load > aero.max_g_load);
+        assert!(destroyed);
+    }
+}
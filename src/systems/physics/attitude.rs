@@ -0,0 +1,153 @@
+use crate::components::attitude::AttitudeControl;
+use crate::components::damage::Destroyed;
+use crate::components::physics_object::PhysicsObject;
+use crate::constants::{AUTOPILOT_BRAKING_MARGIN, AUTOPILOT_SETTLE_TIME};
+use crate::systems::physics::MissionClock;
+use bevy::prelude::*;
+
+/// Turns vessels by their angular velocity and applies control torque
+///
+/// Pilot input takes precedence, otherwise SAS turns towards the hold direction
+/// at the fastest rate it can still brake from, or just stops the spin, within
+/// what the actuators allow. The reaction wheels cover small torques for free,
+/// the RCS only burns monopropellant for the rest.
+/// Runs before propulsion so the engine fires along the updated heading.
+pub fn attitude_system(
+    clock: Res<MissionClock>,
+    mut query: Query<(
+        &mut Transform,
+        &mut AttitudeControl,
+        &PhysicsObject,
+        Has<Destroyed>,
+    )>,
+) {
+    let dt = clock.dt;
+
+    for (mut transform, mut control, phys, destroyed) in &mut query {
+        let inertia = phys.moment_of_inertia();
+        let max_torque = control.max_torque();
+
+        let command = if destroyed || max_torque <= 0. {
+            0.
+        } else if control.torque_input != 0. {
+            control.torque_input.clamp(-1., 1.)
+        } else if control.sas {
+            let target_rate = control.hold_direction.map_or(0., |direction| {
+                let nose = transform.rotation.mul_vec3(Vec3::Y).truncate();
+                let error = nose.angle_to(direction.truncate());
+                let braking = AUTOPILOT_BRAKING_MARGIN * max_torque / inertia;
+                // Small errors are closed gently, and never overshot within one step
+                let rate = (2. * braking * error.abs())
+                    .sqrt()
+                    .min(error.abs() / dt.max(AUTOPILOT_SETTLE_TIME));
+                rate.copysign(error)
+            });
+            ((target_rate - control.angular_velocity) * inertia / (max_torque * dt)).clamp(-1., 1.)
+        } else {
+            0.
+        };
+        let torque = command * max_torque;
+
+        if control.rcs_torque > 0. && control.monopropellant > 0. {
+            let rcs_share = ((torque.abs() - control.wheel_torque) / control.rcs_torque).max(0.);
+            control.monopropellant =
+                (control.monopropellant - control.monopropellant_rate * rcs_share * dt).max(0.);
+        }
+
+        control.angular_velocity += torque / inertia * dt;
+        transform.rotate_z(control.angular_velocity * dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::math::DVec3;
+    use std::f32::consts::FRAC_PI_2;
+
+    /// World with one vessel spinning at `spin` rad/s under `control`
+    fn spinning(spin: f32, control: AttitudeControl) -> (World, Entity) {
+        let mut world = World::new();
+        world.insert_resource(MissionClock {
+            dt: 0.02,
+            ..default()
+        });
+        let vessel = world
+            .spawn((
+                Transform::default(),
+                AttitudeControl {
+                    angular_velocity: spin,
+                    ..control
+                },
+                PhysicsObject::new(10_000., 5., DVec3::ZERO, DVec3::ZERO, None),
+            ))
+            .id();
+        (world, vessel)
+    }
+
+    fn step(world: &mut World, vessel: Entity) -> AttitudeControl {
+        world.run_system_once(attitude_system).unwrap();
+        world.get::<AttitudeControl>(vessel).unwrap().clone()
+    }
+
+    #[test]
+    fn sas_damps_the_spin() {
+        let (mut world, vessel) = spinning(3., AttitudeControl::default());
+
+        let mut spin = 3.;
+        for _ in 0..10 {
+            let control = step(&mut world, vessel);
+            assert!(control.angular_velocity.abs() <= spin);
+            spin = control.angular_velocity.abs();
+        }
+        assert_eq!(spin, 0.);
+    }
+
+    #[test]
+    fn wheels_alone_spare_the_monopropellant() {
+        // Within reach of the wheels in a single step
+        let (mut world, vessel) = spinning(0.1, AttitudeControl::default());
+        let control = step(&mut world, vessel);
+        assert!(control.angular_velocity.abs() < 1e-6);
+        assert_eq!(control.monopropellant, control.max_monopropellant);
+
+        let (mut world, vessel) = spinning(3., AttitudeControl::default());
+        let control = step(&mut world, vessel);
+        assert!(control.monopropellant < control.max_monopropellant);
+    }
+
+    #[test]
+    fn spin_persists_without_sas() {
+        let control = AttitudeControl {
+            sas: false,
+            ..default()
+        };
+        let (mut world, vessel) = spinning(0.5, control);
+
+        assert_eq!(step(&mut world, vessel).angular_velocity, 0.5);
+        let rotation = world.get::<Transform>(vessel).unwrap().rotation;
+        assert!((rotation.to_euler(EulerRot::XYZ).2 - 0.01).abs() < 1e-6);
+    }
+
+    #[test]
+    fn hold_turns_onto_the_direction_without_overshoot() {
+        let control = AttitudeControl {
+            hold_direction: Some(Vec3::X),
+            ..default()
+        };
+        let (mut world, vessel) = spinning(0., control);
+
+        // The nose starts along +Y, a quarter turn counter-clockwise of +X
+        let mut error = -FRAC_PI_2;
+        for _ in 0..500 {
+            step(&mut world, vessel);
+            let nose = world.get::<Transform>(vessel).unwrap().rotation * Vec3::Y;
+            let next = nose.truncate().angle_to(Vec2::X);
+            assert!(next >= error - 1e-5);
+            assert!(next < 1e-3);
+            error = next;
+        }
+        assert!(error.abs() < 1e-3);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::components::attitude::{AttitudeControl, AttitudeHold};
+use crate::components::physics_object::PhysicsObject;
+use crate::components::target::Target;
+use bevy::prelude::*;
+
+/// World direction a hold mode points at
+///
+/// Returns `None` for the stability hold and when the reference body the mode
+/// needs is missing or the direction is degenerate.
+pub fn hold_direction(
+    hold: AttitudeHold,
+    vessel: &PhysicsObject,
+    central: Option<&PhysicsObject>,
+    target: Option<&PhysicsObject>,
+) -> Option<Vec3> {
+    let direction = match hold {
+        AttitudeHold::Stability => return None,
+        AttitudeHold::Prograde => vessel.vel - central?.vel,
+        AttitudeHold::Retrograde => central?.vel - vessel.vel,
+        AttitudeHold::RadialOut => vessel.position - central?.position,
+        AttitudeHold::RadialIn => central?.position - vessel.position,
+        AttitudeHold::Target => target?.position - vessel.position,
+    };
+    direction.as_vec3().try_normalize()
+}
+
+/// Refreshes the direction each vessel's SAS hold mode points at
+///
+/// Runs before the attitude system, which turns the vessel towards it.
+pub fn autopilot_system(
+    mut vessels: Query<(&PhysicsObject, &mut AttitudeControl, Option<&Target>)>,
+    bodies: Query<&PhysicsObject>,
+) {
+    for (phys, mut control, target) in &mut vessels {
+        let central = phys.central_body.and_then(|entity| bodies.get(entity).ok());
+        let target = target.and_then(|Target(entity)| bodies.get(*entity).ok());
+        control.hold_direction = hold_direction(control.hold, phys, central, target);
+    }
+}
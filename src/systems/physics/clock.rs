@@ -0,0 +1,70 @@
+use crate::components::damage::Destroyed;
+use crate::components::propulsion::Propulsion;
+use crate::config::Config;
+use crate::constants::{BURN_MAX_SUBSTEPS, BURN_SUBSTEP_DT};
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+
+/// Simulated time elapsed since the scenario started
+///
+/// The one time source of the physics, every system in [`PhysicsStep`] reads
+/// the length of the step it is in from here.
+#[derive(Resource, Default)]
+pub struct MissionClock {
+    /// Mission time in seconds
+    pub elapsed: f64,
+    /// Simulated seconds covered by the physics step being run
+    pub dt: f32,
+    /// Mission time the physics halts at until cleared, so a warp ends exactly there
+    pub stop_at: Option<f64>,
+}
+
+/// Schedule of the systems that advance the physics by [`MissionClock::dt`]
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PhysicsStep;
+
+/// Advances the mission clock by one fixed step, running [`PhysicsStep`] for it
+///
+/// While an engine fires the step is split into substeps no longer than an
+/// unwarped one, so thrust, fuel and attitude are integrated the same at any
+/// time warp. Coasting covers whatever is left of the step at once. Very high
+/// warp stages are limited to `BURN_MAX_SUBSTEPS`, which keeps them playable.
+/// A step that would pass [`MissionClock::stop_at`] is cut short to end on it.
+pub fn physics_step_system(
+    world: &mut World,
+    burning: &mut QueryState<&Propulsion, Without<Destroyed>>,
+) {
+    let mut step = world.resource::<Config>().step_dt();
+    let clock = world.resource::<MissionClock>();
+    let stop = clock
+        .stop_at
+        .filter(|stop| *stop - clock.elapsed <= step as f64);
+    if let Some(stop) = stop {
+        if stop <= clock.elapsed {
+            return;
+        }
+        step = (stop - clock.elapsed) as f32;
+    }
+    let substeps = ((step / BURN_SUBSTEP_DT).ceil() as u32).clamp(1, BURN_MAX_SUBSTEPS);
+    let substep = step / substeps as f32;
+
+    let mut taken = 0;
+    while taken < substeps {
+        let burning = burning
+            .iter(world)
+            .any(|propulsion| propulsion.thrust_percentage != 0. && propulsion.fuel > 0.);
+        let count = if burning { 1 } else { substeps - taken };
+        taken += count;
+
+        let mut clock = world.resource_mut::<MissionClock>();
+        clock.dt = substep * count as f32;
+        clock.elapsed += clock.dt as f64;
+        if taken == substeps
+            && let Some(stop) = stop
+        {
+            // Land on the stop itself rather than within rounding of it
+            clock.elapsed = stop;
+        }
+        world.run_schedule(PhysicsStep);
+    }
+}
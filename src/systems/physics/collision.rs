@@ -1,31 +1,56 @@
+use crate::components::damage::{Destroyed, DestructionCause};
+use crate::components::landing::{LandingOutcome, SurfaceContact, Touchdown};
 use crate::components::physics_object::PhysicsObject;
-use crate::config::Config;
+use crate::constants::LANDING_CONTACT_MARGIN;
+use crate::systems::physics::MissionClock;
+use bevy::math::DVec3;
 use bevy::prelude::*;
 
+/// Sent when a vessel first touches a body
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ContactEvent {
+    pub vessel: Entity,
+    pub touchdown: Touchdown,
+}
+
+type CollisionQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut PhysicsObject,
+        &'static Transform,
+        Option<&'static mut SurfaceContact>,
+        Has<Destroyed>,
+    ),
+>;
+
 pub fn collision_system(
-    config: Res<Config>,
-    mut query: Query<(Entity, &mut Transform, &mut PhysicsObject)>,
+    mut commands: Commands,
+    clock: Res<MissionClock>,
+    mut contacts: EventWriter<ContactEvent>,
+    mut query: CollisionQuery,
 ) {
     let mut combinations = query.iter_combinations_mut();
 
     while let Some(
         [
-            (_entity_a, mut transform_a, mut physics_a),
-            (_entity_b, mut transform_b, mut physics_b),
+            (entity_a, mut physics_a, transform_a, mut contact_a, destroyed_a),
+            (entity_b, mut physics_b, transform_b, mut contact_b, destroyed_b),
         ],
     ) = combinations.fetch_next()
     {
         // Current positions
-        let pos_a = transform_a.translation;
-        let pos_b = transform_b.translation;
+        let pos_a = physics_a.position;
+        let pos_b = physics_b.position;
 
         // Previous positions (approximate based on velocity)
-        let dt = config.dt;
+        let dt = clock.dt as f64;
         let prev_pos_a = pos_a - physics_a.vel * dt;
         let prev_pos_b = pos_b - physics_b.vel * dt;
 
         // Check for collision using continuous collision detection
-        let min_distance = physics_a.radius + physics_b.radius;
+        let min_distance = (physics_a.radius + physics_b.radius) as f64;
 
         if check_continuous_collision(prev_pos_a, pos_a, prev_pos_b, pos_b, min_distance) {
             // Calculate collision point and normal
@@ -35,12 +60,58 @@ pub fn collision_system(
             if distance > 0.0 {
                 let collision_normal = delta / distance;
 
+                // Touchdowns are measured before the impulse changes the velocities
+                let touchdowns = [
+                    contact_a
+                        .as_deref_mut()
+                        .and_then(|contact| {
+                            new_touchdown(
+                                contact,
+                                &physics_a,
+                                transform_a.rotation,
+                                entity_b,
+                                &physics_b,
+                                clock.elapsed,
+                            )
+                        })
+                        .map(|touchdown| (entity_a, destroyed_a, touchdown)),
+                    contact_b
+                        .as_deref_mut()
+                        .and_then(|contact| {
+                            new_touchdown(
+                                contact,
+                                &physics_b,
+                                transform_b.rotation,
+                                entity_a,
+                                &physics_a,
+                                clock.elapsed,
+                            )
+                        })
+                        .map(|touchdown| (entity_b, destroyed_b, touchdown)),
+                ];
+                for (vessel, destroyed, touchdown) in touchdowns.into_iter().flatten() {
+                    info!(
+                        "Touchdown: {} at {:.1} m/s (vertical {:.1}, horizontal {:.1}, tilt {:.0}°)",
+                        touchdown.outcome.label(),
+                        touchdown.impact_speed,
+                        touchdown.vertical_speed,
+                        touchdown.horizontal_speed,
+                        touchdown.tilt.to_degrees()
+                    );
+                    if touchdown.outcome == LandingOutcome::Destroyed && !destroyed {
+                        commands
+                            .entity(vessel)
+                            .insert(Destroyed(DestructionCause::Crashed));
+                    }
+                    contacts.write(ContactEvent { vessel, touchdown });
+                }
+
                 // Separate objects to prevent overlap
                 let overlap = min_distance - distance;
                 if overlap > 0.0 {
                     let separation = collision_normal * (overlap * 0.5);
-                    transform_a.translation += separation;
-                    transform_b.translation -= separation;
+                    physics_a.position += separation;
+                    physics_b.position -= separation;
                 }
 
                 // Calculate relative velocity
@@ -54,12 +125,12 @@ pub fn collision_system(
 
                     // Calculate impulse magnitude
                     let impulse_magnitude = -(1.0 + restitution) * velocity_along_normal;
-                    let impulse_magnitude =
-                        impulse_magnitude / (1.0 / physics_a.mass + 1.0 / physics_b.mass);
+                    let impulse_magnitude = impulse_magnitude
+                        / (1.0 / physics_a.mass as f64 + 1.0 / physics_b.mass as f64);
 
                     // Store masses to avoid borrowing issues
-                    let mass_a = physics_a.mass;
-                    let mass_b = physics_b.mass;
+                    let mass_a = physics_a.mass as f64;
+                    let mass_b = physics_b.mass as f64;
 
                     // Apply impulse to velocities
                     let impulse = collision_normal * impulse_magnitude;
@@ -69,15 +140,71 @@ pub fn collision_system(
             }
         }
     }
+
+    release_contacts(&mut query);
+}
+
+/// Records the touchdown when a vessel meets `body` for the first time since last touching it
+fn new_touchdown(
+    contact: &mut SurfaceContact,
+    vessel: &PhysicsObject,
+    rotation: Quat,
+    body_entity: Entity,
+    body: &PhysicsObject,
+    time: f64,
+) -> Option<Touchdown> {
+    if contact.touching == Some(body_entity) {
+        return None;
+    }
+
+    let normal = (vessel.position - body.position).normalize().as_vec3();
+    let relative_velocity = (vessel.vel - body.vel).as_vec3();
+    let vertical_speed = -relative_velocity.dot(normal);
+    let horizontal_speed = relative_velocity.reject_from_normalized(normal).length();
+    let tilt = rotation.mul_vec3(Vec3::Y).angle_between(normal);
+
+    let touchdown = Touchdown {
+        body: body_entity,
+        time,
+        impact_speed: relative_velocity.length(),
+        vertical_speed,
+        horizontal_speed,
+        tilt,
+        outcome: LandingOutcome::classify(vertical_speed.max(0.), horizontal_speed, tilt),
+    };
+    contact.touching = Some(body_entity);
+    contact.last_touchdown = Some(touchdown);
+    Some(touchdown)
+}
+
+/// Forgets the touched body once a vessel has lifted off it
+fn release_contacts(query: &mut CollisionQuery) {
+    let released: Vec<Entity> = query
+        .iter()
+        .filter_map(|(entity, phys, _, contact, _)| {
+            let body = contact?.touching?;
+            let lifted_off = query.get(body).is_ok_and(|(_, body, ..)| {
+                phys.position.distance(body.position)
+                    > (phys.radius + body.radius + LANDING_CONTACT_MARGIN) as f64
+            });
+            lifted_off.then_some(entity)
+        })
+        .collect();
+
+    for entity in released {
+        if let Ok((_, _, _, Some(mut contact), _)) = query.get_mut(entity) {
+            contact.touching = None;
+        }
+    }
 }
 
 // Continuous collision detection between two moving spheres
 fn check_continuous_collision(
-    prev_pos_a: Vec3,
-    pos_a: Vec3,
-    prev_pos_b: Vec3,
-    pos_b: Vec3,
-    min_distance: f32,
+    prev_pos_a: DVec3,
+    pos_a: DVec3,
+    prev_pos_b: DVec3,
+    pos_b: DVec3,
+    min_distance: f64,
 ) -> bool {
     // Current distance
     let current_distance = (pos_a - pos_b).length();
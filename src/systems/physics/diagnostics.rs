@@ -0,0 +1,250 @@
+use crate::components::markers::{Debris, User};
+use crate::components::physics_object::PhysicsObject;
+use crate::constants::{DIAGNOSTICS_HISTORY, DIAGNOSTICS_LOG_INTERVAL, G, SOFTENING};
+use crate::systems::physics::MissionClock;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Conserved quantities of the gravitating bodies at one moment
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConservationSample {
+    /// Mission time in seconds
+    pub time: f64,
+    pub kinetic_energy: f64,
+    /// Softened gravitational potential energy, matching the forces used by the integrator
+    pub potential_energy: f64,
+    pub total_energy: f64,
+    pub linear_momentum: DVec3,
+    /// Angular momentum about the origin, out of the plane
+    pub angular_momentum: f64,
+    /// Sum of the momentum magnitudes, the scale momentum drift is measured against
+    pub momentum_scale: f64,
+}
+
+impl ConservationSample {
+    /// Measures the conserved quantities of `bodies` at mission time `time`
+    pub fn measure(time: f64, bodies: &[&PhysicsObject]) -> Self {
+        let mut sample = ConservationSample { time, ..default() };
+
+        for (i, phys) in bodies.iter().enumerate() {
+            let mass = phys.mass as f64;
+            let momentum = mass * phys.vel;
+            sample.kinetic_energy += 0.5 * mass * phys.vel.length_squared();
+            sample.linear_momentum += momentum;
+            sample.angular_momentum += phys.position.truncate().perp_dot(momentum.truncate());
+            sample.momentum_scale += momentum.length();
+
+            for other in &bodies[i + 1..] {
+                let distance_softened = (phys.position.distance_squared(other.position)
+                    + (SOFTENING as f64).powi(2))
+                .sqrt();
+                sample.potential_energy -= G as f64 * mass * other.mass as f64 / distance_softened;
+            }
+        }
+        sample.total_energy = sample.kinetic_energy + sample.potential_energy;
+        sample
+    }
+}
+
+/// Energy and momentum of the celestial bodies, tracked every fixed step
+///
+/// Spacecraft are left out: they don't pull on the bodies, so momentum
+/// including them isn't conserved, and their thrust would show up as drift.
+#[derive(Resource)]
+pub struct ConservationDiagnostics {
+    /// Values when tracking started, drift is measured against these
+    pub baseline: Option<ConservationSample>,
+    pub current: ConservationSample,
+    /// Recent samples, oldest first
+    pub history: VecDeque<ConservationSample>,
+    /// Bodies the baseline was taken for, a new set restarts tracking
+    bodies: Vec<Entity>,
+    log_timer: Timer,
+}
+
+impl Default for ConservationDiagnostics {
+    fn default() -> Self {
+        Self {
+            baseline: None,
+            current: ConservationSample::default(),
+            history: VecDeque::with_capacity(DIAGNOSTICS_HISTORY),
+            bodies: Vec::new(),
+            log_timer: Timer::from_seconds(DIAGNOSTICS_LOG_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+impl ConservationDiagnostics {
+    /// Total energy change relative to the initial total energy
+    pub fn energy_drift(&self, sample: &ConservationSample) -> f64 {
+        self.baseline.map_or(0., |baseline| {
+            (sample.total_energy - baseline.total_energy) / baseline.total_energy.abs()
+        })
+    }
+
+    /// Linear momentum change relative to the momentum scale
+    pub fn momentum_drift(&self, sample: &ConservationSample) -> f64 {
+        self.baseline.map_or(0., |baseline| {
+            (sample.linear_momentum - baseline.linear_momentum).length()
+                / baseline.momentum_scale.max(f64::EPSILON)
+        })
+    }
+
+    /// Angular momentum change relative to the initial angular momentum
+    pub fn angular_momentum_drift(&self, sample: &ConservationSample) -> f64 {
+        self.baseline.map_or(0., |baseline| {
+            (sample.angular_momentum - baseline.angular_momentum)
+                / baseline.angular_momentum.abs().max(f64::EPSILON)
+        })
+    }
+}
+
+/// Gravitating bodies, spacecraft don't pull on anything
+type BodyQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static PhysicsObject), (Without<User>, Without<Debris>)>;
+
+/// Measures the conserved quantities after the physics step
+pub fn diagnostics_system(
+    clock: Res<MissionClock>,
+    mut diagnostics: ResMut<ConservationDiagnostics>,
+    query: BodyQuery,
+) {
+    let (entities, bodies): (Vec<Entity>, Vec<&PhysicsObject>) = query.iter().unzip();
+    let sample = ConservationSample::measure(clock.elapsed, &bodies);
+
+    if entities != diagnostics.bodies {
+        diagnostics.bodies = entities;
+        diagnostics.baseline = (!bodies.is_empty()).then_some(sample);
+        diagnostics.history.clear();
+    }
+
+    if diagnostics.history.len() == DIAGNOSTICS_HISTORY {
+        diagnostics.history.pop_front();
+    }
+    diagnostics.history.push_back(sample);
+    diagnostics.current = sample;
+}
+
+/// Periodically logs the drift of the conserved quantities
+pub fn log_diagnostics_system(
+    time: Res<Time<Real>>,
+    mut diagnostics: ResMut<ConservationDiagnostics>,
+) {
+    if !diagnostics.log_timer.tick(time.delta()).just_finished() || diagnostics.baseline.is_none() {
+        return;
+    }
+
+    let current = diagnostics.current;
+    info!(
+        "Conservation at t={:.0}s: energy drift {:.3e}, momentum drift {:.3e}, angular momentum drift {:.3e}",
+        current.time,
+        diagnostics.energy_drift(&current),
+        diagnostics.momentum_drift(&current),
+        diagnostics.angular_momentum_drift(&current),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrator::Integrator;
+    use crate::systems::physics::gravity::{GravityBody, integrate};
+    use bevy::math::DVec2;
+    use std::f64::consts::{FRAC_PI_2, TAU};
+
+    const PLANET_MASS: f32 = 1.0e22;
+    const MOON_MASS: f32 = 1.0e20;
+    const DISTANCE: f64 = 1.0e7;
+
+    /// A planet and a moon on circular orbits around their barycenter, turned by `angle`
+    fn two_bodies(angle: f64) -> [PhysicsObject; 2] {
+        let total = (PLANET_MASS + MOON_MASS) as f64;
+        let speed = (G as f64 * total / DISTANCE).sqrt();
+        let rotation = DVec2::from_angle(angle);
+        let body = |mass: f32, share: f64| {
+            let position = rotation.rotate(DVec2::X * DISTANCE * share);
+            let velocity = rotation.rotate(DVec2::Y * speed * share);
+            PhysicsObject::new(mass, 1000., position.extend(0.), velocity.extend(0.), None)
+        };
+        [
+            body(PLANET_MASS, -MOON_MASS as f64 / total),
+            body(MOON_MASS, PLANET_MASS as f64 / total),
+        ]
+    }
+
+    fn measure(time: f64, bodies: &[PhysicsObject]) -> ConservationSample {
+        ConservationSample::measure(time, &bodies.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn circular_orbits_do_not_drift() {
+        let diagnostics = ConservationDiagnostics {
+            baseline: Some(measure(0., &two_bodies(0.))),
+            ..default()
+        };
+        assert!(diagnostics.baseline.unwrap().total_energy < 0.);
+
+        // The same orbits a quarter turn later hold the same energy and momentum
+        let later = measure(1000., &two_bodies(FRAC_PI_2));
+        assert!(diagnostics.energy_drift(&later).abs() < 1e-9);
+        assert!(diagnostics.momentum_drift(&later) < 1e-9);
+        assert!(diagnostics.angular_momentum_drift(&later).abs() < 1e-9);
+    }
+
+    #[test]
+    fn integrated_orbits_drift_little() {
+        let bodies = two_bodies(0.);
+        let diagnostics = ConservationDiagnostics {
+            baseline: Some(measure(0., &bodies)),
+            ..default()
+        };
+
+        let mut states: Vec<GravityBody> = bodies
+            .iter()
+            .map(|phys| GravityBody {
+                position: phys.position,
+                velocity: phys.vel,
+                mass: phys.mass,
+                attractor: true,
+                external_accel: DVec3::ZERO,
+            })
+            .collect();
+        let mu = G as f64 * (PLANET_MASS + MOON_MASS) as f64;
+        let period = TAU * (DISTANCE.powi(3) / mu).sqrt();
+        let steps = 1000;
+        for _ in 0..steps {
+            integrate(
+                &mut states,
+                Integrator::default(),
+                (period / steps as f64) as f32,
+            );
+        }
+
+        let after: Vec<PhysicsObject> = states
+            .iter()
+            .map(|state| {
+                PhysicsObject::new(state.mass, 1000., state.position, state.velocity, None)
+            })
+            .collect();
+        let sample = measure(period, &after);
+        assert!(diagnostics.energy_drift(&sample).abs() < 1e-4);
+        assert!(diagnostics.momentum_drift(&sample) < 1e-9);
+        assert!(diagnostics.angular_momentum_drift(&sample).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_kick_shows_up_as_drift() {
+        let diagnostics = ConservationDiagnostics {
+            baseline: Some(measure(0., &two_bodies(0.))),
+            ..default()
+        };
+
+        let mut kicked = two_bodies(0.);
+        kicked[1].vel *= 1.01;
+        let sample = measure(0., &kicked);
+        assert!(diagnostics.energy_drift(&sample) > 1e-3);
+        assert!(diagnostics.momentum_drift(&sample) > 1e-3);
+        assert!(diagnostics.angular_momentum_drift(&sample) > 1e-3);
+    }
+}
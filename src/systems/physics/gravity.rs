@@ -1,90 +1,181 @@
-use crate::components::markers::User;
+use crate::components::markers::{Debris, User};
 use crate::components::physics_object::PhysicsObject;
 use crate::config::Config;
-use crate::constants::{G, SOFTENING};
+use crate::constants::{G, ON_RAILS_WARP_STAGE, SOFTENING};
+use crate::integrator::Integrator;
+use crate::orbital::propagate_kepler;
+use crate::systems::physics::{MissionClock, SoiHierarchy};
+use bevy::math::DVec3;
 use bevy::prelude::*;
 use itertools::izip;
 
+/// Gravitational state of one object, detached from the ECS so it can be
+/// stepped both by the simulation and by trajectory prediction
+#[derive(Clone, Copy, Debug)]
+pub struct GravityBody {
+    pub position: DVec3,
+    pub velocity: DVec3,
+    pub mass: f32,
+    /// Whether the object pulls on others, spacecraft don't
+    pub attractor: bool,
+    /// Acceleration from applied forces such as thrust, held constant over a step
+    pub external_accel: DVec3,
+}
+
+/// Integrates gravity and applied forces with the configured integrator
+///
+/// From `ON_RAILS_WARP_STAGE` on, objects coasting around a heavier body
+/// without touching it instead follow their Kepler orbit around their central
+/// body, which stays exact at any step length. A step that would carry an
+/// object out of its sphere of influence is integrated numerically, so it
+/// crosses the boundary under the pull of both bodies.
 pub fn gravity_system(
     config: Res<Config>,
-    mut query: Query<(Entity, &mut Transform, &mut PhysicsObject, Has<User>)>,
+    clock: Res<MissionClock>,
+    hierarchy: Res<SoiHierarchy>,
+    mut query: Query<(Entity, &mut PhysicsObject, Has<User>, Has<Debris>)>,
 ) {
-    let accel_1: Vec<_> = query
+    let entities: Vec<Entity> = query.iter().map(|(entity, ..)| entity).collect();
+    let mut bodies: Vec<GravityBody> = query
         .iter()
-        .map(|(entity, _, _, _)| calculate_gravity_accel_with_central_body(entity, &query))
+        .map(|(_, phys, is_user, is_debris)| GravityBody {
+            position: phys.position,
+            velocity: phys.vel,
+            mass: phys.mass,
+            attractor: !(is_user || is_debris),
+            external_accel: (phys.applied_force / phys.mass).as_dvec3(),
+        })
         .collect();
-
-    for ((accel, central_body), (_, mut transform, mut phys, _)) in izip!(&accel_1, &mut query) {
-        let new_position =
-            transform.translation + phys.vel * config.dt + 0.5 * accel * config.dt * config.dt;
-        transform.translation = new_position;
-
-        phys.central_body = *central_body;
-    }
-
-    let accel_2: Vec<_> = query
+    let radii: Vec<f32> = query.iter().map(|(_, phys, ..)| phys.radius).collect();
+    let index_of = |entity: Entity| entities.iter().position(|other| *other == entity);
+    let central_bodies: Vec<Option<usize>> = query
         .iter()
-        .map(|(entity, _, _, _)| calculate_gravity_accel(entity, &query))
+        .map(|(_, phys, ..)| phys.central_body.and_then(index_of))
         .collect();
 
-    for (a1, a2, (_, _, mut phys, _)) in izip!(accel_1, accel_2, &mut query) {
-        let new_velocity = phys.vel + 0.5 * (a1.0 + a2) * config.dt;
-        phys.vel = new_velocity;
-    }
-}
-
-pub fn calculate_gravity_accel(
-    target_entity: Entity,
-    query: &Query<(Entity, &mut Transform, &mut PhysicsObject, Has<User>)>,
-) -> Vec3 {
-    let mut net_accel = Vec3::ZERO;
-    let target_pos = query.get(target_entity).unwrap().1.translation;
-
-    for (entity, transform, physics_object, is_user) in query {
-        if entity == target_entity || is_user {
+    let start = bodies.clone();
+    integrate(&mut bodies, config.integrator, clock.dt);
+
+    let mut on_rails: Vec<bool> = if config.warp_stage >= ON_RAILS_WARP_STAGE {
+        start
+            .iter()
+            .zip(&central_bodies)
+            .map(|(body, central)| {
+                central.is_some_and(|central| coasting(body, &start[central], radii[central]))
+            })
+            .collect()
+    } else {
+        vec![false; bodies.len()]
+    };
+
+    // Central bodies on rails have to be moved before whatever orbits them
+    let depth = |mut index: usize| {
+        let mut depth = 0;
+        while on_rails[index] {
+            index = central_bodies[index].unwrap_or(index);
+            depth += 1;
+        }
+        depth
+    };
+    let mut order: Vec<usize> = (0..bodies.len()).filter(|index| on_rails[*index]).collect();
+    order.sort_by_key(|index| depth(*index));
+    for index in order {
+        let Some(central) = central_bodies[index] else {
+            continue;
+        };
+        let mu = G as f64 * (start[central].mass + start[index].mass) as f64;
+        // Falls back to the integrated state when Kepler's equation won't solve
+        let Some((position, velocity)) = propagate_kepler(
+            start[index].position - start[central].position,
+            start[index].velocity - start[central].velocity,
+            mu,
+            clock.dt as f64,
+        ) else {
+            on_rails[index] = false;
+            continue;
+        };
+        let position = bodies[central].position + position;
+
+        // Bodies keep their parent, anything else may leave for another sphere
+        let crossing = hierarchy.node(entities[index]).is_none()
+            && hierarchy.body_at(position, Some(entities[central]), |entity| {
+                index_of(entity).map(|index| bodies[index].position)
+            }) != Some(entities[central]);
+        if crossing {
+            on_rails[index] = false;
             continue;
         }
-
-        let distance_vec = transform.translation - target_pos;
-        let distance_sq_softened = distance_vec.length_squared() + SOFTENING.powi(2);
-        let inv_r_cubed = distance_sq_softened.powf(-1.5);
-
-        let accel = G * physics_object.mass * inv_r_cubed * distance_vec;
-
-        net_accel += accel;
+        bodies[index].position = position;
+        bodies[index].velocity = bodies[central].velocity + velocity;
     }
 
-    net_accel
+    for (body, rails, (_, mut phys, ..)) in izip!(&bodies, on_rails, &mut query) {
+        phys.position = body.position;
+        phys.vel = body.velocity;
+        phys.applied_force = Vec3::ZERO;
+        phys.on_rails = rails;
+    }
 }
 
-pub fn calculate_gravity_accel_with_central_body(
-    target_entity: Entity,
-    query: &Query<(Entity, &mut Transform, &mut PhysicsObject, Has<User>)>,
-) -> (Vec3, Option<Entity>) {
-    let mut net_accel = Vec3::ZERO;
-    let target_pos = query.get(target_entity).unwrap().1.translation;
-
-    let mut max_accel = 0.0;
-    let mut central_body = None;
-
-    for (entity, transform, physics_object, is_user) in query {
-        if entity == target_entity || is_user {
-            continue;
-        }
-
-        let distance_vec = transform.translation - target_pos;
-        let distance_sq_softened = distance_vec.length_squared() + SOFTENING.powi(2);
-        let inv_r_cubed = distance_sq_softened.powf(-1.5);
-
-        let accel = G * physics_object.mass * inv_r_cubed * distance_vec;
-
-        if accel.length_squared() > max_accel {
-            max_accel = accel.length_squared();
-            central_body = Some(entity);
-        }
+/// Whether a body follows a Kepler orbit around `central` without forces
+/// other than its gravity or the orbit reaching the surface
+fn coasting(body: &GravityBody, central: &GravityBody, central_radius: f32) -> bool {
+    if body.external_accel != DVec3::ZERO || body.mass >= central.mass {
+        return false;
+    }
+    let mu = G as f64 * (central.mass + body.mass) as f64;
+    let position = body.position - central.position;
+    let velocity = body.velocity - central.velocity;
+    let angular_momentum = position.cross(velocity).length();
+    let eccentricity = ((velocity.length_squared() - mu / position.length()) * position
+        - position.dot(velocity) * velocity)
+        .length()
+        / mu;
+    let periapsis = angular_momentum * angular_momentum / mu / (1. + eccentricity);
+    periapsis > central_radius as f64
+}
 
-        net_accel += accel;
+/// Advances all bodies by `dt` under gravity and their external acceleration
+pub fn integrate(bodies: &mut [GravityBody], integrator: Integrator, dt: f32) {
+    let mut positions: Vec<DVec3> = bodies.iter().map(|body| body.position).collect();
+    let mut velocities: Vec<DVec3> = bodies.iter().map(|body| body.velocity).collect();
+
+    integrator.step(&mut positions, &mut velocities, dt as f64, |positions| {
+        accelerations_at(positions, bodies)
+            .into_iter()
+            .zip(bodies.iter())
+            .map(|(accel, body)| accel + body.external_accel)
+            .collect()
+    });
+
+    for (body, position, velocity) in izip!(bodies.iter_mut(), positions, velocities) {
+        body.position = position;
+        body.velocity = velocity;
     }
+}
 
-    (net_accel, central_body)
+/// Net gravitational acceleration on each body with the bodies moved to `positions`
+fn accelerations_at(positions: &[DVec3], bodies: &[GravityBody]) -> Vec<DVec3> {
+    positions
+        .iter()
+        .enumerate()
+        .map(|(target, target_position)| {
+            let mut net_accel = DVec3::ZERO;
+
+            for (index, (position, body)) in positions.iter().zip(bodies).enumerate() {
+                if index == target || !body.attractor {
+                    continue;
+                }
+
+                let distance_vec = *position - *target_position;
+                let distance_sq_softened =
+                    distance_vec.length_squared() + (SOFTENING as f64).powi(2);
+                let inv_r_cubed = distance_sq_softened.powf(-1.5);
+
+                net_accel += G as f64 * body.mass as f64 * inv_r_cubed * distance_vec;
+            }
+
+            net_accel
+        })
+        .collect()
 }
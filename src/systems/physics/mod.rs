@@ -1,9 +1,23 @@
-mod apply_force;
+mod atmosphere;
+mod attitude;
+mod autopilot;
+mod clock;
 mod collision;
+mod diagnostics;
 pub mod gravity;
+mod origin;
 mod propulsion;
+mod soi;
 
-pub use apply_force::apply_force_system;
-pub use collision::collision_system;
+pub use atmosphere::atmosphere_system;
+pub use attitude::attitude_system;
+pub use autopilot::{autopilot_system, hold_direction};
+pub use clock::{MissionClock, PhysicsStep, physics_step_system};
+pub use collision::{ContactEvent, collision_system};
+pub use diagnostics::{
+    ConservationDiagnostics, ConservationSample, diagnostics_system, log_diagnostics_system,
+};
 pub use gravity::gravity_system;
+pub use origin::{FloatingOrigin, floating_origin_system};
 pub use propulsion::propulsion_system;
+pub use soi::{SoiChangeEvent, SoiHierarchy, SoiNode, soi_system};
@@ -0,0 +1,28 @@
+use crate::components::markers::User;
+use crate::components::physics_object::PhysicsObject;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+/// Point in simulation space that sits at the world origin when rendering
+///
+/// Follows the player's vessel, so everything near the camera is drawn with
+/// full `f32` precision however far the vessel is from the system's origin.
+#[derive(Resource, Default)]
+pub struct FloatingOrigin(pub DVec3);
+
+/// Recenters the origin on the vessel and derives every `Transform` from the
+/// simulation state
+pub fn floating_origin_system(
+    mut origin: ResMut<FloatingOrigin>,
+    user: Query<&PhysicsObject, With<User>>,
+    mut query: Query<(&mut Transform, &PhysicsObject)>,
+) {
+    if let Some(user) = user.iter().next() {
+        origin.0 = user.position;
+    }
+
+    for (mut transform, phys) in &mut query {
+        let relative = (phys.position - origin.0).as_vec3();
+        transform.translation = relative.truncate().extend(transform.translation.z);
+    }
+}
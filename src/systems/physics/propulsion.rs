@@ -1,10 +1,17 @@
+use crate::components::damage::Destroyed;
 use crate::components::physics_object::PhysicsObject;
 use crate::components::propulsion::Propulsion;
+use crate::systems::physics::MissionClock;
 use bevy::prelude::*;
 
+/// Burns fuel for the set thrust level and lightens the vessel accordingly
+///
+/// The thrust force is chosen so that integrating it against the vessel's new
+/// mass gives exactly the rocket equation's velocity change for the fuel burnt,
+/// keeping the delta-v readout and the flown delta-v in agreement.
 pub fn propulsion_system(
-    time: Res<Time>,
-    mut query: Query<(&Transform, &mut Propulsion, &mut PhysicsObject)>,
+    clock: Res<MissionClock>,
+    mut query: Query<(&Transform, &mut Propulsion, &mut PhysicsObject), Without<Destroyed>>,
 ) {
     for (transform, mut propulsion, mut phys) in &mut query {
         // Only process if there's thrust input
@@ -18,27 +25,20 @@ pub fn propulsion_system(
             continue;
         }
 
-        // Calculate actual thrust based on fuel availability
-        let mut actual_thrust_percentage = propulsion.thrust_percentage;
-
-        // Calculate fuel consumption for this frame
+        // Consume fuel only when actually thrusting, and no more than is left
         let fuel_needed =
-            propulsion.fuel_consumption_rate * actual_thrust_percentage.abs() * time.delta_secs();
-
-        // Limit thrust if we don't have enough fuel
-        if fuel_needed > propulsion.fuel {
-            actual_thrust_percentage =
-                propulsion.fuel / (propulsion.fuel_consumption_rate * time.delta_secs());
-        }
+            propulsion.fuel_consumption_rate() * propulsion.thrust_percentage.abs() * clock.dt;
+        let fuel_consumed = fuel_needed.min(propulsion.fuel);
 
-        // Consume fuel only when actually thrusting
-        let fuel_consumed =
-            propulsion.fuel_consumption_rate * actual_thrust_percentage.abs() * time.delta_secs();
-        propulsion.fuel = (propulsion.fuel - fuel_consumed).max(0.0);
+        propulsion.fuel -= fuel_consumed;
+        phys.mass = propulsion.mass();
 
-        // Apply thrust force in the direction of the object's forward vector
-        let thrust_force =
-            propulsion.max_thrust * actual_thrust_percentage * transform.rotation.mul_vec3(Vec3::Y);
-        phys.applied_force += thrust_force;
+        // Apply thrust in the direction of the object's forward vector, the mass
+        // ratio is written as ln(1 + x) as it is within a few ulps of one
+        let delta_v = propulsion.exhaust_velocity() * (fuel_consumed / phys.mass).ln_1p();
+        let direction =
+            transform.rotation.mul_vec3(Vec3::Y) * propulsion.thrust_percentage.signum();
+        let mass = phys.mass;
+        phys.applied_force += direction * delta_v / clock.dt * mass;
     }
 }
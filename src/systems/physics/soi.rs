@@ -0,0 +1,280 @@
+use crate::components::markers::{Debris, User};
+use crate::components::physics_object::PhysicsObject;
+use crate::constants::{G, SOI_HYSTERESIS};
+use crate::systems::physics::gravity::GravityBody;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+/// Sphere of influence of one attracting body
+#[derive(Clone, Copy, Debug)]
+pub struct SoiNode {
+    pub entity: Entity,
+    /// Body whose sphere this one lies in, `None` for the root
+    pub parent: Option<Entity>,
+    /// Laplace radius in meters, infinite for the root
+    pub radius: f64,
+}
+
+/// Which body's sphere of influence lies in which, heaviest body first
+///
+/// The one authority on central bodies: an object orbits the body of the
+/// deepest sphere containing it. A sphere is entered at its Laplace radius but
+/// only left `SOI_HYSTERESIS` beyond it, so objects skimming the boundary don't
+/// flip back and forth.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SoiHierarchy {
+    nodes: Vec<SoiNode>,
+}
+
+impl SoiHierarchy {
+    /// Arranges the attracting `bodies` into spheres of influence
+    ///
+    /// Each body lies in the sphere of a heavier one, `previous` provides the
+    /// hysteresis for bodies near a boundary.
+    pub fn build(bodies: &[(Entity, GravityBody)], previous: &SoiHierarchy) -> Self {
+        let mut attractors: Vec<&(Entity, GravityBody)> =
+            bodies.iter().filter(|(_, body)| body.attractor).collect();
+        attractors.sort_by(|(_, a), (_, b)| b.mass.total_cmp(&a.mass));
+        let state_of = |entity: Entity| {
+            bodies
+                .iter()
+                .find(|(other, _)| *other == entity)
+                .map(|(_, body)| body)
+        };
+
+        let mut hierarchy = SoiHierarchy::default();
+        for (entity, body) in attractors {
+            let parent = hierarchy.body_at(body.position, previous.parent(*entity), |entity| {
+                state_of(entity).map(|body| body.position)
+            });
+            let radius = parent
+                .and_then(state_of)
+                .map_or(f64::INFINITY, |parent| laplace_radius(body, parent));
+            hierarchy.nodes.push(SoiNode {
+                entity: *entity,
+                parent,
+                radius,
+            });
+        }
+        hierarchy
+    }
+
+    /// Body of the deepest sphere containing `position`
+    ///
+    /// `current` is the body the object was in so far, its spheres get the
+    /// hysteresis margin. `position_of` looks up where the bodies are.
+    pub fn body_at(
+        &self,
+        position: DVec3,
+        current: Option<Entity>,
+        position_of: impl Fn(Entity) -> Option<DVec3>,
+    ) -> Option<Entity> {
+        let mut body = self.nodes.first()?.entity;
+        'descend: loop {
+            for child in self.nodes.iter().filter(|node| node.parent == Some(body)) {
+                let Some(center) = position_of(child.entity) else {
+                    continue;
+                };
+                let margin = if self.is_within(current, child.entity) {
+                    1. + SOI_HYSTERESIS
+                } else {
+                    1.
+                };
+                if position.distance(center) < child.radius * margin {
+                    body = child.entity;
+                    continue 'descend;
+                }
+            }
+            return Some(body);
+        }
+    }
+
+    pub fn node(&self, entity: Entity) -> Option<&SoiNode> {
+        self.nodes.iter().find(|node| node.entity == entity)
+    }
+
+    /// Body whose sphere the body `entity` lies in
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.node(entity).and_then(|node| node.parent)
+    }
+
+    /// Laplace radius of the body `entity`
+    pub fn radius(&self, entity: Entity) -> Option<f64> {
+        self.node(entity).map(|node| node.radius)
+    }
+
+    /// Whether the body `entity` is `ancestor` or lies somewhere inside its sphere
+    fn is_within(&self, mut entity: Option<Entity>, ancestor: Entity) -> bool {
+        while let Some(body) = entity {
+            if body == ancestor {
+                return true;
+            }
+            entity = self.parent(body);
+        }
+        false
+    }
+}
+
+/// Laplace radius `a * (m / M)^(2/5)` of `body` orbiting `parent`
+///
+/// Bodies on open orbits use their current distance for the semi-major axis.
+fn laplace_radius(body: &GravityBody, parent: &GravityBody) -> f64 {
+    let mu = G as f64 * (parent.mass + body.mass) as f64;
+    let distance = body.position.distance(parent.position);
+    let energy = body.velocity.distance_squared(parent.velocity) / 2. - mu / distance;
+    let semi_major_axis = if energy < 0. {
+        -mu / (2. * energy)
+    } else {
+        distance
+    };
+    semi_major_axis * (body.mass as f64 / parent.mass as f64).powf(0.4)
+}
+
+/// Sent when an object moves into another body's sphere of influence
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SoiChangeEvent {
+    pub entity: Entity,
+    pub from: Option<Entity>,
+    pub to: Option<Entity>,
+}
+
+/// Rebuilds the [`SoiHierarchy`] and sets every object's central body from it
+///
+/// Bodies orbit the body of their parent sphere, everything else the body of
+/// the deepest sphere containing it. Runs after gravity, so the next step sees
+/// where this one ended.
+pub fn soi_system(
+    mut hierarchy: ResMut<SoiHierarchy>,
+    mut query: Query<(Entity, &mut PhysicsObject, Has<User>, Has<Debris>)>,
+    names: Query<&Name>,
+    mut changes: EventWriter<SoiChangeEvent>,
+) {
+    let bodies: Vec<(Entity, GravityBody)> = query
+        .iter()
+        .map(|(entity, phys, is_user, is_debris)| {
+            let body = GravityBody {
+                position: phys.position,
+                velocity: phys.vel,
+                mass: phys.mass,
+                attractor: !(is_user || is_debris),
+                external_accel: DVec3::ZERO,
+            };
+            (entity, body)
+        })
+        .collect();
+    *hierarchy = SoiHierarchy::build(&bodies, &hierarchy);
+    let position_of = |entity: Entity| {
+        bodies
+            .iter()
+            .find(|(other, _)| *other == entity)
+            .map(|(_, body)| body.position)
+    };
+
+    for (entity, mut phys, is_user, _) in &mut query {
+        let central = match hierarchy.node(entity) {
+            Some(node) => node.parent,
+            None => hierarchy.body_at(phys.position, phys.central_body, position_of),
+        };
+        if central == phys.central_body {
+            continue;
+        }
+
+        if is_user && let Some(name) = central.and_then(|central| names.get(central).ok()) {
+            info!("Entered the sphere of influence of {}", name);
+        }
+        changes.write(SoiChangeEvent {
+            entity,
+            from: phys.central_body,
+            to: central,
+        });
+        phys.central_body = central;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EARTH: Entity = Entity::from_raw(1);
+    const LUNA: Entity = Entity::from_raw(2);
+    const EARTH_MASS: f32 = 5.972e24;
+    const LUNA_MASS: f32 = 7.342e22;
+    const LUNA_DISTANCE: f64 = 3.844e8;
+
+    fn body(position: DVec3, velocity: DVec3, mass: f32) -> GravityBody {
+        GravityBody {
+            position,
+            velocity,
+            mass,
+            attractor: true,
+            external_accel: DVec3::ZERO,
+        }
+    }
+
+    /// Earth at rest with Luna on a circular orbit along +X
+    fn earth_and_luna() -> Vec<(Entity, GravityBody)> {
+        let mu = G as f64 * (EARTH_MASS + LUNA_MASS) as f64;
+        let speed = (mu / LUNA_DISTANCE).sqrt();
+        vec![
+            (
+                LUNA,
+                body(DVec3::X * LUNA_DISTANCE, DVec3::Y * speed, LUNA_MASS),
+            ),
+            (EARTH, body(DVec3::ZERO, DVec3::ZERO, EARTH_MASS)),
+        ]
+    }
+
+    fn position_of(entity: Entity) -> Option<DVec3> {
+        earth_and_luna()
+            .into_iter()
+            .find(|(other, _)| *other == entity)
+            .map(|(_, body)| body.position)
+    }
+
+    #[test]
+    fn laplace_radius_of_luna() {
+        let bodies = earth_and_luna();
+        let radius = laplace_radius(&bodies[0].1, &bodies[1].1);
+        // About 66,100 km
+        assert!((radius - 6.61e7).abs() < 0.01 * 6.61e7, "radius {radius}");
+    }
+
+    #[test]
+    fn heaviest_body_is_the_root() {
+        let hierarchy = SoiHierarchy::build(&earth_and_luna(), &SoiHierarchy::default());
+
+        assert_eq!(hierarchy.parent(EARTH), None);
+        assert_eq!(hierarchy.radius(EARTH), Some(f64::INFINITY));
+        assert_eq!(hierarchy.parent(LUNA), Some(EARTH));
+        assert!(hierarchy.radius(LUNA).unwrap() < LUNA_DISTANCE);
+    }
+
+    #[test]
+    fn hysteresis_on_leaving() {
+        let hierarchy = SoiHierarchy::build(&earth_and_luna(), &SoiHierarchy::default());
+        let radius = hierarchy.radius(LUNA).unwrap();
+        let beyond_luna = |fraction: f64| DVec3::X * (LUNA_DISTANCE + radius * fraction);
+
+        // Entered at the Laplace radius itself
+        assert_eq!(
+            hierarchy.body_at(beyond_luna(0.99), Some(EARTH), position_of),
+            Some(LUNA)
+        );
+        assert_eq!(
+            hierarchy.body_at(beyond_luna(1.01), Some(EARTH), position_of),
+            Some(EARTH)
+        );
+
+        // Left only beyond the margin
+        let within_margin = beyond_luna(1. + SOI_HYSTERESIS / 2.);
+        assert_eq!(
+            hierarchy.body_at(within_margin, Some(LUNA), position_of),
+            Some(LUNA)
+        );
+        let past_margin = beyond_luna(1. + SOI_HYSTERESIS * 2.);
+        assert_eq!(
+            hierarchy.body_at(past_margin, Some(LUNA), position_of),
+            Some(EARTH)
+        );
+    }
+}
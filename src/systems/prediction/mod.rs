@@ -0,0 +1,732 @@
+mod n_body;
+
+pub use n_body::{PredictedBody, propagate_n_body};
+
+use crate::components::celestial_body::CelestialBody;
+use crate::components::maneuver::{ManeuverNode, ManeuverPlan};
+use crate::components::markers::{Debris, User};
+use crate::components::physics_object::PhysicsObject;
+use crate::components::propulsion::Propulsion;
+use crate::components::trajectory_prediction::{
+    PathEvent, PathEventKind, PathPoint, PathSample, TrajectoryPrediction,
+};
+use crate::config::{Config, PredictionMode};
+use crate::constants::{
+    G, MAX_DT, MIN_DT, MOON_ORBIT_RADIUS, PREDICTION_DRIFT_TOLERANCE, PREDICTION_POINTS, SOFTENING,
+};
+use crate::integrator::Integrator;
+use crate::orbital::maneuver_frame;
+use crate::systems::physics::gravity::GravityBody;
+use crate::systems::physics::{MissionClock, SoiHierarchy};
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on};
+use itertools::Itertools;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+/// Integration steps between yields, so single-threaded targets can interleave other work
+const YIELD_INTERVAL: usize = 256;
+
+type PredictionQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static PhysicsObject,
+        &'static mut TrajectoryPrediction,
+        Option<&'static Propulsion>,
+        Option<&'static ManeuverPlan>,
+        Has<PendingPrediction>,
+    ),
+>;
+
+type BodyQuery<'w, 's> = Query<'w, 's, (Entity, &'static PhysicsObject, Has<User>, Has<Debris>)>;
+
+/// Path produced by either prediction mode
+#[derive(Default)]
+pub struct PredictedPath {
+    /// States relative to `central_body`
+    pub samples: Vec<PathSample>,
+    /// States relative to the body each sample is drawn around
+    pub local_samples: Vec<PathSample>,
+    /// Drawn position of each sample
+    pub path: Vec<PathPoint>,
+    /// Body that was central when the prediction started
+    pub central_body: Option<Entity>,
+    pub events: Vec<PathEvent>,
+}
+
+impl PredictedPath {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            samples: Vec::with_capacity(capacity),
+            local_samples: Vec::with_capacity(capacity),
+            path: Vec::with_capacity(capacity),
+            central_body: None,
+            events: Vec::new(),
+        }
+    }
+}
+
+/// A prediction running on the async compute task pool
+#[derive(Component)]
+pub struct PendingPrediction {
+    task: Task<PredictionOutput>,
+    /// Path computed so far, shown before the task finishes
+    progress: PathProgress,
+    /// Mission time the prediction starts at
+    epoch: f64,
+    /// Body the samples are relative to
+    central_body: Option<Entity>,
+    /// Whether partial results have replaced the previously cached path
+    streaming: bool,
+}
+
+/// Path a running prediction has computed but not yet handed over
+#[derive(Default)]
+struct PathChunk {
+    path: Vec<PathPoint>,
+    samples: Vec<PathSample>,
+}
+
+/// Passes a prediction's path to the main thread while it is still being computed
+#[derive(Clone, Default)]
+pub struct PathProgress(Arc<Mutex<PathChunk>>);
+
+impl PathProgress {
+    /// Adds newly predicted samples and their drawn points
+    pub fn publish(&self, samples: &[PathSample], path: &[PathPoint]) {
+        if let Ok(mut chunk) = self.0.lock() {
+            chunk.samples.extend_from_slice(samples);
+            chunk.path.extend_from_slice(path);
+        }
+    }
+
+    /// Everything published since the last call
+    fn take(&self) -> PathChunk {
+        self.0
+            .lock()
+            .map(|mut chunk| std::mem::take(&mut *chunk))
+            .unwrap_or_default()
+    }
+}
+
+/// Everything a prediction needs, copied out of the world
+struct PredictionInput {
+    mode: PredictionMode,
+    integrator: Integrator,
+    bodies: Vec<PredictedBody>,
+    target: usize,
+    /// Central body of the target
+    central: Option<usize>,
+    /// Spheres of influence at the time of the snapshot
+    soi: SoiHierarchy,
+    /// Mission time of the snapshot
+    epoch: f64,
+    nodes: Vec<ManeuverNode>,
+    /// Receives the path without burns as it grows
+    progress: PathProgress,
+}
+
+pub struct PredictionOutput {
+    epoch: f64,
+    path: PredictedPath,
+    /// Path with the planned burns, and the nodes it was predicted for
+    planned: Option<(Vec<ManeuverNode>, PredictedPath)>,
+}
+
+/// Starts background predictions for objects whose cached path no longer
+/// matches their state
+///
+/// A path is recomputed when the object thrusts, changes central body, strays
+/// from the path or outlives it, or when its maneuver nodes change. Changing
+/// the warp stage or prediction mode also cancels running predictions.
+pub fn request_predictions_system(
+    mut commands: Commands,
+    config: Res<Config>,
+    clock: Res<MissionClock>,
+    hierarchy: Res<SoiHierarchy>,
+    mut query: PredictionQuery,
+    all_bodies: BodyQuery,
+) {
+    let mut system = None;
+
+    for (entity, phys, mut prediction, propulsion, plan, pending) in &mut query {
+        if phys.vel.length_squared() < 2. {
+            if !prediction.samples.is_empty() {
+                prediction.clear();
+            }
+            if pending {
+                commands.entity(entity).remove::<PendingPrediction>();
+            }
+            continue;
+        }
+
+        // Let running predictions finish unless the settings changed under them
+        if pending && !config.is_changed() {
+            continue;
+        }
+
+        let stale = config.is_changed()
+            || prediction.samples.is_empty()
+            || phys.central_body != prediction.central_body
+            || propulsion.is_some_and(|p| p.thrust_percentage > 0. && p.fuel > 0.)
+            || plan.is_some_and(|plan| plan.nodes != plan.predicted_nodes)
+            || has_drifted(&prediction, phys, clock.elapsed, &all_bodies);
+        if !stale {
+            continue;
+        }
+
+        let system = system.get_or_insert_with(|| predicted_bodies(&all_bodies));
+        let Some(target) = system.iter().position(|body| body.entity == entity) else {
+            continue;
+        };
+
+        let progress = PathProgress::default();
+        let input = PredictionInput {
+            mode: config.prediction_mode,
+            integrator: config.integrator,
+            bodies: system.clone(),
+            target,
+            central: phys
+                .central_body
+                .and_then(|central| system.iter().position(|body| body.entity == central)),
+            soi: hierarchy.clone(),
+            epoch: clock.elapsed,
+            nodes: plan.map(|plan| plan.nodes.clone()).unwrap_or_default(),
+            progress: progress.clone(),
+        };
+
+        let task = AsyncComputeTaskPool::get().spawn(predict(input));
+        commands.entity(entity).insert(PendingPrediction {
+            task,
+            progress,
+            epoch: clock.elapsed,
+            central_body: phys.central_body,
+            streaming: false,
+        });
+    }
+}
+
+/// Shows the path of running predictions as it grows and stores finished ones
+///
+/// The first partial path replaces the cached one, events and the planned path
+/// only arrive with the finished prediction.
+pub fn collect_predictions_system(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut PendingPrediction,
+        &mut TrajectoryPrediction,
+        Option<&mut ManeuverPlan>,
+    )>,
+) {
+    for (entity, mut pending, mut prediction, plan) in &mut query {
+        let chunk = pending.progress.take();
+        if !chunk.samples.is_empty() {
+            if !pending.streaming {
+                prediction.clear();
+                prediction.epoch = pending.epoch;
+                prediction.central_body = pending.central_body;
+                pending.streaming = true;
+            }
+            prediction.samples.extend(chunk.samples);
+            prediction.path.extend(chunk.path);
+        }
+
+        let Some(output) = block_on(future::poll_once(&mut pending.task)) else {
+            continue;
+        };
+        commands.entity(entity).remove::<PendingPrediction>();
+
+        prediction.epoch = output.epoch;
+        prediction.path = output.path.path;
+        prediction.samples = output.path.samples;
+        prediction.central_body = output.path.central_body;
+        prediction.events = output.path.events;
+
+        if let Some(mut plan) = plan {
+            store_planned_path(&mut plan, output.planned, output.epoch);
+        }
+    }
+}
+
+/// Resolves cached paths against the current body positions for drawing and picking
+pub fn update_prediction_points_system(
+    bodies: Query<&Transform, With<PhysicsObject>>,
+    mut predictions: Query<&mut TrajectoryPrediction>,
+    mut plans: Query<&mut ManeuverPlan>,
+) {
+    let position_of = |entity| bodies.get(entity).ok().map(|t| t.translation);
+
+    for mut prediction in &mut predictions {
+        let prediction = &mut *prediction;
+        prediction.points.clear();
+        prediction.points.extend(
+            prediction
+                .path
+                .iter()
+                .map(|point| point.resolve(position_of)),
+        );
+    }
+
+    for mut plan in &mut plans {
+        let plan = &mut *plan;
+        plan.planned_points.clear();
+        plan.planned_points.extend(
+            plan.planned_path
+                .iter()
+                .map(|point| point.resolve(position_of)),
+        );
+        plan.node_positions = plan
+            .node_points
+            .iter()
+            .map(|point| point.map(|point| point.resolve(position_of)))
+            .collect();
+    }
+}
+
+/// Whether the object has left the part of space its prediction covers
+fn has_drifted(
+    prediction: &TrajectoryPrediction,
+    phys: &PhysicsObject,
+    now: f64,
+    bodies: &BodyQuery,
+) -> bool {
+    let Some(predicted) = prediction.sample_at((now - prediction.epoch) as f32) else {
+        return true;
+    };
+    let Some((_, central_phys, ..)) = prediction
+        .central_body
+        .and_then(|central| bodies.get(central).ok())
+    else {
+        return true;
+    };
+
+    let actual = (phys.position - central_phys.position).as_vec3();
+    actual.distance(predicted.position) > PREDICTION_DRIFT_TOLERANCE * predicted.position.length()
+}
+
+fn store_planned_path(
+    plan: &mut ManeuverPlan,
+    planned: Option<(Vec<ManeuverNode>, PredictedPath)>,
+    epoch: f64,
+) {
+    let Some((nodes, path)) = planned else {
+        plan.predicted_nodes.clear();
+        plan.planned_path.clear();
+        plan.node_points.clear();
+        plan.node_frames.clear();
+        return;
+    };
+
+    let node_samples: Vec<Option<usize>> = nodes
+        .iter()
+        .map(|node| {
+            let time = (node.time - epoch) as f32;
+            path.local_samples.iter().position(|s| s.time >= time)
+        })
+        .collect();
+
+    plan.node_points = node_samples
+        .iter()
+        .map(|index| index.map(|index| path.path[index]))
+        .collect();
+    plan.node_frames = node_samples
+        .iter()
+        .map(|index| {
+            index.map_or((Vec3::ZERO, Vec3::ZERO), |index| {
+                let s = path.local_samples[index];
+                maneuver_frame(s.position, s.velocity)
+            })
+        })
+        .collect();
+
+    // Only draw the path from the first burn on, the current prediction covers the rest
+    let first = node_samples
+        .first()
+        .copied()
+        .flatten()
+        .unwrap_or(path.path.len());
+    plan.planned_path = path.path[first..].to_vec();
+    plan.predicted_nodes = nodes;
+}
+
+async fn predict(input: PredictionInput) -> PredictionOutput {
+    let path = predict_path(&input, &[], Some(&input.progress)).await;
+
+    let planned = if input.nodes.is_empty() {
+        None
+    } else {
+        let burns: Vec<(f32, Vec2)> = input
+            .nodes
+            .iter()
+            .map(|node| ((node.time - input.epoch) as f32, node.delta_v()))
+            .collect();
+        Some((
+            input.nodes.clone(),
+            predict_path(&input, &burns, None).await,
+        ))
+    };
+
+    PredictionOutput {
+        epoch: input.epoch,
+        path,
+        planned,
+    }
+}
+
+async fn predict_path(
+    input: &PredictionInput,
+    burns: &[(f32, Vec2)],
+    progress: Option<&PathProgress>,
+) -> PredictedPath {
+    match (input.mode, input.central) {
+        (PredictionMode::NBody, _) => {
+            propagate_n_body(
+                &input.bodies,
+                input.target,
+                input.central,
+                &input.soi,
+                burns,
+                input.integrator,
+                progress,
+            )
+            .await
+        }
+        (PredictionMode::TwoBody, Some(central)) => {
+            let central = &input.bodies[central];
+            let parent = input.soi.node(central.entity).and_then(|node| {
+                Some(SoiExit {
+                    parent: node.parent?,
+                    radius: node.radius as f32,
+                })
+            });
+            propagate_two_body(
+                &input.bodies[input.target],
+                central,
+                parent,
+                burns,
+                input.integrator,
+                progress,
+            )
+            .await
+        }
+        (PredictionMode::TwoBody, None) => PredictedPath::default(),
+    }
+}
+
+/// Snapshot of every physics object for prediction
+pub fn predicted_bodies(query: &BodyQuery) -> Vec<PredictedBody> {
+    query
+        .iter()
+        .map(|(entity, phys, is_user, is_debris)| PredictedBody {
+            entity,
+            radius: phys.radius,
+            state: GravityBody {
+                position: phys.position,
+                velocity: phys.vel,
+                mass: phys.mass,
+                attractor: !(is_user || is_debris),
+                external_accel: DVec3::ZERO,
+            },
+        })
+        .collect()
+}
+
+/// Prediction step size, growing with the distance from the central body's surface
+pub fn prediction_dt(distance_from_central: f32) -> f32 {
+    let normalized_distance = distance_from_central / (MOON_ORBIT_RADIUS);
+    MIN_DT + normalized_distance * (MAX_DT - MIN_DT)
+}
+
+/// Edge of the central body's sphere of influence for two-body predictions
+#[derive(Clone, Copy)]
+struct SoiExit {
+    /// Body the path continues around past the edge
+    parent: Entity,
+    radius: f32,
+}
+
+/// Predicts `object` around `central` alone, drawn around the central body
+///
+/// Stops where the path leaves the central body's sphere of influence.
+async fn propagate_two_body(
+    object: &PredictedBody,
+    central: &PredictedBody,
+    exit: Option<SoiExit>,
+    burns: &[(f32, Vec2)],
+    integrator: Integrator,
+    progress: Option<&PathProgress>,
+) -> PredictedPath {
+    let start = PathSample {
+        position: (object.state.position - central.state.position).as_vec3(),
+        velocity: (object.state.velocity - central.state.velocity).as_vec3(),
+        time: 0.,
+    };
+    let collision_distance = object.radius + central.radius;
+
+    // Store positions relative to the central body
+    // This makes orbits appear as circles around the central body
+    let to_point = |sample: &PathSample| PathPoint {
+        reference: Some(central.entity),
+        offset: sample.position,
+    };
+
+    let samples = propagate_path(
+        start,
+        central.state.mass,
+        collision_distance..exit.map_or(f32::INFINITY, |exit| exit.radius),
+        prediction_dt(start.position.length() - collision_distance),
+        burns,
+        integrator,
+        |samples| {
+            if let Some(progress) = progress {
+                let path: Vec<PathPoint> = samples.iter().map(to_point).collect();
+                progress.publish(samples, &path);
+            }
+        },
+    )
+    .await;
+
+    let events = samples
+        .last()
+        .and_then(|sample| {
+            let distance = sample.position.length();
+            let kind = if distance < collision_distance {
+                PathEventKind::Collision {
+                    body: central.entity,
+                }
+            } else {
+                let exit = exit.filter(|exit| distance > exit.radius)?;
+                PathEventKind::SoiChange {
+                    from: central.entity,
+                    to: exit.parent,
+                }
+            };
+            Some(PathEvent {
+                kind,
+                time: sample.time,
+                point: to_point(sample),
+            })
+        })
+        .into_iter()
+        .collect();
+
+    PredictedPath {
+        path: samples.iter().map(to_point).collect(),
+        local_samples: samples.clone(),
+        samples,
+        central_body: Some(central.entity),
+        events,
+    }
+}
+
+/// Propagates a path around a single central body
+///
+/// Works in the central body's frame, which is exact as long as the central body
+/// moves in a straight line. `burns` are `(time, delta_v)` pairs with the
+/// delta-v given as (prograde, radial) and applied when the path reaches their
+/// time. Stops after a full revolution following the last burn, when the
+/// distance from the central body leaves `bounds` (a collision below, an exit
+/// above) or after `PREDICTION_POINTS` points. `publish` is handed the new
+/// samples before every yield.
+pub async fn propagate_path(
+    start: PathSample,
+    central_mass: f32,
+    bounds: Range<f32>,
+    dt: f32,
+    burns: &[(f32, Vec2)],
+    integrator: Integrator,
+    mut publish: impl FnMut(&[PathSample]),
+) -> Vec<PathSample> {
+    let mut samples = Vec::with_capacity(PREDICTION_POINTS);
+    let mut burns = burns.iter().peekable();
+
+    let mut position = start.position.as_dvec3();
+    let mut velocity = start.velocity.as_dvec3();
+    let mut time = start.time;
+
+    // Add the current position as the first prediction point to eliminate gap
+    samples.push(start);
+
+    let mut total_angle = 0.0;
+    let mut prev_position = position;
+    let mut published = 0;
+
+    for i in 0..(PREDICTION_POINTS - 1) {
+        if i % YIELD_INTERVAL == YIELD_INTERVAL - 1 {
+            publish(&samples[published..]);
+            published = samples.len();
+            future::yield_now().await;
+        }
+
+        // Apply every burn the path has reached
+        while let Some((_, delta_v)) = burns.next_if(|(burn_time, _)| *burn_time <= time) {
+            let (prograde, radial) = maneuver_frame(position.as_vec3(), velocity.as_vec3());
+            velocity += (prograde * delta_v.x + radial * delta_v.y).as_dvec3();
+            total_angle = 0.0;
+        }
+
+        // Gravity from the central body alone
+        let mut positions = [position];
+        let mut velocities = [velocity];
+        integrator.step(&mut positions, &mut velocities, dt as f64, |positions| {
+            let distance_sq_softened = positions[0].length_squared() + (SOFTENING as f64).powi(2);
+            let inv_r_cubed = distance_sq_softened.powf(-1.5);
+            vec![-(G as f64) * central_mass as f64 * inv_r_cubed * positions[0]]
+        });
+        [position] = positions;
+        [velocity] = velocities;
+        time += dt;
+
+        samples.push(PathSample {
+            position: position.as_vec3(),
+            velocity: velocity.as_vec3(),
+            time,
+        });
+
+        // Check for collision with the central body or leaving its sphere
+        if position.length() < bounds.start as f64 || position.length() > bounds.end as f64 {
+            break;
+        }
+
+        let angle = prev_position.angle_between(position) as f32;
+        total_angle += angle;
+
+        if total_angle > std::f32::consts::TAU && burns.peek().is_none() {
+            break;
+        }
+
+        prev_position = position;
+    }
+
+    samples
+}
+
+pub fn render_trajectory_predictions(
+    mut gizmos: Gizmos,
+    clock: Res<MissionClock>,
+    query: Query<(&TrajectoryPrediction, Option<&CelestialBody>)>,
+    bodies: Query<(&Transform, &PhysicsObject)>,
+    camera: Query<&Projection, With<Camera2d>>,
+) {
+    let cam_scale = camera
+        .iter()
+        .find_map(|proj| match proj {
+            Projection::Orthographic(ortho) => Some(ortho.scale),
+            _ => None,
+        })
+        .unwrap_or(1.0);
+    let position_of = |entity| bodies.get(entity).ok().map(|(t, _)| t.translation);
+
+    for (prediction, body) in query {
+        // Skip the part of the cached path that has already been flown
+        let elapsed = (clock.elapsed - prediction.epoch) as f32;
+        let start = prediction
+            .samples
+            .partition_point(|sample| sample.time < elapsed)
+            .saturating_sub(1);
+        let points = prediction.points.get(start..).unwrap_or_default();
+
+        // Bodies draw their path in their own color, vessels in white
+        let path_color = body.map_or(Color::WHITE, |body| body.color);
+        for (i, (first, second)) in points.iter().tuple_windows().enumerate() {
+            let color = path_color.with_alpha(1.0 - (i as f32 / points.len() as f32));
+            gizmos.line(*first, *second, color);
+        }
+
+        for event in prediction.events.iter().filter(|e| e.time >= elapsed) {
+            let point = event.point.resolve(position_of);
+            match event.kind {
+                PathEventKind::SoiChange { .. } => {
+                    gizmos.circle_2d(point.truncate(), 8. * cam_scale, Color::srgb(0.7, 0.4, 1.0));
+                }
+                PathEventKind::Collision { .. } => {
+                    let size = 6. * cam_scale;
+                    let color = Color::srgb(1.0, 0.2, 0.2);
+                    gizmos.line(
+                        point + Vec3::new(-size, -size, 0.),
+                        point + Vec3::new(size, size, 0.),
+                        color,
+                    );
+                    gizmos.line(
+                        point + Vec3::new(-size, size, 0.),
+                        point + Vec3::new(size, -size, 0.),
+                        color,
+                    );
+                }
+                PathEventKind::ClosestApproach {
+                    body, body_point, ..
+                } => {
+                    // Mark the path and where the body will be at that moment
+                    let color = Color::srgba(1.0, 0.9, 0.3, 0.6);
+                    let body_point = body_point.resolve(position_of);
+                    gizmos.circle_2d(point.truncate(), 5. * cam_scale, color);
+                    gizmos.line(point, body_point, color);
+                    if let Ok((_, phys)) = bodies.get(body) {
+                        gizmos.circle_2d(body_point.truncate(), phys.radius, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADIUS: f32 = 318_550.;
+    const MU: f32 = 5.0e11;
+
+    fn body(
+        entity: u32,
+        radius: f32,
+        mass: f32,
+        position: DVec3,
+        velocity: DVec3,
+    ) -> PredictedBody {
+        PredictedBody {
+            entity: Entity::from_raw(entity),
+            radius,
+            state: GravityBody {
+                position,
+                velocity,
+                mass,
+                attractor: entity == 1,
+                external_accel: DVec3::ZERO,
+            },
+        }
+    }
+
+    #[test]
+    fn partial_paths_lead_the_finished_one() {
+        let central = body(1, RADIUS, MU / G, DVec3::ZERO, DVec3::ZERO);
+        // An escape trajectory runs for every prediction point
+        let distance = 2. * RADIUS as f64;
+        let speed = 1.5 * (2. * MU as f64 / distance).sqrt();
+        let object = body(2, 10., 1000., DVec3::X * distance, DVec3::Y * speed);
+
+        let progress = PathProgress::default();
+        let path = block_on(propagate_two_body(
+            &object,
+            &central,
+            None,
+            &[],
+            Integrator::default(),
+            Some(&progress),
+        ));
+
+        let chunk = progress.take();
+        assert!(!chunk.samples.is_empty(), "nothing was published early");
+        assert!(chunk.samples.len() < path.samples.len());
+        assert_eq!(chunk.path.len(), chunk.samples.len());
+        for (partial, finished) in chunk.samples.iter().zip(&path.samples) {
+            assert_eq!(partial.time, finished.time);
+            assert_eq!(partial.position, finished.position);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use super::{PathProgress, PredictedPath, YIELD_INTERVAL, prediction_dt};
+use crate::components::trajectory_prediction::{PathEvent, PathEventKind, PathPoint, PathSample};
+use crate::constants::PREDICTION_POINTS;
+use crate::integrator::Integrator;
+use crate::orbital::maneuver_frame;
+use crate::systems::physics::SoiHierarchy;
+use crate::systems::physics::gravity::{GravityBody, integrate};
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::future;
+
+/// An object taking part in a prediction
+#[derive(Clone, Copy, Debug)]
+pub struct PredictedBody {
+    pub entity: Entity,
+    pub radius: f32,
+    pub state: GravityBody,
+}
+
+/// Closest approach of the path to one body seen so far
+#[derive(Clone, Copy)]
+struct Approach {
+    sample: usize,
+    distance: f32,
+    time: f32,
+    point: PathPoint,
+    body_point: PathPoint,
+}
+
+/// Propagates every body with the simulation's integrator and records the
+/// path of `target`
+///
+/// The step size follows the target's distance from the surface of its central
+/// body, which starts out as `central` and follows the spheres of influence. `burns` are `(time, delta_v)` pairs with the delta-v given as
+/// (prograde, radial) relative to the central body at the time of the burn.
+/// Stops after a full revolution around the central body following the last
+/// burn, when the target hits a body or after `PREDICTION_POINTS` points.
+/// The path so far goes to `progress` before every yield.
+pub async fn propagate_n_body(
+    bodies: &[PredictedBody],
+    target: usize,
+    central: Option<usize>,
+    soi: &SoiHierarchy,
+    burns: &[(f32, Vec2)],
+    integrator: Integrator,
+    progress: Option<&PathProgress>,
+) -> PredictedPath {
+    let mut states: Vec<GravityBody> = bodies.iter().map(|body| body.state).collect();
+    let mut burns = burns.iter().peekable();
+
+    let mut central = central;
+    let start_central = central;
+
+    let mut path = PredictedPath {
+        central_body: start_central.map(|index| bodies[index].entity),
+        ..PredictedPath::with_capacity(PREDICTION_POINTS)
+    };
+    let mut approaches: Vec<Option<Approach>> = vec![None; bodies.len()];
+
+    let mut time = 0.;
+    let mut total_angle = 0.;
+    let mut published = 0;
+
+    for step in 0..PREDICTION_POINTS {
+        if step % YIELD_INTERVAL == YIELD_INTERVAL - 1 {
+            if let Some(progress) = progress {
+                progress.publish(&path.samples[published..], &path.path[published..]);
+                published = path.samples.len();
+            }
+            future::yield_now().await;
+        }
+
+        let local = relative_state(&states, target, central, time);
+        let point = path_point(bodies, &states, central, states[target].position);
+        path.samples
+            .push(relative_state(&states, target, start_central, time));
+        path.local_samples.push(local);
+        path.path.push(point);
+
+        for (index, body) in bodies.iter().enumerate() {
+            if index == target || !body.state.attractor {
+                continue;
+            }
+
+            let distance = states[target].position.distance(states[index].position) as f32;
+            if approaches[index].is_none_or(|approach| distance < approach.distance) {
+                approaches[index] = Some(Approach {
+                    sample: step,
+                    distance,
+                    time,
+                    point,
+                    body_point: path_point(bodies, &states, central, states[index].position),
+                });
+            }
+
+            if distance < bodies[target].radius + body.radius {
+                path.events.push(PathEvent {
+                    kind: PathEventKind::Collision { body: body.entity },
+                    time,
+                    point,
+                });
+            }
+        }
+
+        if path
+            .events
+            .last()
+            .is_some_and(|event| matches!(event.kind, PathEventKind::Collision { .. }))
+            || step == PREDICTION_POINTS - 1
+        {
+            break;
+        }
+
+        // Apply every burn the path has reached
+        while let Some((_, delta_v)) = burns.next_if(|(burn_time, _)| *burn_time <= time) {
+            let (prograde, radial) = maneuver_frame(local.position, local.velocity);
+            states[target].velocity += (prograde * delta_v.x + radial * delta_v.y).as_dvec3();
+            total_angle = 0.;
+        }
+
+        let surface_distance = central.map_or(local.position.length(), |index| {
+            local.position.length() - bodies[target].radius - bodies[index].radius
+        });
+        let dt = prediction_dt(surface_distance);
+
+        integrate(&mut states, integrator, dt);
+        let new_central = central_of(bodies, &states, target, central, soi);
+        time += dt;
+
+        if new_central != central {
+            if let (Some(from), Some(to)) = (central, new_central) {
+                path.events.push(PathEvent {
+                    kind: PathEventKind::SoiChange {
+                        from: bodies[from].entity,
+                        to: bodies[to].entity,
+                    },
+                    time,
+                    point: path_point(bodies, &states, central, states[target].position),
+                });
+            }
+            central = new_central;
+            total_angle = 0.;
+            continue;
+        }
+
+        let next = relative_state(&states, target, central, time);
+        total_angle += local.position.angle_between(next.position);
+        if total_angle > std::f32::consts::TAU && burns.peek().is_none() {
+            // Record the closing point before stopping
+            path.samples
+                .push(relative_state(&states, target, start_central, time));
+            path.local_samples.push(next);
+            path.path.push(path_point(
+                bodies,
+                &states,
+                central,
+                states[target].position,
+            ));
+            break;
+        }
+    }
+
+    // Only minima inside the path are approaches, the ends are just where we stopped looking
+    let last = path.path.len() - 1;
+    path.events.extend(
+        approaches
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, approach)| Some((index, approach?)))
+            .filter(|(_, approach)| approach.sample > 0 && approach.sample < last)
+            .map(|(index, approach)| PathEvent {
+                kind: PathEventKind::ClosestApproach {
+                    body: bodies[index].entity,
+                    distance: approach.distance,
+                    body_point: approach.body_point,
+                },
+                time: approach.time,
+                point: approach.point,
+            }),
+    );
+    path.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    path
+}
+
+/// Index of the body whose sphere of influence `target` is in, `central` so far
+fn central_of(
+    bodies: &[PredictedBody],
+    states: &[GravityBody],
+    target: usize,
+    central: Option<usize>,
+    soi: &SoiHierarchy,
+) -> Option<usize> {
+    let index_of = |entity: Entity| bodies.iter().position(|body| body.entity == entity);
+    let entity = bodies[target].entity;
+    let body = match soi.node(entity) {
+        Some(node) => node.parent,
+        None => soi.body_at(
+            states[target].position,
+            central.map(|index| bodies[index].entity),
+            |entity| index_of(entity).map(|index| states[index].position),
+        ),
+    };
+    body.and_then(index_of)
+}
+
+/// State of `target` relative to the body at `index`
+fn relative_state(
+    states: &[GravityBody],
+    target: usize,
+    index: Option<usize>,
+    time: f32,
+) -> PathSample {
+    index.map_or(PathSample::default(), |index| PathSample {
+        position: (states[target].position - states[index].position).as_vec3(),
+        velocity: (states[target].velocity - states[index].velocity).as_vec3(),
+        time,
+    })
+}
+
+/// Draws `position` around the central body, so orbits appear as closed curves
+fn path_point(
+    bodies: &[PredictedBody],
+    states: &[GravityBody],
+    central: Option<usize>,
+    position: DVec3,
+) -> PathPoint {
+    match central {
+        Some(index) => PathPoint {
+            reference: Some(bodies[index].entity),
+            offset: (position - states[index].position).as_vec3(),
+        },
+        None => PathPoint {
+            reference: None,
+            offset: position.as_vec3(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{G, MOON_ORBIT_RADIUS};
+    use bevy::tasks::block_on;
+
+    const PLANET_MASS: f32 = 7.5e21;
+    const PLANET_RADIUS: f32 = 318_550.;
+    const MOON_MASS: f32 = PLANET_MASS / 81.;
+    const MOON_RADIUS: f32 = 86_850.;
+
+    fn body(
+        entity: u32,
+        radius: f32,
+        mass: f32,
+        position: DVec3,
+        velocity: DVec3,
+    ) -> PredictedBody {
+        PredictedBody {
+            entity: Entity::from_raw(entity),
+            radius,
+            state: GravityBody {
+                position,
+                velocity,
+                mass,
+                attractor: entity != 3,
+                external_accel: DVec3::ZERO,
+            },
+        }
+    }
+
+    /// A planet at rest and a moon on a circular orbit along +X, then `vessel`
+    fn system(vessel: PredictedBody) -> Vec<PredictedBody> {
+        let distance = MOON_ORBIT_RADIUS as f64;
+        let speed = (G as f64 * (PLANET_MASS + MOON_MASS) as f64 / distance).sqrt();
+        vec![
+            body(1, PLANET_RADIUS, PLANET_MASS, DVec3::ZERO, DVec3::ZERO),
+            body(
+                2,
+                MOON_RADIUS,
+                MOON_MASS,
+                DVec3::X * distance,
+                DVec3::Y * speed,
+            ),
+            vessel,
+        ]
+    }
+
+    fn predict(bodies: &[PredictedBody], central: usize) -> PredictedPath {
+        let states: Vec<(Entity, GravityBody)> = bodies
+            .iter()
+            .map(|body| (body.entity, body.state))
+            .collect();
+        let soi = SoiHierarchy::build(&states, &SoiHierarchy::default());
+        block_on(propagate_n_body(
+            bodies,
+            2,
+            Some(central),
+            &soi,
+            &[],
+            Integrator::default(),
+            None,
+        ))
+    }
+
+    #[test]
+    fn falling_vessel_hits_the_planet() {
+        let vessel = body(
+            3,
+            10.,
+            1000.,
+            DVec3::X * 2. * PLANET_RADIUS as f64,
+            DVec3::NEG_X * 500.,
+        );
+        let path = predict(&system(vessel), 0);
+
+        let impact = path.events.last().expect("no events");
+        assert!(matches!(
+            impact.kind,
+            PathEventKind::Collision { body } if body == Entity::from_raw(1)
+        ));
+        assert_eq!(impact.time, path.samples.last().unwrap().time);
+    }
+
+    #[test]
+    fn escaping_vessel_leaves_the_moon() {
+        let moon = system(body(3, 10., 1000., DVec3::ZERO, DVec3::ZERO))[1].state;
+        let vessel = body(
+            3,
+            10.,
+            1000.,
+            moon.position + DVec3::X * 2. * MOON_RADIUS as f64,
+            moon.velocity + DVec3::X * 1000.,
+        );
+        let path = predict(&system(vessel), 1);
+
+        let change = path
+            .events
+            .iter()
+            .find(|event| matches!(event.kind, PathEventKind::SoiChange { .. }))
+            .expect("no SOI change");
+        assert!(matches!(
+            change.kind,
+            PathEventKind::SoiChange { from, to }
+                if from == Entity::from_raw(2) && to == Entity::from_raw(1)
+        ));
+        assert!(
+            !path
+                .events
+                .iter()
+                .any(|event| matches!(event.kind, PathEventKind::Collision { .. }))
+        );
+    }
+}
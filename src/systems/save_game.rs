@@ -0,0 +1,435 @@
+use crate::components::aerodynamics::Aerodynamics;
+use crate::components::appearance::{Appearance, Shape};
+use crate::components::atmosphere::Atmosphere;
+use crate::components::attitude::AttitudeControl;
+use crate::components::celestial_body::CelestialBody;
+use crate::components::damage::Destroyed;
+use crate::components::flight_computer::FlightComputer;
+use crate::components::landing::SurfaceContact;
+use crate::components::maneuver::{ManeuverNode, ManeuverPlan};
+use crate::components::markers::{Debris, User};
+use crate::components::objectives::ObjectiveProgress;
+use crate::components::physics_object::PhysicsObject;
+use crate::components::propulsion::Propulsion;
+use crate::components::staging::{Stage, Stages};
+use crate::components::target::Target;
+use crate::components::trajectory_prediction::TrajectoryPrediction;
+use crate::components::transfer::TransferPlanner;
+use crate::config::Config;
+use crate::persistence::{
+    SaveGame, SavedAerodynamics, SavedAppearance, SavedAtmosphere, SavedAttitudeControl,
+    SavedCelestialBody, SavedManeuverNode, SavedObject, SavedObjectives, SavedPropulsion,
+    SavedShape, SavedStage, SavedStages,
+};
+use crate::systems::objectives::ObjectiveTracker;
+use crate::systems::physics::MissionClock;
+use crate::systems::world_setup::ActiveScenario;
+use bevy::math::DVec3;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Request to write the current simulation state into a save slot
+#[derive(Event)]
+pub struct SaveRequest(pub String);
+
+/// Request to replace the current simulation state with a save slot
+#[derive(Event)]
+pub struct LoadRequest(pub String);
+
+type SavedQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Option<&'static Name>,
+        &'static Transform,
+        &'static PhysicsObject,
+        &'static Appearance,
+        Has<User>,
+        Has<TrajectoryPrediction>,
+        // Engine, remaining stages, attitude control, target and flight computer of vessels
+        (
+            Option<&'static Propulsion>,
+            Option<&'static Stages>,
+            Option<&'static AttitudeControl>,
+            Option<&'static Target>,
+            Option<&'static FlightComputer>,
+        ),
+        Option<&'static ObjectiveTracker>,
+        Option<&'static ManeuverPlan>,
+        Option<&'static Atmosphere>,
+        Option<&'static Aerodynamics>,
+        Option<&'static Destroyed>,
+        Has<Debris>,
+        Option<&'static CelestialBody>,
+    ),
+>;
+
+pub fn save_game_system(
+    mut requests: EventReader<SaveRequest>,
+    config: Res<Config>,
+    clock: Res<MissionClock>,
+    query: SavedQuery,
+) {
+    for SaveRequest(slot) in requests.read() {
+        let indices: HashMap<Entity, usize> = query
+            .iter()
+            .enumerate()
+            .map(|(index, (entity, ..))| (entity, index))
+            .collect();
+
+        let objects = query
+            .iter()
+            .map(
+                |(
+                    _,
+                    name,
+                    transform,
+                    phys,
+                    appearance,
+                    is_user,
+                    predicted,
+                    (propulsion, stages, attitude, target, computer),
+                    tracker,
+                    plan,
+                    atmosphere,
+                    aero,
+                    destroyed,
+                    is_debris,
+                    celestial_body,
+                )| {
+                    SavedObject {
+                        name: name.map(|n| n.to_string()).unwrap_or_default(),
+                        position: phys.position.to_array(),
+                        rotation: transform.rotation.to_array(),
+                        velocity: phys.vel.to_array(),
+                        mass: phys.mass,
+                        radius: phys.radius,
+                        central_body: phys
+                            .central_body
+                            .and_then(|entity| indices.get(&entity).copied()),
+                        appearance: save_appearance(appearance),
+                        is_user,
+                        predict_trajectory: predicted,
+                        propulsion: propulsion.map(|p| SavedPropulsion {
+                            max_thrust: p.max_thrust,
+                            thrust_percentage: p.thrust_percentage,
+                            fuel: p.fuel,
+                            max_fuel: p.max_fuel,
+                            specific_impulse: p.specific_impulse,
+                            dry_mass: p.dry_mass,
+                        }),
+                        objectives: tracker.map(|t| SavedObjectives {
+                            current: t.progress.current.clone(),
+                            upcoming: t.progress.upcoming.clone(),
+                            completed: t.progress.completed.clone(),
+                            is_completed: t.progress.is_completed,
+                            completion_time: t.progress.completion_time,
+                            escape_elapsed: t.escape_stopwatch.elapsed_secs(),
+                            orbit_sweep: t.orbit_sweep,
+                        }),
+                        maneuver_nodes: plan
+                            .map(|plan| {
+                                plan.nodes
+                                    .iter()
+                                    .map(|node| SavedManeuverNode {
+                                        time: node.time,
+                                        prograde: node.prograde,
+                                        radial: node.radial,
+                                    })
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                        atmosphere: atmosphere.map(|a| SavedAtmosphere {
+                            sea_level_density: a.sea_level_density,
+                            scale_height: a.scale_height,
+                            height: a.height,
+                            heating_coefficient: a.heating_coefficient,
+                        }),
+                        aerodynamics: aero.map(|a| SavedAerodynamics {
+                            drag_coefficient: a.drag_coefficient,
+                            reference_area: a.reference_area,
+                            nose_radius: a.nose_radius,
+                            thermal_time_constant: a.thermal_time_constant,
+                            max_temperature: a.max_temperature,
+                            max_g_load: a.max_g_load,
+                            temperature: a.temperature,
+                        }),
+                        destroyed: destroyed.map(|d| d.0),
+                        is_debris,
+                        stages: stages.map(|s| SavedStages {
+                            active_dry_mass: s.active_dry_mass,
+                            upcoming: s
+                                .upcoming
+                                .iter()
+                                .map(|stage| SavedStage {
+                                    dry_mass: stage.dry_mass,
+                                    fuel: stage.fuel,
+                                    max_fuel: stage.max_fuel,
+                                    max_thrust: stage.max_thrust,
+                                    specific_impulse: stage.specific_impulse,
+                                })
+                                .collect(),
+                        }),
+                        attitude_control: attitude.map(|a| SavedAttitudeControl {
+                            angular_velocity: a.angular_velocity,
+                            wheel_torque: a.wheel_torque,
+                            rcs_torque: a.rcs_torque,
+                            monopropellant: a.monopropellant,
+                            max_monopropellant: a.max_monopropellant,
+                            monopropellant_rate: a.monopropellant_rate,
+                            sas: a.sas,
+                            hold: a.hold,
+                        }),
+                        target: target.and_then(|Target(entity)| indices.get(entity).copied()),
+                        flight_computer: computer.is_some_and(|computer| computer.mission),
+                        celestial_body: celestial_body.map(|body| SavedCelestialBody {
+                            parent: body.parent.and_then(|entity| indices.get(&entity).copied()),
+                            min_warp_altitude: body.min_warp_altitude,
+                            limited_warp_altitude: body.limited_warp_altitude,
+                            color: body.color.to_srgba().to_f32_array(),
+                        }),
+                    }
+                },
+            )
+            .collect();
+
+        let save = SaveGame {
+            warp_stage: config.warp_stage,
+            mission_time: clock.elapsed,
+            objects,
+        };
+
+        match save.store(slot) {
+            Ok(()) => info!("Saved game to slot {slot}"),
+            Err(e) => error!("Failed to save game to slot {slot}: {e}"),
+        }
+    }
+}
+
+pub fn load_game_system(
+    mut commands: Commands,
+    mut requests: EventReader<LoadRequest>,
+    mut config: ResMut<Config>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut clock: ResMut<MissionClock>,
+    active_scenario: Option<ResMut<ActiveScenario>>,
+    existing: Query<Entity, With<PhysicsObject>>,
+) {
+    // Only the most recent request matters, earlier ones would be overwritten
+    let Some(LoadRequest(slot)) = requests.read().last() else {
+        return;
+    };
+
+    let save = match SaveGame::restore(slot) {
+        Ok(save) => save,
+        Err(e) => {
+            error!("Failed to load slot {slot}: {e}");
+            return;
+        }
+    };
+
+    // A scenario that is still loading must not spawn on top of the save
+    if let Some(mut active_scenario) = active_scenario {
+        active_scenario.spawned = true;
+    }
+
+    for entity in &existing {
+        commands.entity(entity).despawn();
+    }
+
+    // Reserve all entities first so central body indices can be remapped
+    let entities: Vec<Entity> = save
+        .objects
+        .iter()
+        .map(|_| commands.spawn_empty().id())
+        .collect();
+
+    for (object, &entity) in save.objects.iter().zip(&entities) {
+        let mut entity = commands.entity(entity);
+        entity.insert((
+            Name::new(object.name.clone()),
+            Transform::from_rotation(Quat::from_array(object.rotation)),
+            PhysicsObject::new(
+                object.mass,
+                object.radius,
+                DVec3::from_array(object.position),
+                DVec3::from_array(object.velocity),
+                object
+                    .central_body
+                    .and_then(|index| entities.get(index).copied()),
+            ),
+            load_appearance(&object.appearance),
+        ));
+
+        if object.is_user {
+            let nodes = object
+                .maneuver_nodes
+                .iter()
+                .map(|node| ManeuverNode {
+                    time: node.time,
+                    prograde: node.prograde,
+                    radial: node.radial,
+                })
+                .collect();
+            entity.insert((
+                User,
+                ManeuverPlan { nodes, ..default() },
+                SurfaceContact::default(),
+                FlightComputer {
+                    mission: object.flight_computer,
+                    ..default()
+                },
+                TransferPlanner::default(),
+            ));
+        }
+
+        if object.is_debris {
+            entity.insert(Debris);
+        }
+
+        if object.predict_trajectory {
+            entity.insert(TrajectoryPrediction::new());
+        }
+
+        if let Some(p) = &object.propulsion {
+            entity.insert(Propulsion {
+                max_thrust: p.max_thrust,
+                thrust_percentage: p.thrust_percentage,
+                fuel: p.fuel,
+                max_fuel: p.max_fuel,
+                specific_impulse: p.specific_impulse,
+                dry_mass: p.dry_mass,
+            });
+        }
+
+        if let Some(s) = &object.stages {
+            entity.insert(Stages {
+                active_dry_mass: s.active_dry_mass,
+                upcoming: s
+                    .upcoming
+                    .iter()
+                    .map(|stage| Stage {
+                        dry_mass: stage.dry_mass,
+                        fuel: stage.fuel,
+                        max_fuel: stage.max_fuel,
+                        max_thrust: stage.max_thrust,
+                        specific_impulse: stage.specific_impulse,
+                    })
+                    .collect(),
+            });
+        }
+
+        if let Some(a) = &object.attitude_control {
+            entity.insert(AttitudeControl {
+                angular_velocity: a.angular_velocity,
+                wheel_torque: a.wheel_torque,
+                rcs_torque: a.rcs_torque,
+                monopropellant: a.monopropellant,
+                max_monopropellant: a.max_monopropellant,
+                monopropellant_rate: a.monopropellant_rate,
+                sas: a.sas,
+                hold: a.hold,
+                ..default()
+            });
+        }
+
+        if let Some(target) = object.target.and_then(|index| entities.get(index)) {
+            entity.insert(Target(*target));
+        }
+
+        if let Some(a) = &object.atmosphere {
+            entity.insert(Atmosphere {
+                sea_level_density: a.sea_level_density,
+                scale_height: a.scale_height,
+                height: a.height,
+                heating_coefficient: a.heating_coefficient,
+            });
+        }
+
+        if let Some(a) = &object.aerodynamics {
+            entity.insert(Aerodynamics {
+                drag_coefficient: a.drag_coefficient,
+                reference_area: a.reference_area,
+                nose_radius: a.nose_radius,
+                thermal_time_constant: a.thermal_time_constant,
+                max_temperature: a.max_temperature,
+                max_g_load: a.max_g_load,
+                temperature: a.temperature,
+                ..default()
+            });
+        }
+
+        match &object.celestial_body {
+            Some(body) => {
+                let [r, g, b, a] = body.color;
+                entity.insert(CelestialBody {
+                    name: object.name.clone(),
+                    radius: object.radius,
+                    parent: body.parent.and_then(|index| entities.get(index).copied()),
+                    min_warp_altitude: body.min_warp_altitude,
+                    limited_warp_altitude: body.limited_warp_altitude,
+                    color: Color::srgba(r, g, b, a),
+                });
+            }
+            // Older saves only tell bodies apart from spacecraft by what they lack
+            None if !object.is_user && !object.is_debris && object.propulsion.is_none() => {
+                entity.insert(CelestialBody::new(
+                    object.name.clone(),
+                    object.radius,
+                    object
+                        .central_body
+                        .and_then(|index| entities.get(index).copied()),
+                    load_appearance(&object.appearance).color,
+                ));
+            }
+            None => {}
+        }
+
+        if let Some(cause) = object.destroyed {
+            entity.insert(Destroyed(cause));
+        }
+
+        if let Some(o) = &object.objectives {
+            let mut tracker = ObjectiveTracker::new(ObjectiveProgress {
+                current: o.current.clone(),
+                upcoming: o.upcoming.clone(),
+                completed: o.completed.clone(),
+                is_completed: o.is_completed,
+                completion_time: o.completion_time,
+            });
+            tracker
+                .escape_stopwatch
+                .set_elapsed(Duration::from_secs_f32(o.escape_elapsed));
+            tracker.orbit_sweep = o.orbit_sweep;
+            entity.insert(tracker);
+        }
+    }
+
+    config.set_warp_stage(save.warp_stage, &mut fixed_time);
+    clock.elapsed = save.mission_time;
+    clock.stop_at = None;
+    info!("Loaded game from slot {slot}");
+}
+
+fn save_appearance(appearance: &Appearance) -> SavedAppearance {
+    SavedAppearance {
+        shape: match appearance.shape {
+            Shape::Circle(radius) => SavedShape::Circle(radius),
+            Shape::Triangle(vertices) => SavedShape::Triangle(vertices.map(|v| v.to_array())),
+        },
+        color: appearance.color.to_srgba().to_f32_array(),
+    }
+}
+
+fn load_appearance(saved: &SavedAppearance) -> Appearance {
+    let [r, g, b, a] = saved.color;
+    Appearance {
+        shape: match saved.shape {
+            SavedShape::Circle(radius) => Shape::Circle(radius),
+            SavedShape::Triangle(vertices) => Shape::Triangle(vertices.map(Vec2::from_array)),
+        },
+        color: Color::srgba(r, g, b, a),
+    }
+}
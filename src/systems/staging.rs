@@ -0,0 +1,84 @@
+use crate::components::appearance::Appearance;
+use crate::components::damage::Destroyed;
+use crate::components::markers::Debris;
+use crate::components::object_bundle::ObjectBundle;
+use crate::components::physics_object::PhysicsObject;
+use crate::components::propulsion::Propulsion;
+use crate::components::staging::Stages;
+use crate::config::Config;
+use crate::constants::{DEBRIS_RADIUS, LANDING_CONTACT_MARGIN, STAGE_SEPARATION_SPEED};
+use bevy::prelude::*;
+
+/// Request to jettison the firing stage of a vessel and ignite the next one
+#[derive(Event)]
+pub struct StageRequest(pub Entity);
+
+type StagingQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static mut PhysicsObject,
+        &'static mut Propulsion,
+        &'static mut Stages,
+        Option<&'static Name>,
+    ),
+    Without<Destroyed>,
+>;
+
+/// Separates spent stages into debris that keeps flying on its own
+///
+/// The stage is pushed out behind the vessel, the separation impulse is shared
+/// by mass so the pair's momentum is unchanged.
+pub fn staging_system(
+    mut commands: Commands,
+    mut requests: EventReader<StageRequest>,
+    config: Res<Config>,
+    mut vessels: StagingQuery,
+) {
+    for StageRequest(vessel) in requests.read() {
+        let Ok((transform, mut phys, mut propulsion, mut stages, name)) = vessels.get_mut(*vessel)
+        else {
+            continue;
+        };
+        let Some(spent) = stages.separate(&mut propulsion) else {
+            info!("No stage left to separate");
+            continue;
+        };
+
+        let total_mass = phys.mass as f64;
+        phys.mass = propulsion.mass();
+
+        // Far enough behind that the two don't overlap within the next step
+        let forward = transform.rotation.mul_vec3(Vec3::Y).as_dvec3();
+        let gap = (phys.radius + DEBRIS_RADIUS + LANDING_CONTACT_MARGIN) as f64
+            + (STAGE_SEPARATION_SPEED * config.step_dt()) as f64;
+        let separation = forward * STAGE_SEPARATION_SPEED as f64;
+        let debris_velocity = phys.vel - separation * phys.mass as f64 / total_mass;
+        phys.vel += separation * spent.mass() as f64 / total_mass;
+
+        let name = name.map_or("Vessel".to_string(), |name| name.to_string());
+        info!(
+            "{} jettisoned a {:.1}t stage, {} left",
+            name,
+            spent.mass() / 1000.,
+            stages.upcoming.len() + 1
+        );
+
+        commands.spawn((
+            ObjectBundle {
+                transform: Transform::from_rotation(transform.rotation),
+                physics_object: PhysicsObject::new(
+                    spent.mass(),
+                    DEBRIS_RADIUS,
+                    phys.position - forward * gap,
+                    debris_velocity,
+                    phys.central_body,
+                ),
+                appearance: Appearance::circle(DEBRIS_RADIUS, Color::srgb(0.6, 0.6, 0.6)),
+            },
+            Name::new(format!("{name} debris")),
+            Debris,
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::components::maneuver::{ManeuverNode, ManeuverPlan};
+use crate::components::markers::User;
+use crate::components::objectives::ObjectiveProgress;
+use crate::components::physics_object::PhysicsObject;
+use crate::components::target::Target;
+use crate::components::transfer::{TransferGoal, TransferPlanner};
+use crate::flight_program::RelativeState;
+use crate::input::{ActionState, InputAction};
+use crate::planner::{Transfer, plan_ejection, plan_intercept, plan_orbit_change};
+use crate::systems::objectives::ObjectiveTracker;
+use crate::systems::physics::{MissionClock, SoiHierarchy};
+use bevy::prelude::*;
+
+type PlannerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static PhysicsObject,
+        Option<&'static Target>,
+        Option<&'static ObjectiveTracker>,
+        &'static mut TransferPlanner,
+        &'static mut ManeuverPlan,
+    ),
+    With<User>,
+>;
+
+/// Plans transfers to the target and turns them into maneuver nodes
+///
+/// Targeting the body the vessel orbits plans an orbit change, targeting a
+/// body that orbits the same body plans an intercept and targeting the body's
+/// own primary plans the escape towards it. Orbits are aimed at the middle of
+/// the orbit objective's band. H replaces the maneuver nodes with the Hohmann
+/// transfer, B with the bi-elliptic one.
+pub fn transfer_planner_system(
+    actions: Res<ActionState>,
+    clock: Res<MissionClock>,
+    hierarchy: Res<SoiHierarchy>,
+    mut user: PlannerQuery,
+    bodies: Query<&PhysicsObject, Without<User>>,
+) {
+    let Some((phys, target, tracker, mut planner, mut plan)) = user.iter_mut().next() else {
+        return; // No user entity found
+    };
+
+    *planner = TransferPlanner::default();
+    let band = tracker.map_or_else(
+        || ObjectiveProgress::default().orbit_band(),
+        |tracker| tracker.progress.orbit_band(),
+    );
+    let altitude = (band.start() + band.end()) / 2.;
+
+    if let Some(Target(target)) = target
+        && let Some(central_body) = phys.central_body
+        && let Ok(central) = bodies.get(central_body)
+        && let Ok(target_phys) = bodies.get(*target)
+    {
+        let vessel = RelativeState::between(phys, central);
+        let primary = central
+            .central_body
+            .and_then(|entity| Some((entity, bodies.get(entity).ok()?)));
+        let soi_radius = hierarchy
+            .radius(central_body)
+            .map_or(f32::INFINITY, |radius| radius as f32);
+
+        if *target == central_body {
+            // Keep the bi-elliptic apoapsis well inside the body's sphere of influence
+            let max_radius = soi_radius / 2.;
+            if let Some((hohmann, bi_elliptic)) =
+                plan_orbit_change(&vessel, central.radius + altitude, max_radius)
+            {
+                planner.goal = Some(TransferGoal::Orbit {
+                    body: central_body,
+                    altitude,
+                });
+                planner.hohmann = Some(hohmann);
+                planner.bi_elliptic = bi_elliptic;
+            }
+        } else if let Some((primary, primary_phys)) = primary
+            && primary == *target
+        {
+            let orbit = RelativeState::between(central, primary_phys);
+            planner.hohmann =
+                plan_ejection(&vessel, &orbit, soi_radius, primary_phys.radius + altitude);
+            planner.goal = planner.hohmann.is_some().then_some(TransferGoal::Orbit {
+                body: primary,
+                altitude,
+            });
+        } else if target_phys.central_body == Some(central_body) {
+            let orbit = RelativeState::between(target_phys, central);
+            planner.hohmann = plan_intercept(&vessel, &orbit);
+            planner.goal = planner
+                .hohmann
+                .is_some()
+                .then_some(TransferGoal::Intercept(*target));
+        }
+    }
+
+    let (transfer, kind) = if actions.just_pressed(InputAction::PlanHohmann) {
+        (&planner.hohmann, "Hohmann")
+    } else if actions.just_pressed(InputAction::PlanBiElliptic) {
+        (&planner.bi_elliptic, "bi-elliptic")
+    } else {
+        return;
+    };
+
+    match transfer {
+        Some(transfer) => {
+            plan.nodes = nodes(transfer, clock.elapsed);
+            plan.selected = Some(0);
+            info!(
+                "Planned {} transfer: {:.1} m/s in {} burns",
+                kind,
+                transfer.delta_v(),
+                transfer.burns.len()
+            );
+        }
+        None => info!("No {} transfer to the target", kind),
+    }
+}
+
+/// Maneuver nodes for the burns of a transfer planned at mission time `now`
+fn nodes(transfer: &Transfer, now: f64) -> Vec<ManeuverNode> {
+    transfer
+        .burns
+        .iter()
+        .map(|burn| ManeuverNode {
+            time: now + burn.time as f64,
+            prograde: burn.prograde,
+            radial: 0.,
+        })
+        .collect()
+}
@@ -1,19 +1,58 @@
-use crate::components::markers::{User, UserInfoUi};
+use crate::components::aerodynamics::Aerodynamics;
+use crate::components::attitude::{AttitudeControl, AttitudeHold};
+use crate::components::celestial_body::CelestialBody;
+use crate::components::damage::Destroyed;
+use crate::components::flight_computer::FlightComputer;
+use crate::components::landing::SurfaceContact;
+use crate::components::maneuver::ManeuverPlan;
+use crate::components::markers::{DiagnosticsUi, User, UserInfoUi};
+use crate::components::orbit::Orbit;
 use crate::components::physics_object::PhysicsObject;
 use crate::components::propulsion::Propulsion;
-use crate::config::Config;
-use crate::constants::{EARTH_RADIUS, LEO_MIN_ALTITUDE, MOON_RADIUS};
+use crate::components::staging::Stages;
+use crate::components::target::Target;
+use crate::components::trajectory_prediction::{PathEventKind, TrajectoryPrediction};
+use crate::components::transfer::{TransferGoal, TransferPlanner};
+use crate::config::{Config, PredictionMode};
+use crate::constants::{
+    DIAGNOSTICS_HISTORY, DT_STAGES, LANDING_SAFE_HORIZONTAL_SPEED, LANDING_SAFE_TILT_DEGREES,
+    LANDING_SAFE_VERTICAL_SPEED, LEO_MIN_ALTITUDE, ORBIT_REQUIRED_SWEEP_DEGREES,
+};
 use crate::systems::objectives::ObjectiveTracker;
+use crate::systems::physics::{
+    ConservationDiagnostics, ConservationSample, MissionClock, hold_direction,
+};
+use crate::systems::warp::WarpTo;
 use bevy::prelude::*;
 
 // Type alias to reduce complexity
-type MoonQuery<'w, 's> = Query<
+type BodyQuery<'w, 's> = Query<'w, 's, (&'static PhysicsObject, &'static CelestialBody)>;
+
+type UserQuery<'w, 's> = Query<
     'w,
     's,
-    (&'static Transform, &'static PhysicsObject),
-    (Without<User>, With<PhysicsObject>),
+    (
+        &'static Propulsion,
+        &'static PhysicsObject,
+        &'static ObjectiveTracker,
+        &'static Orbit,
+        Option<&'static ManeuverPlan>,
+        Option<&'static TrajectoryPrediction>,
+        Option<&'static Aerodynamics>,
+        Option<&'static Destroyed>,
+        Option<&'static SurfaceContact>,
+        Option<&'static Stages>,
+        Option<&'static AttitudeControl>,
+        Option<&'static Target>,
+        Option<&'static FlightComputer>,
+        Option<&'static TransferPlanner>,
+    ),
+    With<User>,
 >;
 
+/// Drift of one conserved quantity in a sample
+type DriftFn = fn(&ConservationDiagnostics, &ConservationSample) -> f64;
+
 pub fn create_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     let font = asset_server.load("jbnf.ttf");
 
@@ -31,23 +70,55 @@ pub fn create_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
 
 pub fn update_ui_system(
     config: Res<Config>,
-    user: Query<(&Transform, &Propulsion, &PhysicsObject, &ObjectiveTracker), With<User>>,
+    clock: Res<MissionClock>,
+    warp_to: Res<WarpTo>,
+    user: UserQuery,
     mut ui: Query<&mut Text, With<UserInfoUi>>,
-    moon_query: MoonQuery,
+    bodies: BodyQuery,
+    names: Query<&Name>,
 ) {
-    let (def_transform, def_propulsion, def_phys, def_tracker) = (
-        Transform::default(),
+    let (def_propulsion, def_phys, def_tracker, def_orbit) = (
         Propulsion::default(),
         PhysicsObject::default(),
         ObjectiveTracker::default(),
+        Orbit::default(),
     );
 
-    let (user_transform, propulsion, physics_object, objective_tracker) =
-        if let Some(data) = user.iter().next() {
-            data
-        } else {
-            (&def_transform, &def_propulsion, &def_phys, &def_tracker)
-        };
+    let (
+        propulsion,
+        physics_object,
+        objective_tracker,
+        orbit,
+        plan,
+        prediction,
+        aero,
+        destroyed,
+        contact,
+        stages,
+        attitude,
+        target,
+        computer,
+        planner,
+    ) = if let Some(data) = user.iter().next() {
+        data
+    } else {
+        (
+            &def_propulsion,
+            &def_phys,
+            &def_tracker,
+            &def_orbit,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    };
 
     let mut ui_text = if let Some(text) = ui.iter_mut().next() {
         text
@@ -55,7 +126,6 @@ pub fn update_ui_system(
         return;
     };
 
-    let pos = user_transform.translation;
     let thrust = propulsion.thrust_percentage * 100.;
     let fuel_percentage = (propulsion.fuel / propulsion.max_fuel) * 100.0;
     let fuel_status = if fuel_percentage <= 0.0 {
@@ -68,38 +138,18 @@ pub fn update_ui_system(
         "✓ Fuel OK"
     };
 
-    // Determine central body (closest celestial body) and calculate altitude relative to it
-    let distance_from_earth = pos.length();
-    let mut closest_moon_distance = f32::INFINITY;
-    let mut closest_moon_velocity = Vec3::ZERO;
-    for (moon_transform, moon_physics) in moon_query.iter() {
-        let distance_to_moon = pos.distance(moon_transform.translation);
-        if distance_to_moon < closest_moon_distance {
-            closest_moon_distance = distance_to_moon;
-            closest_moon_velocity = moon_physics.vel;
-        }
-    }
-
-    // Calculate altitude relative to central body and relative speed
-    let (altitude, central_body_name, is_moon_central, relative_speed) =
-        if closest_moon_distance < distance_from_earth {
-            let relative_velocity = physics_object.vel - closest_moon_velocity;
-            (
-                closest_moon_distance - MOON_RADIUS,
-                "Moon",
-                true,
-                relative_velocity.length(),
-            )
-        } else {
-            // Earth is stationary in our reference frame, so Earth velocity is Vec3::ZERO
-            let relative_velocity = physics_object.vel; // Earth velocity is 0 in our coordinate system
-            (
-                distance_from_earth - EARTH_RADIUS,
-                "Earth",
-                false,
-                relative_velocity.length(),
-            )
-        };
+    // Altitude and speed relative to the body whose sphere of influence we are in
+    let central = physics_object
+        .central_body
+        .and_then(|entity| bodies.get(entity).ok());
+    let (altitude, relative_speed) = central.map_or((0., 0.), |(central, body)| {
+        (
+            body.altitude((physics_object.position - central.position).length() as f32),
+            (physics_object.vel - central.vel).length() as f32,
+        )
+    });
+    let central_body = central.map(|(_, body)| body);
+    let central_body_name = central_body.map_or("-", |body| body.name.as_str());
 
     // Objective status
     let objective_status = if objective_tracker.progress.all_completed() {
@@ -107,24 +157,45 @@ pub fn update_ui_system(
     } else {
         let current_obj = &objective_tracker.progress.current;
         let progress_info = match current_obj {
-            crate::components::objectives::Objective::EscapeMoon => if is_moon_central {
-                "Still in Moon's sphere of influence - gain more speed!"
-            } else {
-                "✓ Escaped Moon's gravity!"
-            }
-            .to_string(),
-            crate::components::objectives::Objective::OrbitEarth => {
-                let leo_min = LEO_MIN_ALTITUDE;
+            crate::components::objectives::Objective::EscapeMoon => match central_body {
+                Some(body) if body.is_moon() => {
+                    format!(
+                        "Still in {}'s sphere of influence - gain more speed!",
+                        body.name
+                    )
+                }
+                Some(body) => format!("✓ Escaped the moon's gravity, now orbiting {}!", body.name),
+                None => "✓ Escaped the moon's gravity!".to_string(),
+            },
+            crate::components::objectives::Objective::OrbitEarth {
+                min_apoapsis,
+                max_apoapsis,
+            } => {
+                let periapsis = orbit
+                    .periapsis_altitude()
+                    .map_or("-".to_string(), |alt| format!("{:.1}km", alt / 1000.0));
+                let apoapsis = orbit
+                    .apoapsis_altitude()
+                    .map_or("escape".to_string(), |alt| format!("{:.1}km", alt / 1000.0));
                 format!(
-                    "Alt {:.1}km (need >{:.1}km from Earth)",
-                    altitude / 1000.0,
-                    leo_min / 1000.0
+                    "Pe {} (need >{:.1}km), Ap {} (need {:.1}-{:.1}km)\nOrbit held: {:.0}°/{:.0}°",
+                    periapsis,
+                    LEO_MIN_ALTITUDE / 1000.0,
+                    apoapsis,
+                    min_apoapsis / 1000.0,
+                    max_apoapsis / 1000.0,
+                    objective_tracker.orbit_sweep,
+                    ORBIT_REQUIRED_SWEEP_DEGREES
                 )
             }
             crate::components::objectives::Objective::LandOnEarth => {
                 format!(
-                    "Alt {:.1}km from Earth - Get close to land!",
-                    altitude / 1000.0
+                    "Alt {:.1}km from {} - Touch down below {:.0} m/s vertical, {:.0} m/s horizontal, {:.0}° tilt",
+                    altitude / 1000.0,
+                    central_body_name,
+                    LANDING_SAFE_VERTICAL_SPEED,
+                    LANDING_SAFE_HORIZONTAL_SPEED,
+                    LANDING_SAFE_TILT_DEGREES
                 )
             }
         };
@@ -140,34 +211,503 @@ pub fn update_ui_system(
         }
     };
 
-    // Calculate time warp restrictions
-    let timewarp_status = if is_moon_central {
-        if altitude < 5000.0 {
-            "⚠ NO TIMEWARP (Too close to Moon)"
-        } else if altitude < 30000.0 {
-            "⚠ LIMITED TIMEWARP (Near Moon)"
-        } else {
-            "✓ Full timewarp available"
+    // Time warp restrictions of the body we are near
+    let timewarp_status = match central_body.map(|body| (body, body.max_warp_stage(altitude))) {
+        Some((body, 0)) => format!("⚠ NO TIMEWARP (Too close to {})", body.name),
+        Some((body, stage)) if stage < DT_STAGES.len() - 1 => {
+            format!("⚠ LIMITED TIMEWARP (Near {})", body.name)
         }
-    } else if altitude < 30000.0 {
-        "⚠ NO TIMEWARP (Too close to Earth)"
-    } else if altitude < 100000.0 {
-        "⚠ LIMITED TIMEWARP (Near Earth)"
-    } else {
-        "✓ Full timewarp available"
+        _ => "✓ Full timewarp available".to_string(),
     };
 
     **ui_text = format!(
-        "thrust: {:.1}%\nfuel: {:.1}kg ({:.1}%) {}\ntimewarp: {:.3}x\naltitude: {:.1}km (from {})\nspeed: {:.1} m/s (relative)\n{}\n\n{}",
+        "thrust: {:.1}%\nfuel: {:.1}kg ({:.1}%) {}\nΔv: {:.1} m/s  mass: {:.1}t  Isp: {:.0}s\n{}\n{}\n{}\n{}\ntimewarp: {:.3}x  integrator: {}{}\naltitude: {:.1}km (from {})\nspeed: {:.1} m/s (relative)\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n\n{}",
         thrust,
         propulsion.fuel,
         fuel_percentage,
         fuel_status,
+        propulsion.delta_v(),
+        propulsion.mass() / 1000.0,
+        propulsion.specific_impulse,
+        stages_readout(propulsion, stages),
+        attitude_readout(attitude),
+        flight_computer_readout(computer),
+        target_readout(physics_object, target, &bodies),
         config.time_multiplier,
+        if physics_object.on_rails {
+            "on rails"
+        } else {
+            config.integrator.label()
+        },
+        warp_to_readout(&warp_to, clock.elapsed),
         altitude / 1000.0,
         central_body_name,
         relative_speed,
+        reentry_readout(aero, destroyed),
+        landing_readout(contact, &names),
+        orbit_readout(orbit),
+        maneuver_readout(plan, clock.elapsed, propulsion),
+        transfer_readout(planner, &names),
+        prediction_readout(config.prediction_mode, prediction, clock.elapsed, &names),
         timewarp_status,
         objective_status
     );
 }
+
+/// Delta-v left in every stage, the firing one first
+fn stages_readout(propulsion: &Propulsion, stages: Option<&Stages>) -> String {
+    let Some(stages) = stages else {
+        return "stages: 1".to_string();
+    };
+
+    let delta_v = stages.delta_v(propulsion);
+    let per_stage = delta_v
+        .iter()
+        .enumerate()
+        .map(|(index, dv)| format!("S{} {:.0}", index + 1, dv))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    let hint = if stages.can_stage() {
+        "  [Space] stage"
+    } else {
+        ""
+    };
+    format!(
+        "stages Δv: {} m/s (total {:.0} m/s){}",
+        per_stage,
+        delta_v.iter().sum::<f32>(),
+        hint
+    )
+}
+
+/// Spin rate, SAS state and remaining RCS propellant
+fn attitude_readout(attitude: Option<&AttitudeControl>) -> String {
+    let Some(attitude) = attitude else {
+        return "spin: -".to_string();
+    };
+
+    let rcs_percentage = if attitude.max_monopropellant > 0.0 {
+        attitude.monopropellant / attitude.max_monopropellant * 100.0
+    } else {
+        0.0
+    };
+    let sas = if !attitude.sas {
+        "off".to_string()
+    } else if attitude.hold != AttitudeHold::Stability && attitude.hold_direction.is_none() {
+        format!("{} (no reference)", attitude.hold.label())
+    } else {
+        attitude.hold.label().to_string()
+    };
+    format!(
+        "spin: {:+.1}°/s  SAS: {}  RCS: {:.0}kg ({:.0}%)",
+        attitude.angular_velocity.to_degrees(),
+        sas,
+        attitude.monopropellant,
+        rcs_percentage
+    )
+}
+
+/// Program flying the vessel and what it is doing
+fn flight_computer_readout(computer: Option<&FlightComputer>) -> String {
+    let Some(computer) = computer else {
+        return "flight computer: -".to_string();
+    };
+
+    match &computer.program {
+        Some(program) if computer.finished => {
+            format!(
+                "flight computer: {} done - {}",
+                program.name(),
+                computer.phase
+            )
+        }
+        Some(program) => format!("flight computer: {} - {}", program.name(), computer.phase),
+        None if computer.mission => "flight computer: mission complete".to_string(),
+        None => "flight computer: off  [F] fly mission".to_string(),
+    }
+}
+
+/// Distance to the selected target and how fast it is closing
+fn target_readout(vessel: &PhysicsObject, target: Option<&Target>, bodies: &BodyQuery) -> String {
+    let Some((phys, body)) = target.and_then(|Target(entity)| bodies.get(*entity).ok()) else {
+        return "target: -".to_string();
+    };
+
+    let offset = (phys.position - vessel.position).as_vec3();
+    let closing_speed = (vessel.vel - phys.vel)
+        .as_vec3()
+        .dot(offset.normalize_or_zero());
+    format!(
+        "target: {}  distance {:.1}km  closing {:+.1} m/s",
+        body.name,
+        body.altitude(offset.length()) / 1000.0,
+        closing_speed
+    )
+}
+
+/// Hull temperature and g-load against the vessel's limits
+fn reentry_readout(aero: Option<&Aerodynamics>, destroyed: Option<&Destroyed>) -> String {
+    if let Some(Destroyed(cause)) = destroyed {
+        return format!("❌ VESSEL DESTROYED - {}", cause.label());
+    }
+    let Some(aero) = aero else {
+        return "hull: -".to_string();
+    };
+
+    let warning =
+        if aero.temperature > 0.8 * aero.max_temperature || aero.g_load > 0.8 * aero.max_g_load {
+            " ⚠ NEAR LIMIT"
+        } else {
+            ""
+        };
+    format!(
+        "hull: {:.0}K/{:.0}K  g-load: {:.1}g/{:.1}g{}",
+        aero.temperature, aero.max_temperature, aero.g_load, aero.max_g_load, warning
+    )
+}
+
+/// Outcome and measurements of the vessel's last touchdown
+fn landing_readout(contact: Option<&SurfaceContact>, names: &Query<&Name>) -> String {
+    let Some(touchdown) = contact.and_then(|contact| contact.last_touchdown) else {
+        return "touchdown: -".to_string();
+    };
+
+    let body = names
+        .get(touchdown.body)
+        .map_or("body".to_string(), |name| name.to_string());
+    format!(
+        "touchdown: {} on {} at {:.1} m/s (v {:.1}, h {:.1}, tilt {:.0}°)",
+        touchdown.outcome.label(),
+        body,
+        touchdown.impact_speed,
+        touchdown.vertical_speed,
+        touchdown.horizontal_speed,
+        touchdown.tilt.to_degrees()
+    )
+}
+
+/// Keplerian elements of the user's orbit around its central body
+fn orbit_readout(orbit: &Orbit) -> String {
+    let Some(elements) = orbit.elements else {
+        return "orbit: -".to_string();
+    };
+
+    let apoapsis = orbit
+        .apoapsis_altitude()
+        .map_or("escape".to_string(), |alt| format!("{:.1}km", alt / 1000.0));
+    let period = elements.period.map_or("-".to_string(), format_duration);
+
+    format!(
+        "orbit: Pe {:.1}km / Ap {}\necc: {:.3}  sma: {:.1}km  period: {}\narg. periapsis: {:.1}°  true anomaly: {:.1}°",
+        orbit.periapsis_altitude().unwrap_or_default() / 1000.0,
+        apoapsis,
+        elements.eccentricity,
+        elements.semi_major_axis / 1000.0,
+        period,
+        elements
+            .argument_of_periapsis
+            .to_degrees()
+            .rem_euclid(360.0),
+        elements.true_anomaly.to_degrees(),
+    )
+}
+
+/// Time to, delta-v and burn duration of the selected or next maneuver node
+fn maneuver_readout(plan: Option<&ManeuverPlan>, now: f64, propulsion: &Propulsion) -> String {
+    let Some(node) = plan.and_then(|plan| plan.selected_node().or(plan.nodes.first())) else {
+        return "maneuver: -".to_string();
+    };
+
+    let delta_v = node.delta_v().length();
+    let burn = propulsion
+        .burn_duration(delta_v)
+        .map_or("insufficient fuel".to_string(), format_duration);
+
+    format!(
+        "maneuver: T-{}  Δv {:.1} m/s (pro {:.1}, rad {:.1})  burn: {}",
+        format_duration((node.time - now).max(0.) as f32),
+        delta_v,
+        node.prograde,
+        node.radial,
+        burn,
+    )
+}
+
+/// Event a warp-to command is heading for and how far off it is
+fn warp_to_readout(warp_to: &WarpTo, now: f64) -> String {
+    if let Some(entry) = &warp_to.time_entry {
+        return format!("  warp to time: {entry}_ (Enter to warp, Esc to cancel)");
+    }
+    warp_to.target.map_or(String::new(), |target| {
+        format!(
+            "  warp to {} T-{}",
+            target.event.label(),
+            format_duration((target.time - now).max(0.) as f32)
+        )
+    })
+}
+
+/// Where the planned transfer leads, when it departs and what it costs
+fn transfer_readout(planner: Option<&TransferPlanner>, names: &Query<&Name>) -> String {
+    let Some((planner, goal, hohmann)) =
+        planner.and_then(|planner| Some((planner, planner.goal?, planner.hohmann.as_ref()?)))
+    else {
+        return "transfer: -".to_string();
+    };
+
+    let name = |entity: Entity| {
+        names
+            .get(entity)
+            .map_or("?".to_string(), |name| name.to_string())
+    };
+    let destination = match goal {
+        TransferGoal::Orbit { body, altitude } => {
+            format!("{} orbit {:.0}km", name(body), altitude / 1000.0)
+        }
+        TransferGoal::Intercept(body) => format!("intercept {}", name(body)),
+    };
+    let bi_elliptic = planner
+        .bi_elliptic
+        .as_ref()
+        .map_or(String::new(), |transfer| {
+            format!("  bi-elliptic Δv {:.1} m/s [B]", transfer.delta_v())
+        });
+
+    format!(
+        "transfer: {}  T-{}  Δv {:.1} m/s over {} [H]{}",
+        destination,
+        format_duration(hohmann.wait()),
+        hohmann.delta_v(),
+        format_duration(hohmann.duration),
+        bi_elliptic
+    )
+}
+
+/// Prediction mode and the next SOI change, impact or closest approach
+fn prediction_readout(
+    mode: PredictionMode,
+    prediction: Option<&TrajectoryPrediction>,
+    now: f64,
+    names: &Query<&Name>,
+) -> String {
+    let name = |entity: Entity| {
+        names
+            .get(entity)
+            .map_or("?".to_string(), |name| name.to_string())
+    };
+
+    let next = prediction
+        .and_then(|prediction| {
+            let elapsed = (now - prediction.epoch) as f32;
+            prediction
+                .events
+                .iter()
+                .find(|event| event.time >= elapsed)
+                .map(|event| (event, event.time - elapsed))
+        })
+        .map(|(event, time_until)| {
+            let what = match event.kind {
+                PathEventKind::SoiChange { to, .. } => format!("enter {}", name(to)),
+                PathEventKind::Collision { body } => format!("impact {}", name(body)),
+                PathEventKind::ClosestApproach { body, distance, .. } => {
+                    format!("closest {} {:.1}km", name(body), distance / 1000.0)
+                }
+            };
+            format!("  next: {} in {}", what, format_duration(time_until))
+        })
+        .unwrap_or_default();
+
+    format!("prediction: {}{}", mode.label(), next)
+}
+
+fn format_duration(seconds: f32) -> String {
+    let total = seconds as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m {seconds:02}s")
+    } else {
+        format!("{minutes}m {seconds:02}s")
+    }
+}
+
+/// Viewport rectangle of the conservation graph, in logical pixels from the top left
+const DIAGNOSTICS_GRAPH: Rect = Rect {
+    min: Vec2::new(10., 60.),
+    max: Vec2::new(410., 240.),
+};
+
+pub fn create_diagnostics_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("jbnf.ttf"),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(DIAGNOSTICS_GRAPH.max.y + 4.),
+            left: Val::Px(DIAGNOSTICS_GRAPH.min.x),
+            ..default()
+        },
+        Visibility::Hidden,
+        DiagnosticsUi,
+    ));
+}
+
+/// Draws the drift of energy and momentum since tracking started while the overlay is shown
+pub fn render_diagnostics_system(
+    diagnostics: Res<ConservationDiagnostics>,
+    mut overlay: Query<(&mut Text, &Visibility), With<DiagnosticsUi>>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((mut text, visibility)) = overlay.single_mut() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera.single() else {
+        return;
+    };
+    if visibility == Visibility::Hidden || diagnostics.baseline.is_none() {
+        return;
+    }
+
+    let series: [(DriftFn, Color); 3] = [
+        (
+            ConservationDiagnostics::energy_drift,
+            Color::srgb(1., 0.6, 0.2),
+        ),
+        (
+            ConservationDiagnostics::momentum_drift,
+            Color::srgb(0.4, 1., 0.4),
+        ),
+        (
+            ConservationDiagnostics::angular_momentum_drift,
+            Color::srgb(0.4, 0.8, 1.),
+        ),
+    ];
+    let scale = diagnostics
+        .history
+        .iter()
+        .flat_map(|sample| series.map(|(drift, _)| drift(&diagnostics, sample).abs()))
+        .fold(f64::EPSILON, f64::max);
+
+    let to_world = |viewport: Vec2| camera.viewport_to_world_2d(camera_transform, viewport).ok();
+    let graph = DIAGNOSTICS_GRAPH;
+    let frame = [
+        graph.min,
+        Vec2::new(graph.max.x, graph.min.y),
+        graph.max,
+        Vec2::new(graph.min.x, graph.max.y),
+        graph.min,
+    ];
+    gizmos.linestrip_2d(
+        frame.into_iter().filter_map(to_world),
+        Color::srgb(0.5, 0.5, 0.5),
+    );
+    gizmos.linestrip_2d(
+        [
+            Vec2::new(graph.min.x, graph.center().y),
+            Vec2::new(graph.max.x, graph.center().y),
+        ]
+        .into_iter()
+        .filter_map(to_world),
+        Color::srgb(0.3, 0.3, 0.3),
+    );
+
+    // Zero drift runs through the middle, +-scale reaches the edges
+    let step = graph.width() / DIAGNOSTICS_HISTORY.saturating_sub(1).max(1) as f32;
+    for (drift, color) in series {
+        gizmos.linestrip_2d(
+            diagnostics
+                .history
+                .iter()
+                .enumerate()
+                .filter_map(|(i, sample)| {
+                    let value = (drift(&diagnostics, sample) / scale) as f32;
+                    to_world(Vec2::new(
+                        graph.min.x + i as f32 * step,
+                        graph.center().y - value * graph.half_size().y,
+                    ))
+                }),
+            color,
+        );
+    }
+
+    let current = &diagnostics.current;
+    text.0 = format!(
+        "conservation (scale ±{:.1e})\nenergy drift: {:+.3e}  (E = {:.4e} J)\nmomentum drift: {:.3e}\nangular momentum drift: {:+.3e}",
+        scale,
+        diagnostics.energy_drift(current),
+        current.total_energy,
+        diagnostics.momentum_drift(current),
+        diagnostics.angular_momentum_drift(current),
+    );
+}
+
+/// Distance of the attitude indicators from the vessel, in screen pixels
+const INDICATOR_DISTANCE_PX: f32 = 36.;
+
+/// Marks every hold direction around the vessel, the held one ringed in white
+///
+/// Prograde and radial out are plain circles, their opposites are crossed out.
+pub fn render_attitude_indicators_system(
+    mut gizmos: Gizmos,
+    user: Query<
+        (
+            &Transform,
+            &PhysicsObject,
+            &AttitudeControl,
+            Option<&Target>,
+        ),
+        With<User>,
+    >,
+    bodies: Query<&PhysicsObject>,
+    camera: Query<&Projection, With<Camera2d>>,
+) {
+    let Some((transform, phys, attitude, target)) = user.iter().next() else {
+        return;
+    };
+    let cam_scale = camera
+        .iter()
+        .find_map(|proj| match proj {
+            Projection::Orthographic(ortho) => Some(ortho.scale),
+            _ => None,
+        })
+        .unwrap_or(1.0);
+
+    let center = transform.translation.truncate();
+    let central = phys.central_body.and_then(|entity| bodies.get(entity).ok());
+    let target = target.and_then(|Target(entity)| bodies.get(*entity).ok());
+    let radius = 5. * cam_scale;
+
+    let nose = transform.rotation.mul_vec3(Vec3::Y).truncate();
+    gizmos.line_2d(
+        center,
+        center + nose * INDICATOR_DISTANCE_PX * cam_scale,
+        Color::srgb(0.7, 0.7, 0.7),
+    );
+
+    for hold in AttitudeHold::DIRECTIONS {
+        let Some(direction) = hold_direction(hold, phys, central, target) else {
+            continue;
+        };
+        let marker = center + direction.truncate() * INDICATOR_DISTANCE_PX * cam_scale;
+        let color = match hold {
+            AttitudeHold::Prograde | AttitudeHold::Retrograde => Color::srgb(0.8, 1.0, 0.2),
+            AttitudeHold::RadialOut | AttitudeHold::RadialIn => Color::srgb(0.2, 0.9, 1.0),
+            _ => Color::srgb(1.0, 0.3, 0.9),
+        };
+
+        gizmos.circle_2d(marker, radius, color);
+        if matches!(hold, AttitudeHold::Retrograde | AttitudeHold::RadialIn) {
+            let diagonal = Vec2::splat(radius * std::f32::consts::FRAC_1_SQRT_2);
+            gizmos.line_2d(marker - diagonal, marker + diagonal, color);
+            gizmos.line_2d(marker + diagonal.perp(), marker - diagonal.perp(), color);
+        }
+        if attitude.sas && attitude.hold == hold {
+            gizmos.circle_2d(marker, radius * 1.6, Color::WHITE);
+        }
+    }
+}
@@ -1,165 +1,375 @@
-use crate::components::markers::User;
+use crate::components::attitude::{AttitudeControl, AttitudeHold};
+use crate::components::celestial_body::CelestialBody;
+use crate::components::flight_computer::FlightComputer;
+use crate::components::markers::{DiagnosticsUi, User};
 use crate::components::physics_object::PhysicsObject;
 use crate::components::propulsion::Propulsion;
+use crate::components::target::Target;
 use crate::config::Config;
-use crate::constants::{
-    EARTH_RADIUS, MOON_RADIUS, ROTATION_STEP_RADIANS, THRUST_ADJUSTMENT_STEP,
-    TIME_WARP_LIMITED_EARTH_ALTITUDE, TIME_WARP_LIMITED_MOON_ALTITUDE,
-    TIME_WARP_MIN_EARTH_ALTITUDE, TIME_WARP_MIN_MOON_ALTITUDE,
-};
-use bevy::input::ButtonState;
-use bevy::input::keyboard::{Key, KeyboardInput};
+use crate::constants::{DT_STAGES, THROTTLE_RAMP_RATE, THRUST_ADJUSTMENT_STEP, WARP_TO_RAMP_TIME};
+use crate::input::{ActionState, InputAction};
+use crate::persistence::{QUICKSAVE_SLOT, SAVE_SLOTS};
+use crate::systems::physics::MissionClock;
+use crate::systems::save_game::{LoadRequest, SaveRequest};
+use crate::systems::staging::StageRequest;
+use crate::systems::warp::WarpTo;
 use bevy::prelude::*;
 
 /// Handles player spacecraft rotation input
+///
+/// Holding the rotate actions or tilting the gamepad stick applies torque,
+/// toggling SAS switches it on or off.
 pub fn steering_system(
-    mut evr_kbd: EventReader<KeyboardInput>,
-    mut query: Query<&mut Transform, With<User>>,
+    actions: Res<ActionState>,
+    mut query: Query<&mut AttitudeControl, With<User>>,
 ) {
-    // Use iter_mut().next() for now to avoid deprecation warning
-    let Some(mut user_transform) = query.iter_mut().next() else {
+    let Some(mut control) = query.iter_mut().next() else {
         return; // No user entity found
     };
 
-    for ev in evr_kbd.read() {
-        if ev.state == ButtonState::Released {
-            continue;
+    let mut torque_input = actions.steering;
+    if actions.pressed(InputAction::RotateLeft) {
+        torque_input += 1.0;
+    }
+    if actions.pressed(InputAction::RotateRight) {
+        torque_input -= 1.0;
+    }
+    control.torque_input = torque_input.clamp(-1.0, 1.0);
+
+    if actions.just_pressed(InputAction::ToggleSas) {
+        control.sas = !control.sas;
+        info!("SAS: {}", if control.sas { "on" } else { "off" });
+    }
+}
+
+/// Bodies that can be selected as target, spacecraft and debris can't
+type TargetCandidates<'w, 's> = Query<'w, 's, (Entity, &'static CelestialBody)>;
+
+/// Picks the SAS hold mode (1-6) and cycles the target body (Tab)
+///
+/// Choosing a hold mode switches SAS on.
+pub fn autopilot_input_system(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    mut user: Query<(Entity, &mut AttitudeControl, Option<&Target>), With<User>>,
+    bodies: TargetCandidates,
+) {
+    let Some((entity, mut control, target)) = user.iter_mut().next() else {
+        return; // No user entity found
+    };
+
+    // The save modifier turns the same keys into save slots
+    if !actions.pressed(InputAction::SaveModifier) {
+        for hold in std::iter::once(AttitudeHold::Stability).chain(AttitudeHold::DIRECTIONS) {
+            if actions.just_pressed(InputAction::SasHold(hold)) {
+                control.hold = hold;
+                control.sas = true;
+                info!("SAS hold: {}", hold.label());
+            }
         }
+    }
+
+    if actions.just_pressed(InputAction::CycleTarget) {
+        // Spawn order is stable, cycling ends with no target selected
+        let mut candidates: Vec<(Entity, &CelestialBody)> = bodies.iter().collect();
+        candidates.sort_by_key(|(candidate, _)| *candidate);
+        let next = match target {
+            None => candidates.first(),
+            Some(Target(current)) => candidates
+                .iter()
+                .skip_while(|(candidate, _)| candidate != current)
+                .nth(1),
+        };
 
-        match &ev.logical_key {
-            Key::ArrowLeft => {
-                user_transform.rotation *= Quat::from_rotation_z(ROTATION_STEP_RADIANS)
+        match next {
+            Some((candidate, body)) => {
+                commands.entity(entity).insert(Target(*candidate));
+                info!("Target: {}", body.name);
             }
-            Key::ArrowRight => {
-                user_transform.rotation *= Quat::from_rotation_z(-ROTATION_STEP_RADIANS)
+            None => {
+                commands.entity(entity).remove::<Target>();
+                info!("Target cleared");
             }
-            _ => (),
         }
     }
 }
 
+/// Hands the mission to the flight computer (F), or takes it back
+///
+/// Taking the controls back cuts the engine.
+pub fn flight_computer_input_system(
+    actions: Res<ActionState>,
+    mut user: Query<(&mut FlightComputer, &mut Propulsion), With<User>>,
+) {
+    if !actions.just_pressed(InputAction::ToggleFlightComputer) {
+        return;
+    }
+    let Some((mut computer, mut propulsion)) = user.iter_mut().next() else {
+        return; // No user entity found
+    };
+
+    if computer.mission {
+        computer.disengage();
+        propulsion.thrust_percentage = 0.0;
+        info!("Flight computer: off");
+    } else {
+        computer.mission = true;
+        info!("Flight computer: mission mode");
+    }
+}
+
 /// Handles player thrust control input
+///
+/// The throttle steps with the arrow keys, ramps smoothly while Shift or Ctrl
+/// is held, jumps to full or cut with Z and X and follows the gamepad trigger
+/// whenever it moves. Shift also picks loading over saving, so the ramp
+/// pauses while a save or load is being keyed in.
 pub fn thrust_adjust_system(
-    mut evr_kbd: EventReader<KeyboardInput>,
+    time: Res<Time>,
+    actions: Res<ActionState>,
     mut query: Query<&mut Propulsion, With<User>>,
 ) {
     let Some(mut user_propulsion) = query.iter_mut().next() else {
         return; // No user entity found
     };
 
-    for ev in evr_kbd.read() {
-        if ev.state == ButtonState::Released {
-            continue;
-        }
+    let mut throttle = user_propulsion.thrust_percentage;
+    throttle += THRUST_ADJUSTMENT_STEP
+        * (actions.repeats(InputAction::ThrottleUp) as f32
+            - actions.repeats(InputAction::ThrottleDown) as f32);
 
-        match &ev.logical_key {
-            Key::ArrowUp => {
-                user_propulsion.thrust_percentage =
-                    (user_propulsion.thrust_percentage + THRUST_ADJUSTMENT_STEP).min(1.0);
-            }
-            Key::ArrowDown => {
-                user_propulsion.thrust_percentage =
-                    (user_propulsion.thrust_percentage - THRUST_ADJUSTMENT_STEP).max(0.0);
-            }
-            _ => (),
+    let mut ramp = 0.0;
+    if !actions.pressed(InputAction::SaveModifier) {
+        if actions.pressed(InputAction::ThrottleRampUp) {
+            ramp += 1.0;
+        }
+        if actions.pressed(InputAction::ThrottleRampDown) {
+            ramp -= 1.0;
         }
     }
+    throttle += ramp * THROTTLE_RAMP_RATE * time.delta_secs();
+
+    if let Some(analog) = actions.throttle {
+        throttle = analog;
+    }
+    if actions.just_pressed(InputAction::ThrottleFull) {
+        throttle = 1.0;
+    }
+    if actions.just_pressed(InputAction::ThrottleCut) {
+        throttle = 0.0;
+    }
+
+    let throttle = throttle.clamp(0.0, 1.0);
+    if throttle != user_propulsion.thrust_percentage {
+        user_propulsion.thrust_percentage = throttle;
+    }
+}
+
+/// Jettisons the player's firing stage (Space)
+pub fn staging_input_system(
+    actions: Res<ActionState>,
+    user: Query<Entity, With<User>>,
+    mut stage_requests: EventWriter<StageRequest>,
+) {
+    if actions.just_pressed(InputAction::Stage)
+        && let Some(entity) = user.iter().next()
+    {
+        stage_requests.write(StageRequest(entity));
+    }
 }
 
 /// Handles time warp controls and altitude-based restrictions
+///
+/// A warp-to command ramps the warp up one stage per frame while the event is
+/// far off, and back down so no stage runs closer than `WARP_TO_RAMP_TIME` real
+/// seconds to it. The physics halts on the event itself, where the warp drops
+/// to real time. Changing the warp by hand cancels it.
 pub fn time_warp_system(
-    mut evr_kbd: EventReader<KeyboardInput>,
+    actions: Res<ActionState>,
     mut config: ResMut<Config>,
     mut fixed_time: ResMut<Time<Fixed>>,
-    mut stage: Local<u8>,
-    user_query: Query<&Transform, With<User>>,
-    moon_query: Query<&Transform, (Without<User>, With<PhysicsObject>)>,
+    mut clock: ResMut<MissionClock>,
+    mut warp_to: ResMut<WarpTo>,
+    user_query: Query<&PhysicsObject, With<User>>,
+    bodies: Query<(&PhysicsObject, &CelestialBody)>,
 ) {
-    // (dt, timestep hertz, time multiplier)
-    const DT_STAGES: [(f32, f32, u32); 9] = [
-        (1. / 64., 64., 1),
-        (1. / 64., 64. * 5., 5),
-        (1. / 64. * 5., 64. * 5., 25),
-        (1. / 64. * 10., 64. * 10., 100),
-        (1. / 64. * 50., 64. * 10., 500),
-        (1. / 64. * 125., 64. * 20., 2500),
-        (1. / 64. * 500., 64. * 20., 10000),
-        (1. / 64. * 1250., 64. * 40., 50000),
-        (1. / 64. * 2500., 64. * 100., 250000),
-    ];
-
     // Get user position for altitude calculations
-    let Some(user_transform) = user_query.iter().next() else {
+    let Some(user_phys) = user_query.iter().next() else {
         return; // No user entity found
     };
 
-    let user_position = user_transform.translation;
+    // Each body sets its own warp limits by the altitude above its surface
+    let max_allowed_stage = user_phys
+        .central_body
+        .and_then(|entity| bodies.get(entity).ok())
+        .map_or(DT_STAGES.len() - 1, |(central, body)| {
+            let distance = (user_phys.position - central.position).length() as f32;
+            body.max_warp_stage(body.altitude(distance))
+        });
 
-    // Calculate altitude from Earth and Moon
-    let distance_from_earth = user_position.length();
-    let earth_altitude = distance_from_earth - EARTH_RADIUS;
+    let mut stage_changed = false;
+    let mut new_stage = config.warp_stage;
 
-    let mut closest_moon_distance = f32::INFINITY;
-    for moon_transform in moon_query.iter() {
-        let distance_to_moon = user_position.distance(moon_transform.translation);
-        if distance_to_moon < closest_moon_distance {
-            closest_moon_distance = distance_to_moon;
-        }
+    let manual = actions.repeats(InputAction::WarpDown) + actions.repeats(InputAction::WarpUp) > 0;
+    if manual && warp_to.target.take().is_some() {
+        info!("Warp-to cancelled");
     }
-    let moon_altitude = closest_moon_distance - MOON_RADIUS;
 
-    // Determine maximum allowed time warp stage based on altitude
-    let max_allowed_stage = if earth_altitude < TIME_WARP_MIN_EARTH_ALTITUDE
-        || moon_altitude < TIME_WARP_MIN_MOON_ALTITUDE
-    {
-        // No time warp allowed below minimum safe altitudes
-        0
-    } else if earth_altitude < TIME_WARP_LIMITED_EARTH_ALTITUDE
-        || moon_altitude < TIME_WARP_LIMITED_MOON_ALTITUDE
-    {
-        // Limited time warp below higher altitudes
-        3
-    } else {
-        // Full time warp allowed at high altitudes
-        (DT_STAGES.len() - 1) as u8
-    };
-
-    let mut stage_changed = false;
-    let mut new_stage = *stage;
-
-    for ev in evr_kbd.read() {
-        if ev.state == ButtonState::Released {
-            continue;
+    if let Some(target) = warp_to.target {
+        let remaining = target.time - clock.elapsed;
+        if remaining <= 0. {
+            warp_to.target = None;
+            new_stage = 0;
+            info!("Arrived at {}", target.event.label());
+        } else {
+            let fitting = DT_STAGES
+                .iter()
+                .rposition(|(_, _, multiplier)| {
+                    *multiplier as f64 * WARP_TO_RAMP_TIME as f64 <= remaining
+                })
+                .unwrap_or(0);
+            new_stage = fitting.min(config.warp_stage + 1).min(max_allowed_stage);
         }
+        stage_changed = new_stage != config.warp_stage;
+    }
+    clock.stop_at = warp_to.target.map(|target| target.time);
 
-        match &ev.key_code {
-            KeyCode::BracketLeft => {
-                if new_stage > 0 {
-                    new_stage -= 1;
-                    stage_changed = true;
-                }
-            }
-            KeyCode::BracketRight => {
-                if new_stage < max_allowed_stage {
-                    new_stage += 1;
-                    stage_changed = true;
-                }
-            }
-            _ => (),
+    for _ in 0..actions.repeats(InputAction::WarpDown) {
+        if new_stage > 0 {
+            new_stage -= 1;
+            stage_changed = true;
+        }
+    }
+    for _ in 0..actions.repeats(InputAction::WarpUp) {
+        if new_stage < max_allowed_stage {
+            new_stage += 1;
+            stage_changed = true;
         }
     }
 
     // Force stage down if we're too low
-    if *stage > max_allowed_stage {
+    if config.warp_stage > max_allowed_stage {
         new_stage = max_allowed_stage;
         stage_changed = true;
     }
 
     if stage_changed {
-        *stage = new_stage;
-        let (dt, timestep, multiplier) = DT_STAGES[*stage as usize];
-        config.dt = dt;
-        config.time_multiplier = multiplier;
-        *fixed_time = Time::<Fixed>::from_hz(timestep as f64);
+        config.set_warp_stage(new_stage, &mut fixed_time);
+    }
+}
+
+/// Handles quicksave/quickload and the numbered save slots
+///
+/// F5/F9 use the quicksave slot, Alt+1..4 saves into a numbered slot and
+/// Alt+Shift+1..4 loads it back.
+pub fn save_load_input_system(
+    actions: Res<ActionState>,
+    mut save_requests: EventWriter<SaveRequest>,
+    mut load_requests: EventWriter<LoadRequest>,
+) {
+    if actions.just_pressed(InputAction::Quicksave) {
+        save_requests.write(SaveRequest(QUICKSAVE_SLOT.to_string()));
+    }
+    if actions.just_pressed(InputAction::Quickload) {
+        load_requests.write(LoadRequest(QUICKSAVE_SLOT.to_string()));
+    }
+
+    if !actions.pressed(InputAction::SaveModifier) {
+        return;
+    }
+    let load = actions.pressed(InputAction::LoadModifier);
+
+    for index in 0..SAVE_SLOTS {
+        if actions.just_pressed(InputAction::Slot(index)) {
+            let slot = format!("slot{}", index + 1);
+            if load {
+                load_requests.write(LoadRequest(slot));
+            } else {
+                save_requests.write(SaveRequest(slot));
+            }
+        }
+    }
+}
+
+/// Switches between two-body and N-body trajectory prediction (P)
+pub fn prediction_mode_system(actions: Res<ActionState>, mut config: ResMut<Config>) {
+    if actions.just_pressed(InputAction::TogglePredictionMode) {
+        config.prediction_mode = config.prediction_mode.toggled();
+        info!("Trajectory prediction: {}", config.prediction_mode.label());
+    }
+}
+
+/// Cycles the numerical integrator used by physics and prediction (I)
+pub fn integrator_select_system(actions: Res<ActionState>, mut config: ResMut<Config>) {
+    if actions.just_pressed(InputAction::CycleIntegrator) {
+        config.integrator = config.integrator.next();
+        info!("Integrator: {}", config.integrator.label());
+    }
+}
+
+/// Shows or hides the conservation diagnostics overlay (G)
+pub fn diagnostics_toggle_system(
+    actions: Res<ActionState>,
+    mut overlay: Query<&mut Visibility, With<DiagnosticsUi>>,
+) {
+    if actions.just_pressed(InputAction::ToggleDiagnostics) {
+        for mut visibility in &mut overlay {
+            visibility.toggle_visible_hidden();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{InputMap, update_action_state_system};
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::input::keyboard::KeyboardInput;
+
+    /// Slots saved to and loaded from when `keys` go down together
+    fn slot_requests(keys: &[KeyCode]) -> (Vec<String>, Vec<String>) {
+        let mut world = World::new();
+        world.init_resource::<InputMap>();
+        world.init_resource::<ActionState>();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        world.init_resource::<Events<KeyboardInput>>();
+        world.init_resource::<Events<SaveRequest>>();
+        world.init_resource::<Events<LoadRequest>>();
+        let mut keyboard = ButtonInput::<KeyCode>::default();
+        for key in keys {
+            keyboard.press(*key);
+        }
+        world.insert_resource(keyboard);
+
+        world.run_system_once(update_action_state_system).unwrap();
+        world.run_system_once(save_load_input_system).unwrap();
+        let saves = world
+            .resource_mut::<Events<SaveRequest>>()
+            .drain()
+            .map(|SaveRequest(slot)| slot)
+            .collect();
+        let loads = world
+            .resource_mut::<Events<LoadRequest>>()
+            .drain()
+            .map(|LoadRequest(slot)| slot)
+            .collect();
+        (saves, loads)
+    }
+
+    #[test]
+    fn slot_keys_need_the_save_modifier() {
+        let none: Vec<String> = Vec::new();
+        assert_eq!(
+            slot_requests(&[KeyCode::Digit2]),
+            (none.clone(), none.clone())
+        );
+        assert_eq!(
+            slot_requests(&[KeyCode::AltLeft, KeyCode::Digit2]),
+            (vec!["slot2".to_string()], none.clone())
+        );
+        assert_eq!(
+            slot_requests(&[KeyCode::AltRight, KeyCode::ShiftLeft, KeyCode::Digit2]),
+            (none, vec!["slot2".to_string()])
+        );
     }
 }
@@ -0,0 +1,296 @@
+use crate::components::maneuver::ManeuverPlan;
+use crate::components::markers::User;
+use crate::components::orbit::Orbit;
+use crate::components::propulsion::Propulsion;
+use crate::components::trajectory_prediction::{PathEventKind, TrajectoryPrediction};
+use crate::config::Config;
+use crate::input::{ActionState, InputAction};
+use crate::systems::physics::{ContactEvent, MissionClock, SoiChangeEvent};
+use crate::systems::save_game::LoadRequest;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+/// Moment a warp-to command stops at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarpEvent {
+    Periapsis,
+    Apoapsis,
+    /// The predicted path enters another body's sphere of influence
+    SoiChange,
+    /// The predicted path hits a surface
+    Impact,
+    /// The start of the selected maneuver's burn
+    Maneuver,
+    /// A mission time typed in by the player
+    Time,
+}
+
+impl WarpEvent {
+    pub fn label(self) -> &'static str {
+        match self {
+            WarpEvent::Periapsis => "periapsis",
+            WarpEvent::Apoapsis => "apoapsis",
+            WarpEvent::SoiChange => "SOI change",
+            WarpEvent::Impact => "impact",
+            WarpEvent::Maneuver => "maneuver",
+            WarpEvent::Time => "time",
+        }
+    }
+}
+
+/// An event to warp to and when it happens
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WarpTarget {
+    pub event: WarpEvent,
+    /// Mission time in seconds the simulation stops at
+    pub time: f64,
+}
+
+/// The active warp-to command, `time_warp_system` ramps the warp towards it
+#[derive(Resource, Default)]
+pub struct WarpTo {
+    pub target: Option<WarpTarget>,
+    /// Mission time being typed in, `None` unless the time entry is open
+    pub time_entry: Option<String>,
+}
+
+type WarpQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Orbit,
+        &'static Propulsion,
+        Option<&'static TrajectoryPrediction>,
+        Option<&'static ManeuverPlan>,
+    ),
+    With<User>,
+>;
+
+/// Starts a warp to the next periapsis, apoapsis, SOI change, impact, maneuver or a typed time
+///
+/// SOI changes and impacts come from the trajectory prediction, the maneuver
+/// warp stops where the burn centred on the selected node has to start.
+/// The time entry takes the keyboard until Enter warps or Escape closes it.
+/// Loading a save drops the command, its event belongs to the old trajectory.
+pub fn warp_to_input_system(
+    mut actions: ResMut<ActionState>,
+    clock: Res<MissionClock>,
+    mut loads: EventReader<LoadRequest>,
+    mut keys: EventReader<KeyboardInput>,
+    mut warp_to: ResMut<WarpTo>,
+    user: WarpQuery,
+) {
+    if loads.read().count() > 0 {
+        warp_to.target = None;
+    }
+
+    if let Some(entry) = warp_to.time_entry.as_mut() {
+        let mut entered = None;
+        for event in keys
+            .read()
+            .filter(|event| event.state == ButtonState::Pressed)
+        {
+            match &event.logical_key {
+                Key::Enter => entered = Some(parse_mission_time(entry, clock.elapsed)),
+                Key::Escape => entered = Some(None),
+                Key::Backspace => {
+                    entry.pop();
+                }
+                Key::Character(text) => entry.extend(
+                    text.chars()
+                        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ':' | '+')),
+                ),
+                _ => {}
+            }
+        }
+        let Some(time) = entered else {
+            return;
+        };
+        warp_to.time_entry = None;
+        actions.text_entry = false;
+        if let Some(time) = time {
+            start_warp(&mut warp_to, WarpEvent::Time, Some(time), clock.elapsed);
+        }
+        return;
+    }
+    keys.clear();
+
+    if actions.just_pressed(InputAction::WarpToTime) {
+        warp_to.time_entry = Some(String::new());
+        actions.text_entry = true;
+        return;
+    }
+
+    let events = [
+        (InputAction::WarpToPeriapsis, WarpEvent::Periapsis),
+        (InputAction::WarpToApoapsis, WarpEvent::Apoapsis),
+        (InputAction::WarpToSoiChange, WarpEvent::SoiChange),
+        (InputAction::WarpToImpact, WarpEvent::Impact),
+        (InputAction::WarpToManeuver, WarpEvent::Maneuver),
+    ];
+    let Some(event) = events
+        .into_iter()
+        .find_map(|(action, event)| actions.just_pressed(action).then_some(event))
+    else {
+        return;
+    };
+    let Some((orbit, propulsion, prediction, plan)) = user.iter().next() else {
+        return; // No user entity found
+    };
+
+    let predicted = |matches: fn(&PathEventKind) -> bool| {
+        prediction.and_then(|prediction| {
+            let event = prediction
+                .events
+                .iter()
+                .find(|event| matches(&event.kind))?;
+            Some(prediction.epoch + event.time as f64)
+        })
+    };
+    let time = match event {
+        WarpEvent::Periapsis => orbit
+            .elements
+            .and_then(|elements| elements.time_to_true_anomaly(0.))
+            .map(|wait| clock.elapsed + wait as f64),
+        WarpEvent::Apoapsis => orbit
+            .elements
+            .filter(|elements| elements.is_closed())
+            .and_then(|elements| elements.time_to_true_anomaly(PI))
+            .map(|wait| clock.elapsed + wait as f64),
+        WarpEvent::SoiChange => predicted(|kind| matches!(kind, PathEventKind::SoiChange { .. })),
+        WarpEvent::Impact => predicted(|kind| matches!(kind, PathEventKind::Collision { .. })),
+        WarpEvent::Maneuver => plan
+            .and_then(|plan| plan.selected_node().or(plan.nodes.first()))
+            .map(|node| {
+                let burn = propulsion
+                    .burn_duration(node.delta_v().length())
+                    .unwrap_or(0.);
+                node.time - burn as f64 / 2.
+            }),
+        WarpEvent::Time => None,
+    };
+    start_warp(&mut warp_to, event, time, clock.elapsed);
+}
+
+/// Drops to real time when the player's vessel touches a body or changes sphere
+///
+/// A pending warp-to command is cancelled as well, its event was predicted
+/// from the trajectory that just ended.
+pub fn warp_interrupt_system(
+    mut contacts: EventReader<ContactEvent>,
+    mut soi_changes: EventReader<SoiChangeEvent>,
+    user: Query<Entity, With<User>>,
+    mut warp_to: ResMut<WarpTo>,
+    mut config: ResMut<Config>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+) {
+    let Some(vessel) = user.iter().next() else {
+        contacts.clear();
+        soi_changes.clear();
+        return; // No user entity found
+    };
+    let touched = contacts.read().any(|contact| contact.vessel == vessel);
+    let crossed = soi_changes.read().any(|change| change.entity == vessel);
+    if !(touched || crossed) {
+        return;
+    }
+
+    match warp_to.target.take() {
+        Some(target) if crossed && target.event == WarpEvent::SoiChange => {
+            info!("Arrived at {}", target.event.label());
+        }
+        Some(target) => info!("Warp to {} interrupted", target.event.label()),
+        None => {}
+    }
+    if config.warp_stage > 0 {
+        config.set_warp_stage(0, &mut fixed_time);
+    }
+}
+
+/// Sets the warp-to target when the event still lies ahead
+fn start_warp(warp_to: &mut WarpTo, event: WarpEvent, time: Option<f64>, now: f64) {
+    match time.filter(|time| *time > now) {
+        Some(time) => {
+            warp_to.target = Some(WarpTarget { event, time });
+            info!("Warping to {} in {:.0} s", event.label(), time - now);
+        }
+        None => info!("No {} ahead to warp to", event.label()),
+    }
+}
+
+/// Mission time in seconds from a typed entry like `5400`, `1:30:00` or `+90`
+///
+/// Colons split hours, minutes and seconds, a leading `+` counts from `now`.
+fn parse_mission_time(entry: &str, now: f64) -> Option<f64> {
+    let (offset, entry) = match entry.strip_prefix('+') {
+        Some(rest) => (now, rest),
+        None => (0., entry),
+    };
+    let seconds = entry.split(':').try_fold(0., |total: f64, part| {
+        Some(total * 60. + part.parse::<f64>().ok()?)
+    })?;
+    Some(offset + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    /// Warp stage and warp-to target left after the player's vessel, or some
+    /// other object, changes sphere mid-warp
+    fn cross_sphere(by_user: bool) -> (usize, Option<WarpTarget>) {
+        let mut world = World::new();
+        let mut config = Config::default();
+        let mut fixed_time = Time::<Fixed>::default();
+        config.set_warp_stage(5, &mut fixed_time);
+        world.insert_resource(config);
+        world.insert_resource(fixed_time);
+        world.insert_resource(WarpTo {
+            target: Some(WarpTarget {
+                event: WarpEvent::Periapsis,
+                time: 1.0e5,
+            }),
+            time_entry: None,
+        });
+        world.init_resource::<Events<ContactEvent>>();
+        world.init_resource::<Events<SoiChangeEvent>>();
+        let user = world.spawn(User).id();
+        let other = world.spawn_empty().id();
+
+        let entity = if by_user { user } else { other };
+        world.send_event(SoiChangeEvent {
+            entity,
+            from: None,
+            to: Some(Entity::PLACEHOLDER),
+        });
+        world.run_system_once(warp_interrupt_system).unwrap();
+        (
+            world.resource::<Config>().warp_stage,
+            world.resource::<WarpTo>().target,
+        )
+    }
+
+    #[test]
+    fn sphere_change_interrupts_the_warp() {
+        assert_eq!(cross_sphere(true), (0, None));
+
+        // Debris crossing over doesn't concern the player
+        let (stage, target) = cross_sphere(false);
+        assert_eq!(stage, 5);
+        assert!(target.is_some());
+    }
+
+    #[test]
+    fn parses_mission_times() {
+        assert_eq!(parse_mission_time("5400", 100.), Some(5400.));
+        assert_eq!(parse_mission_time("1:30:00", 100.), Some(5400.));
+        assert_eq!(parse_mission_time("2:30.5", 100.), Some(150.5));
+        assert_eq!(parse_mission_time("+90", 100.), Some(190.));
+        assert_eq!(parse_mission_time("", 100.), None);
+        assert_eq!(parse_mission_time("1::5", 100.), None);
+        assert_eq!(parse_mission_time("1+2", 100.), None);
+    }
+}
@@ -1,124 +1,207 @@
+use crate::components::appearance::Appearance;
+use crate::components::flight_computer::FlightComputer;
+use crate::components::landing::SurfaceContact;
+use crate::components::maneuver::ManeuverPlan;
 use crate::components::markers::User;
 use crate::components::object_bundle::ObjectBundle;
+use crate::components::objectives::ObjectiveProgress;
 use crate::components::physics_object::PhysicsObject;
-use crate::components::propulsion::Propulsion;
+use crate::components::staging::Stages;
 use crate::components::trajectory_prediction::TrajectoryPrediction;
-use crate::constants::{DISTANCE_SCALE, EARTH_RADIUS, MOON_RADIUS, PLANET_SCALE};
+use crate::components::transfer::TransferPlanner;
+use crate::constants::DISTANCE_SCALE;
+use crate::scenario::{ParentBody, Scenario, StageDefinition, speed_scale, srgb};
 use crate::systems::objectives::ObjectiveTracker;
+use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
-use bevy::render::mesh::Triangle2dMeshBuilder;
 
+/// Asset path of the scenario to load at startup
+#[derive(Resource)]
+pub struct ScenarioPath(pub String);
+
+/// The scenario the world is built from
+#[derive(Resource)]
+pub struct ActiveScenario {
+    pub path: String,
+    pub handle: Handle<Scenario>,
+    /// Whether the scenario's objects have been spawned already
+    pub spawned: bool,
+}
+
+/// Starts loading the scenario selected in the simulation plugin
+pub fn load_scenario(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scenario_path: Res<ScenarioPath>,
+) {
+    commands.insert_resource(ActiveScenario {
+        path: scenario_path.0.clone(),
+        handle: asset_server.load(scenario_path.0.clone()),
+        spawned: false,
+    });
+}
+
+/// Spawns the scenario's bodies and vessel once the asset has finished loading
 pub fn create_world(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut active: ResMut<ActiveScenario>,
+    scenarios: Res<Assets<Scenario>>,
+    asset_server: Res<AssetServer>,
 ) {
-    let earth_mass = 5.972e24 * PLANET_SCALE.powi(3);
+    if active.spawned {
+        return;
+    }
 
-    // Moon masses - variety of sizes
-    let luna_mass = 7.342e22 * PLANET_SCALE.powi(3); // Original large moon (Luna)
-    let europa_mass = 4.8e22 * PLANET_SCALE.powi(3); // Medium moon (Europa-like)
+    let Some(scenario) = scenarios.get(&active.handle) else {
+        if asset_server.load_state(&active.handle).is_failed() {
+            error!("Failed to load scenario {}", active.path);
+            active.spawned = true;
+        }
+        return;
+    };
 
-    let user_mass: f32 = 589_000.;
+    spawn_scenario(&mut commands, scenario);
+    active.spawned = true;
+}
 
-    // Moon positions - spread around Earth for interesting dynamics
-    let luna_pos = [384_400_000. * DISTANCE_SCALE, 0., 0.].into(); // Traditional moon distance
-    let europa_pos = [-280_000_000. * DISTANCE_SCALE, 0., 0.].into(); // Closer, opposite side
+fn spawn_scenario(commands: &mut Commands, scenario: &Scenario) {
+    let speed_scale = speed_scale();
 
-    // Calculate velocities for stable orbits
-    let luna_speed = 1_022. * (PLANET_SCALE.powi(3) / DISTANCE_SCALE).sqrt();
-    let europa_speed = 1_080. * (PLANET_SCALE.powi(3) / DISTANCE_SCALE).sqrt(); // Faster for closer orbit
+    // Resolve every body relative to its already resolved parent
+    let mut parents: HashMap<&str, (usize, ParentBody)> = HashMap::default();
+    let mut resolved = Vec::with_capacity(scenario.bodies.len());
 
-    // Moon velocities
-    let luna_vel = [0., luna_speed, 0.].into();
-    let europa_vel = [0., -europa_speed, 0.].into();
+    for (index, body) in scenario.bodies.iter().enumerate() {
+        let parent = body.parent.as_deref().and_then(|name| {
+            let parent = parents.get(name);
+            if parent.is_none() {
+                warn!("Body {} references unknown parent {}", body.name, name);
+            }
+            parent
+        });
 
-    let user_vel: Vec3 = [0., 0., 0.].into();
+        let state = body.initial.resolve(
+            parent.map(|(_, p)| p),
+            body.scaled_mass(),
+            DISTANCE_SCALE,
+            speed_scale,
+        );
 
-    // Total momentum cancellation for Earth
-    let total_momentum: Vec3 =
-        luna_vel * luna_mass + europa_vel * europa_mass + user_vel * user_mass;
-    let earth_vel = -total_momentum / earth_mass;
+        resolved.push((state, parent.map(|(i, _)| *i)));
+        parents.insert(
+            &body.name,
+            (
+                index,
+                ParentBody {
+                    mass: body.scaled_mass(),
+                    radius: body.scaled_radius(),
+                    state,
+                },
+            ),
+        );
+    }
 
-    // Prepare mesh/material handles with different colors
-    let earth_mesh = Mesh2d(meshes.add(Circle::new(EARTH_RADIUS)));
-    let earth_material = MeshMaterial2d(materials.add(Color::srgb(0.2, 0.6, 1.0))); // Blue Earth
+    // Total momentum cancellation, the vessel is too light to matter
+    if let Some(balanced) = scenario.bodies.iter().position(|b| b.balance_momentum) {
+        let total_momentum: Vec3 = scenario
+            .bodies
+            .iter()
+            .zip(&resolved)
+            .enumerate()
+            .filter(|(index, _)| *index != balanced)
+            .map(|(_, (body, (state, _)))| state.velocity * body.scaled_mass())
+            .sum();
+        resolved[balanced].0.velocity = -total_momentum / scenario.bodies[balanced].scaled_mass();
+    }
 
-    let luna_mesh = Mesh2d(meshes.add(Circle::new(MOON_RADIUS)));
-    let luna_material = MeshMaterial2d(materials.add(Color::srgb(0.9, 0.9, 0.8))); // Pale gray Luna
+    let mut entities = Vec::with_capacity(scenario.bodies.len());
+    for (body, (state, parent)) in scenario.bodies.iter().zip(&resolved) {
+        let parent = parent.map(|index| entities[index]);
+        let mut entity = commands.spawn((
+            ObjectBundle {
+                transform: Transform::from_translation(state.position),
+                physics_object: PhysicsObject::new(
+                    body.scaled_mass(),
+                    body.scaled_radius(),
+                    state.position.as_dvec3(),
+                    state.velocity.as_dvec3(),
+                    parent,
+                ),
+                appearance: Appearance::circle(body.scaled_radius(), srgb(body.color)),
+            },
+            Name::new(body.name.clone()),
+            body.celestial_body(parent),
+        ));
 
-    let europa_mesh = Mesh2d(meshes.add(Circle::new(MOON_RADIUS * 0.8)));
-    let europa_material = MeshMaterial2d(materials.add(Color::srgb(0.8, 0.9, 1.0))); // Pale blue Europa
+        if body.predict_trajectory {
+            entity.insert(TrajectoryPrediction::new());
+        }
+        if let Some(atmosphere) = body.scaled_atmosphere() {
+            entity.insert(atmosphere);
+        }
 
-    // Build Earth
-    let earth = commands
-        .spawn((ObjectBundle {
-            transform: Transform::default(),
-            physics_object: PhysicsObject::new(earth_mass, EARTH_RADIUS, earth_vel, None),
-            mesh2d: earth_mesh.clone(),
-            mesh_material: earth_material.clone(),
-        },))
-        .id();
+        entities.push(entity.id());
+    }
 
-    // Luna - The traditional large moon (gray)
-    let luna = commands
-        .spawn((
-            ObjectBundle {
-                transform: Transform {
-                    translation: luna_pos,
-                    ..default()
-                },
-                physics_object: PhysicsObject::new(luna_mass, MOON_RADIUS, luna_vel, Some(earth)),
-                mesh2d: luna_mesh.clone(),
-                mesh_material: luna_material.clone(),
-            },
-            TrajectoryPrediction::new(),
-        ))
-        .id();
+    let vessel = &scenario.vessel;
+    let Some((parent_index, parent)) = parents.get(vessel.parent.as_str()) else {
+        error!("Vessel references unknown body {}", vessel.parent);
+        return;
+    };
+
+    // Use the parent's final velocity in case it was momentum balanced
+    let parent = ParentBody {
+        state: resolved[*parent_index].0,
+        ..*parent
+    };
+    let Some((propulsion, stages)) = Stages::assemble(
+        vessel
+            .stages
+            .iter()
+            .map(StageDefinition::to_stage)
+            .collect(),
+    ) else {
+        error!("Vessel has no stages");
+        return;
+    };
+    let state = vessel.initial.resolve(
+        Some(&parent),
+        propulsion.mass(),
+        DISTANCE_SCALE,
+        speed_scale,
+    );
 
-    // Europa - Medium blue moon
-    commands.spawn((
+    let mut user = commands.spawn((
         ObjectBundle {
-            transform: Transform {
-                translation: europa_pos,
-                ..default()
-            },
+            transform: Transform::from_translation(state.position),
             physics_object: PhysicsObject::new(
-                europa_mass,
-                MOON_RADIUS * 0.8,
-                europa_vel,
-                Some(earth),
+                propulsion.mass(),
+                vessel.radius,
+                state.position.as_dvec3(),
+                state.velocity.as_dvec3(),
+                Some(entities[*parent_index]),
+            ),
+            appearance: Appearance::triangle(
+                [Vec2::new(0., 12.), Vec2::new(-8., -8.), Vec2::new(8., -8.)],
+                srgb(vessel.color),
             ),
-            mesh2d: europa_mesh.clone(),
-            mesh_material: europa_material.clone(),
-        },
-        TrajectoryPrediction::new(),
-    ));
-
-    // User spacecraft (Green triangle) - spawn on Moon's surface
-    commands.spawn((
-        ObjectBundle {
-            transform: Transform {
-                translation: luna_pos + Vec3::new(0., MOON_RADIUS, 0.),
-                ..default()
-            },
-            physics_object: PhysicsObject::new(user_mass, 8.0, luna_vel, Some(luna)),
-            mesh2d: Mesh2d(meshes.add(Triangle2dMeshBuilder::new(
-                Vec2::new(0., 12.),
-                Vec2::new(-8., -8.),
-                Vec2::new(8., -8.),
-            ))),
-            mesh_material: MeshMaterial2d(materials.add(Color::srgb(0., 1., 0.))),
         },
+        Name::new("Vessel"),
         User,
-        Propulsion {
-            max_thrust: 1_688_000.,
-            thrust_percentage: 0.0,
-            fuel: 50_000.0,              // 50,000 kg of fuel (enough for missions)
-            max_fuel: 50_000.0,          // 50,000 kg capacity
-            fuel_consumption_rate: 50.0, // 50 kg/s at full thrust (much more reasonable)
-        },
+        propulsion,
+        stages,
         TrajectoryPrediction::new(),
-        ObjectiveTracker::default(),
+        ManeuverPlan::default(),
+        vessel.aerodynamics.to_component(),
+        vessel.attitude_control.to_component(),
+        SurfaceContact::default(),
+        FlightComputer::default(),
+        TransferPlanner::default(),
     ));
+
+    if let Some(progress) = ObjectiveProgress::new(scenario.objectives.clone()) {
+        user.insert(ObjectiveTracker::new(progress));
+    }
+
+    info!("Loaded scenario {}", scenario.name);
 }
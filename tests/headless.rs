@@ -0,0 +1,361 @@
+//! Runs the windowless simulation with the default scenario, as CI does
+
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use cepwa3_bevy::SimulationPlugin;
+use cepwa3_bevy::components::attitude::{AttitudeControl, AttitudeHold};
+use cepwa3_bevy::components::celestial_body::CelestialBody;
+use cepwa3_bevy::components::damage::Destroyed;
+use cepwa3_bevy::components::flight_computer::FlightComputer;
+use cepwa3_bevy::components::markers::User;
+use cepwa3_bevy::components::objectives::Objective;
+use cepwa3_bevy::components::orbit::Orbit;
+use cepwa3_bevy::components::physics_object::PhysicsObject;
+use cepwa3_bevy::components::propulsion::Propulsion;
+use cepwa3_bevy::components::staging::Stages;
+use cepwa3_bevy::config::Config;
+use cepwa3_bevy::constants::DT_STAGES;
+use cepwa3_bevy::systems::objectives::ObjectiveTracker;
+use cepwa3_bevy::systems::physics::MissionClock;
+use cepwa3_bevy::systems::save_game::{LoadRequest, SaveRequest};
+use std::f32::consts::PI;
+use std::time::Duration;
+
+/// Fixed steps in one second of mission time at real-time warp
+const STEPS_PER_SECOND: usize = 64;
+
+/// Builds the headless app and waits for the scenario to spawn the vessel
+///
+/// Virtual time is paused so frames never run fixed steps of their own, the
+/// tests advance `FixedUpdate` themselves.
+fn spawn_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        SimulationPlugin::default(),
+    ));
+    app.finish();
+    app.cleanup();
+    app.world_mut().resource_mut::<Time<Virtual>>().pause();
+
+    for _ in 0..500 {
+        app.update();
+        if vessel(&mut app).is_some() {
+            return app;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("the scenario did not spawn a vessel");
+}
+
+fn vessel(app: &mut App) -> Option<(Entity, PhysicsObject, Propulsion)> {
+    let world = app.world_mut();
+    let mut query = world.query_filtered::<(Entity, &PhysicsObject, &Propulsion), With<User>>();
+    query
+        .iter(world)
+        .next()
+        .map(|(entity, phys, propulsion)| (entity, phys.clone(), propulsion.clone()))
+}
+
+/// The vessel's state and that of the body it starts on
+fn states(app: &mut App) -> (PhysicsObject, Propulsion, PhysicsObject) {
+    let (_, phys, propulsion) = vessel(app).expect("vessel despawned");
+    let central = phys.central_body.expect("vessel has no central body");
+    let body = app
+        .world()
+        .get::<PhysicsObject>(central)
+        .expect("central body despawned")
+        .clone();
+    (phys, propulsion, body)
+}
+
+fn step(app: &mut App, steps: usize) {
+    for _ in 0..steps {
+        app.world_mut().run_schedule(FixedUpdate);
+    }
+}
+
+/// Warps as fast as the central body allows while the engine is off, as a player would
+fn warp_while_coasting(app: &mut App) {
+    let (vessel, propulsion, body) = states(app);
+    if propulsion.thrust_percentage > 0. {
+        return;
+    }
+    let central = vessel.central_body.expect("vessel has no central body");
+    let Some(celestial) = app.world().get::<CelestialBody>(central) else {
+        return;
+    };
+    let altitude = celestial.altitude((vessel.position - body.position).length() as f32);
+    set_warp_stage(app, celestial.max_warp_stage(altitude));
+}
+
+fn set_warp_stage(app: &mut App, stage: usize) {
+    app.world_mut()
+        .resource_scope(|world, mut config: Mut<Config>| {
+            config.set_warp_stage(stage, &mut world.resource_mut::<Time<Fixed>>());
+        });
+}
+
+fn set_throttle(app: &mut App, throttle: f32) {
+    let (entity, ..) = vessel(app).expect("vessel despawned");
+    app.world_mut()
+        .get_mut::<Propulsion>(entity)
+        .unwrap()
+        .thrust_percentage = throttle;
+}
+
+#[test]
+fn mission_clock_follows_fixed_steps() {
+    let mut app = spawn_app();
+    assert_eq!(app.world().resource::<MissionClock>().elapsed, 0.);
+
+    step(&mut app, 3 * STEPS_PER_SECOND);
+    assert_eq!(app.world().resource::<MissionClock>().elapsed, 3.);
+}
+
+#[test]
+fn vessel_rests_on_its_starting_body() {
+    let mut app = spawn_app();
+    step(&mut app, 2 * STEPS_PER_SECOND);
+
+    let (vessel, propulsion, body) = states(&mut app);
+    let offset = vessel.position - body.position;
+    let altitude = offset.length() - body.radius as f64;
+    assert!(
+        altitude.abs() < vessel.radius as f64 + 1.,
+        "altitude {altitude} m"
+    );
+    assert!((vessel.vel - body.vel).length() < 0.1);
+    assert_eq!(propulsion.fuel, propulsion.max_fuel);
+}
+
+#[test]
+fn burn_lifts_off_and_uses_fuel() {
+    let mut app = spawn_app();
+    let (start, start_propulsion, _) = states(&mut app);
+
+    set_throttle(&mut app, 1.);
+    step(&mut app, STEPS_PER_SECOND);
+    set_throttle(&mut app, 0.);
+
+    let (vessel, propulsion, body) = states(&mut app);
+    let used = start_propulsion.fuel - propulsion.fuel;
+    assert!(
+        (used - start_propulsion.fuel_consumption_rate()).abs() < 0.01,
+        "used {used} kg"
+    );
+
+    // The vessel starts upright, so the burn climbs straight away from the surface
+    let offset = vessel.position - body.position;
+    let radial_speed = (vessel.vel - body.vel).dot(offset.normalize());
+    let acceleration = start_propulsion.max_thrust / start.mass;
+    assert!(
+        radial_speed > 0.8 * acceleration as f64,
+        "climbing at {radial_speed} m/s"
+    );
+    assert!(
+        radial_speed < acceleration as f64,
+        "climbing at {radial_speed} m/s"
+    );
+}
+
+#[test]
+fn runs_are_deterministic() {
+    let run = || {
+        let mut app = spawn_app();
+        set_throttle(&mut app, 0.5);
+        step(&mut app, 2 * STEPS_PER_SECOND);
+        let (vessel, propulsion, _) = states(&mut app);
+        (vessel.position, vessel.vel, propulsion.fuel)
+    };
+
+    let first: (DVec3, DVec3, f32) = run();
+    assert_eq!(first, run());
+}
+
+#[test]
+fn warped_burn_reaches_the_same_orbit() {
+    let top = DT_STAGES.len() - 1;
+    // The top stage covers this many unwarped steps in one
+    let steps = (DT_STAGES[top].0 / DT_STAGES[0].0) as usize;
+
+    let burn = |stage: usize, steps: usize| {
+        let mut app = spawn_app();
+        let (entity, ..) = vessel(&mut app).expect("vessel despawned");
+        // Settle on the surface first, tipping over on touchdown would be a crash
+        step(&mut app, STEPS_PER_SECOND);
+        // Tilted off the vertical, so the burn ends up on a proper orbit
+        app.world_mut()
+            .get_mut::<Transform>(entity)
+            .unwrap()
+            .rotate_z(0.25 * PI);
+        set_warp_stage(&mut app, stage);
+        set_throttle(&mut app, 1.);
+        step(&mut app, steps);
+
+        let clock = app.world().resource::<MissionClock>().elapsed;
+        let orbit = app.world().get::<Orbit>(entity).unwrap().clone();
+        (clock, orbit.elements.expect("no orbit after the burn"))
+    };
+
+    let (unwarped_time, unwarped) = burn(0, steps);
+    let (warped_time, warped) = burn(top, 1);
+    assert_eq!(unwarped_time, warped_time);
+
+    let relative = |a: f32, b: f32| ((a - b) / b).abs();
+    assert!(
+        relative(warped.semi_major_axis, unwarped.semi_major_axis) < 1e-4,
+        "semi-major axis {} m warped, {} m unwarped",
+        warped.semi_major_axis,
+        unwarped.semi_major_axis
+    );
+    assert!(
+        (warped.eccentricity - unwarped.eccentricity).abs() < 1e-4,
+        "eccentricity {} warped, {} unwarped",
+        warped.eccentricity,
+        unwarped.eccentricity
+    );
+    assert!(
+        relative(warped.periapsis, unwarped.periapsis) < 1e-4,
+        "periapsis {} m warped, {} m unwarped",
+        warped.periapsis,
+        unwarped.periapsis
+    );
+}
+
+/// Vessel state a save has to carry, with the central body by name
+fn saved_state(
+    app: &mut App,
+) -> (
+    Transform,
+    PhysicsObject,
+    Propulsion,
+    Stages,
+    AttitudeControl,
+    String,
+) {
+    let (entity, phys, propulsion) = vessel(app).expect("vessel despawned");
+    let world = app.world();
+    let transform = world.get::<Transform>(entity).unwrap();
+    let stages = world.get::<Stages>(entity).expect("vessel has no stages");
+    let control = world
+        .get::<AttitudeControl>(entity)
+        .expect("vessel has no SAS");
+    let central = phys.central_body.expect("vessel has no central body");
+    let name = world
+        .get::<Name>(central)
+        .expect("central body has no name");
+    (
+        *transform,
+        phys,
+        propulsion,
+        stages.clone(),
+        control.clone(),
+        name.to_string(),
+    )
+}
+
+#[test]
+fn saves_load_into_a_fresh_app() {
+    const SLOT: &str = "headless_round_trip";
+
+    let mut original = spawn_app();
+    let (entity, ..) = vessel(&mut original).expect("vessel despawned");
+    set_throttle(&mut original, 0.5);
+    step(&mut original, STEPS_PER_SECOND);
+    original
+        .world_mut()
+        .get_mut::<Transform>(entity)
+        .unwrap()
+        .rotate_z(0.1);
+    {
+        let mut control = original
+            .world_mut()
+            .get_mut::<AttitudeControl>(entity)
+            .unwrap();
+        control.angular_velocity = 0.1;
+        control.hold = AttitudeHold::Prograde;
+        control.monopropellant /= 2.;
+    }
+    original
+        .world_mut()
+        .send_event(SaveRequest(SLOT.to_string()));
+    original.update();
+
+    let mut restored = spawn_app();
+    restored
+        .world_mut()
+        .send_event(LoadRequest(SLOT.to_string()));
+    restored.update();
+    std::fs::remove_file(format!("saves/{SLOT}.save.ron")).expect("save was not written");
+
+    let clock = |app: &App| app.world().resource::<MissionClock>().elapsed;
+    assert_eq!(clock(&original), clock(&restored));
+
+    let (transform, phys, propulsion, stages, control, central) = saved_state(&mut original);
+    let (
+        loaded_transform,
+        loaded_phys,
+        loaded_propulsion,
+        loaded_stages,
+        loaded_control,
+        loaded_central,
+    ) = saved_state(&mut restored);
+
+    assert_eq!(phys.position, loaded_phys.position);
+    assert_eq!(transform.rotation, loaded_transform.rotation);
+    assert_eq!(phys.vel, loaded_phys.vel);
+    assert_eq!(phys.mass, loaded_phys.mass);
+    assert_eq!(phys.radius, loaded_phys.radius);
+    assert_eq!(central, loaded_central);
+
+    assert_eq!(propulsion.fuel, loaded_propulsion.fuel);
+    assert_eq!(propulsion.max_fuel, loaded_propulsion.max_fuel);
+    assert_eq!(propulsion.max_thrust, loaded_propulsion.max_thrust);
+    assert_eq!(
+        propulsion.thrust_percentage,
+        loaded_propulsion.thrust_percentage
+    );
+    assert_eq!(
+        propulsion.specific_impulse,
+        loaded_propulsion.specific_impulse
+    );
+    assert_eq!(propulsion.dry_mass, loaded_propulsion.dry_mass);
+    assert_eq!(stages.active_dry_mass, loaded_stages.active_dry_mass);
+    assert_eq!(stages.upcoming.len(), loaded_stages.upcoming.len());
+    for (stage, loaded) in stages.upcoming.iter().zip(&loaded_stages.upcoming) {
+        assert_eq!(stage.dry_mass, loaded.dry_mass);
+        assert_eq!(stage.fuel, loaded.fuel);
+        assert_eq!(stage.max_thrust, loaded.max_thrust);
+    }
+    assert_eq!(control.angular_velocity, loaded_control.angular_velocity);
+    assert_eq!(control.monopropellant, loaded_control.monopropellant);
+    assert_eq!(control.sas, loaded_control.sas);
+    assert_eq!(control.hold, loaded_control.hold);
+}
+
+#[test]
+fn mission_computer_escapes_the_moon() {
+    let mut app = spawn_app();
+    let (entity, ..) = vessel(&mut app).expect("vessel despawned");
+    app.world_mut()
+        .get_mut::<FlightComputer>(entity)
+        .unwrap()
+        .mission = true;
+
+    for _ in 0..100_000 {
+        let tracker = app.world().get::<ObjectiveTracker>(entity).unwrap();
+        if tracker.progress.completed.contains(&Objective::EscapeMoon) {
+            return;
+        }
+        assert!(app.world().get::<Destroyed>(entity).is_none());
+
+        // Fixed steps of one frame, then the frame applies the program's warp requests
+        warp_while_coasting(&mut app);
+        step(&mut app, 5);
+        app.update();
+    }
+    let clock = app.world().resource::<MissionClock>();
+    panic!("still on the moon after {:.0} s", clock.elapsed);
+}